@@ -0,0 +1,430 @@
+use crate::batch::BuildError;
+use crate::links::is_external;
+use crate::options::ImageSizes;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::Url;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// what an image hook made of an image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessedImage {
+    // replaces the image's url in the document, relative to it like the one it had
+    pub url: String,
+    // the width and height of the image at `url`, for its `<img>`
+    pub size: Option<(u32, u32)>,
+}
+
+// runs over every local image a build copies, to resize or convert it with the
+// `image` crate or anything else. it's handed the image's file and where prose
+// would copy it, and writes whatever files it likes into the output. handing
+// back none leaves the image to be copied as usual
+#[derive(Clone, Default)]
+pub struct ImageHook(Option<ImageProcessor>);
+
+pub type ImageProcessor =
+    Arc<dyn Fn(&Path, &Path) -> io::Result<Option<ProcessedImage>> + Send + Sync>;
+
+impl ImageHook {
+    pub fn new(
+        hook: impl Fn(&Path, &Path) -> io::Result<Option<ProcessedImage>> + Send + Sync + 'static,
+    ) -> ImageHook {
+        ImageHook(Some(Arc::new(hook)))
+    }
+
+    fn process(&self, source: &Path, dest: &Path) -> io::Result<Option<ProcessedImage>> {
+        match &self.0 {
+            Some(hook) => hook(source, dest),
+            None => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for ImageHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ImageHook({})",
+            if self.0.is_some() { "set" } else { "none" }
+        )
+    }
+}
+
+// the images a document's build copied and made
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CopiedImages {
+    // relative to the output directory, leaving out what the hook wrote
+    pub files: Vec<PathBuf>,
+    // for the urls the hook handed back sizes with
+    pub sizes: ImageSizes,
+}
+
+// where a local url points, relative to the content directory. `document` is the
+// markdown file's own path relative to the content directory, urls starting with
+// `/` are taken from the top. anything climbing out of the content directory is ignored
+pub fn resolve(document: &Path, url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    if path.is_empty() || is_external(url) {
+        return None;
+    }
+    let (mut resolved, path) = match path.strip_prefix('/') {
+        Some(path) => (PathBuf::new(), path),
+        None => (
+            document.parent().map(Path::to_path_buf).unwrap_or_default(),
+            path,
+        ),
+    };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            part => resolved.push(part),
+        }
+    }
+    Some(resolved)
+}
+
+// copies every local image the document uses from `src` into `out`, at the same
+// relative location. with `hash` set the copies are named after their contents so
+// they can be cached forever, and the urls in `blocks` are rewritten to match.
+// images the hook takes care of are pointed wherever it says instead
+pub fn copy_images(
+    blocks: &mut [Markdown],
+    src: &Path,
+    out: &Path,
+    document: &Path,
+    hash: bool,
+    hook: &ImageHook,
+) -> Result<CopiedImages, BuildError> {
+    let mut copied = CopiedImages::default();
+    for text in blocks.iter_mut().flat_map(Markdown::texts_mut) {
+        for inline in text.iter_mut() {
+            let url = match inline {
+                MarkdownInline::Image(_, url) => url,
+                _ => continue,
+            };
+            let relative = match resolve(document, &url.decoded()) {
+                Some(relative) => relative,
+                None => continue,
+            };
+            if !src.join(&relative).is_file() {
+                tracing::warn!(path = %document.display(), "image {} does not exist", url);
+                continue;
+            }
+            if let Some(parent) = out.join(&relative).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Some(processed) = hook.process(&src.join(&relative), &out.join(&relative))? {
+                *url = Url::from(processed.url);
+                if let Some(size) = processed.size {
+                    copied.sizes.insert(url.as_str().to_string(), size);
+                }
+                continue;
+            }
+            let contents = fs::read(src.join(&relative))?;
+            let dest = if hash {
+                let hashed = hashed_name(&relative, &contents);
+                *url = Url::from(rename(url, &hashed));
+                relative.with_file_name(hashed)
+            } else {
+                relative
+            };
+            if let Some(parent) = out.join(&dest).parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(out.join(&dest), contents)?;
+            copied.files.push(dest);
+        }
+    }
+    Ok(copied)
+}
+
+// swaps every image in the document for a `data:` url holding the image itself,
+// for html that can be passed around on its own. local images are read from next
+// to `document`, remote ones are fetched when prose is built with `http`. images
+// that can't be had keep their url and get a warning
+pub fn embed_images(blocks: &mut [Markdown], document: &Path) {
+    for text in blocks.iter_mut().flat_map(Markdown::texts_mut) {
+        for inline in text.iter_mut() {
+            let url = match inline {
+                MarkdownInline::Image(_, url) if url.scheme() != Some("data") => url,
+                _ => continue,
+            };
+            match image_data(document, url) {
+                Ok((mime, bytes)) => {
+                    *url = Url::from(format!("data:{};base64,{}", mime, base64(&bytes)))
+                }
+                Err(e) => {
+                    tracing::warn!(path = %document.display(), "image {} was not embedded: {}", url, e)
+                }
+            }
+        }
+    }
+}
+
+// the type and contents of the image at `url`
+fn image_data(document: &Path, url: &Url) -> Result<(String, Vec<u8>), String> {
+    let decoded = url.decoded();
+    let path = decoded.split(['?', '#']).next().unwrap_or_default();
+    if url.is_external() {
+        return fetch(url.as_str());
+    }
+    if path.starts_with('/') {
+        return Err(String::from("there is no site root to find it from"));
+    }
+    let mime = mime_type(path).ok_or("its file type is not an image's")?;
+    let file = document.parent().unwrap_or(Path::new("")).join(path);
+    let bytes = fs::read(&file).map_err(|e| format!("{}: {}", file.display(), e))?;
+    Ok((mime.to_string(), bytes))
+}
+
+#[cfg(feature = "http")]
+fn fetch(url: &str) -> Result<(String, Vec<u8>), String> {
+    // far bigger than any image worth putting in a page
+    const LIMIT: u64 = 32 * 1024 * 1024;
+    let response = ureq::get(url).call().map_err(|e| e.to_string())?;
+    let mime = match response.header("Content-Type") {
+        Some(mime) if mime.starts_with("image/") => mime.to_string(),
+        _ => mime_type(url.split(['?', '#']).next().unwrap_or_default())
+            .ok_or("it was not served as an image")?
+            .to_string(),
+    };
+    Ok((mime, read_at_most(response.into_reader(), LIMIT)?))
+}
+
+// everything `reader` has, unless that is more than `limit` bytes. cut short
+// the image would be broken
+#[cfg(feature = "http")]
+fn read_at_most(reader: impl std::io::Read, limit: u64) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let mut bytes = vec![];
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("it is bigger than {} bytes", limit));
+    }
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(_: &str) -> Result<(String, Vec<u8>), String> {
+    Err(String::from(
+        "fetching remote images needs prose built with --features http",
+    ))
+}
+
+fn mime_type(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        _ => return None,
+    })
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// `img/cat.png` becomes `cat.1f2e3d4c.png`
+fn hashed_name(path: &Path, contents: &[u8]) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let digest = &format!("{:016x}", fnv1a(contents))[..8];
+    match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, digest, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, digest),
+    }
+}
+
+// swaps the file name at the end of a url's path, keeping any query or fragment
+fn rename(url: &str, name: &str) -> String {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let start = url[..end].rfind('/').map_or(0, |slash| slash + 1);
+    format!("{}{}{}", &url[..start], name, &url[end..])
+}
+
+// stable across platforms and compiler versions, unlike std's hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_read_at_most() {
+        assert_eq!(read_at_most(&b"abcd"[..], 4), Ok(b"abcd".to_vec()));
+        assert!(read_at_most(&b"abcde"[..], 4).is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let doc = Path::new("posts/first.md");
+        assert_eq!(
+            resolve(doc, "img/cat.png?v=2"),
+            Some(PathBuf::from("posts/img/cat.png"))
+        );
+        assert_eq!(
+            resolve(doc, "../img/./cat.png"),
+            Some(PathBuf::from("img/cat.png"))
+        );
+        assert_eq!(resolve(doc, "/cat.png"), Some(PathBuf::from("cat.png")));
+        assert_eq!(resolve(doc, "../../cat.png"), None);
+        assert_eq!(resolve(doc, "https://example.com/cat.png"), None);
+        assert_eq!(resolve(doc, "#top"), None);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+    }
+
+    #[test]
+    fn test_embed_images() {
+        let dir = std::env::temp_dir().join(format!("prose-embed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("img")).unwrap();
+        fs::write(dir.join("img/a cat.svg"), "<svg/>").unwrap();
+        fs::write(dir.join("notes.txt"), "hi").unwrap();
+        let image = |url: &str| {
+            Markdown::Line(vec![MarkdownInline::Image(String::from("cat"), Url::from(url))].into())
+        };
+        let mut blocks = vec![
+            image("img/a%20cat.svg?v=1"),
+            image("missing.png"),
+            image("notes.txt"),
+            image("/root.png"),
+        ];
+        embed_images(&mut blocks, &dir.join("post.md"));
+        assert_eq!(
+            blocks,
+            vec![
+                image("data:image/svg+xml;base64,PHN2Zy8+"),
+                image("missing.png"),
+                image("notes.txt"),
+                image("/root.png"),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rename() {
+        assert_eq!(rename("img/cat.png#x", "cat.1.png"), "img/cat.1.png#x");
+        assert_eq!(rename("cat.png", "cat.1.png"), "cat.1.png");
+    }
+
+    #[test]
+    fn test_copy_images() {
+        let dir = std::env::temp_dir().join(format!("prose-assets-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        fs::create_dir_all(src.join("posts/img")).unwrap();
+        fs::write(src.join("posts/img/cat.png"), "meow").unwrap();
+
+        let image = |url: &str| {
+            Markdown::Line(vec![MarkdownInline::Image(String::from("cat"), Url::from(url))].into())
+        };
+        let mut blocks = vec![image("img/cat.png"), image("missing.png")];
+        let hook = ImageHook::default();
+        let copied = copy_images(
+            &mut blocks,
+            &src,
+            &out,
+            Path::new("posts/a.md"),
+            false,
+            &hook,
+        )
+        .unwrap();
+        assert_eq!(copied.files, vec![PathBuf::from("posts/img/cat.png")]);
+        assert_eq!(
+            fs::read_to_string(out.join("posts/img/cat.png")).unwrap(),
+            "meow"
+        );
+        assert_eq!(blocks[0], image("img/cat.png"));
+
+        let copied = copy_images(
+            &mut blocks,
+            &src,
+            &out,
+            Path::new("posts/a.md"),
+            true,
+            &hook,
+        )
+        .unwrap();
+        let hashed = format!("cat.{}.png", &format!("{:016x}", fnv1a(b"meow"))[..8]);
+        assert_eq!(copied.files, vec![Path::new("posts/img").join(&hashed)]);
+        assert_eq!(blocks[0], image(&format!("img/{}", hashed)));
+        assert_eq!(blocks[1], image("missing.png"));
+
+        // a hook that writes a webp next to where the png would go
+        let hook = ImageHook::new(|source, dest| {
+            let webp = dest.with_extension("webp");
+            fs::write(&webp, fs::read(source)?)?;
+            Ok(Some(ProcessedImage {
+                url: format!("img/{}", webp.file_name().unwrap().to_string_lossy()),
+                size: Some((640, 480)),
+            }))
+        });
+        let mut blocks = vec![image("img/cat.png")];
+        let copied = copy_images(
+            &mut blocks,
+            &src,
+            &out,
+            Path::new("posts/a.md"),
+            false,
+            &hook,
+        )
+        .unwrap();
+        assert_eq!(blocks[0], image("img/cat.webp"));
+        assert!(copied.files.is_empty());
+        assert_eq!(copied.sizes.get("img/cat.webp"), Some(&(640, 480)));
+        assert_eq!(
+            fs::read_to_string(out.join("posts/img/cat.webp")).unwrap(),
+            "meow"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use crate::batch::{self, BuildError};
+use crate::options::HtmlOptions;
+use crate::Document;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task;
+
+// how long a server gets to answer before `render_url` gives up on it, so a
+// slow or hung one can't hold a thread of the blocking pool for good
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+// `batch::render_file` for async services: the file is read without blocking
+// and parsed and rendered on tokio's blocking pool, away from the tasks
+// serving requests
+pub async fn render_file(src: &Path, options: &HtmlOptions) -> Result<String, BuildError> {
+    let bytes = tokio::fs::read(src).await?;
+    let (src, options) = (src.to_path_buf(), options.clone());
+    blocking(move || {
+        let doc = Document::parse(&batch::decode_text(&src, bytes))?;
+        Ok(batch::render_document(&src, doc, &options))
+    })
+    .await
+}
+
+// the page for a markdown document fetched over http(s), titled like a file
+// named after the end of the url. without a `base_url` of their own, relative
+// links and images are resolved against the document's url
+pub async fn render_url(url: &str, options: &HtmlOptions) -> Result<String, BuildError> {
+    let (url, mut options) = (url.to_string(), options.clone());
+    // there is no async http client to fetch with, ureq waits on its own
+    // thread of the blocking pool for no longer than `TIMEOUT`
+    let text = {
+        let url = url.clone();
+        blocking(move || fetch(&url, TIMEOUT)).await?
+    };
+    blocking(move || {
+        let path = url.split(['?', '#']).next().unwrap_or_default();
+        let (directory, name) = match path.rsplit_once('/') {
+            // `https://example.com` is the root, not a file in `https:/`
+            Some((directory, _)) if directory.ends_with('/') => (path, ""),
+            Some(split) => split,
+            None => ("", path),
+        };
+        if options.base_url.is_none() {
+            options.base_url = Some(format!("{}/", directory));
+        }
+        let name = PathBuf::from(name);
+        Ok(batch::render_document(
+            &name,
+            Document::parse(&text)?,
+            &options,
+        ))
+    })
+    .await
+}
+
+fn fetch(url: &str, timeout: Duration) -> Result<String, BuildError> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .timeout(timeout * 2)
+        .build();
+    Ok(agent
+        .get(url)
+        .call()
+        .map_err(|e| io::Error::other(format!("{}: {}", url, e)))?
+        .into_string()?)
+}
+
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, BuildError> + Send + 'static,
+) -> Result<T, BuildError> {
+    task::spawn_blocking(work)
+        .await
+        .map_err(|e| BuildError::Io(io::Error::other(e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn run<T>(future: impl std::future::Future<Output = T>) -> T {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_render_file() {
+        let path = std::env::temp_dir().join(format!("prose-async-{}.md", std::process::id()));
+        fs::write(&path, "# Hi\n[up](../a.md)\n").unwrap();
+        let html = run(render_file(&path, &HtmlOptions::default())).unwrap();
+        assert_eq!(
+            html,
+            batch::render_file(&path, &HtmlOptions::default()).unwrap()
+        );
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            run(render_file(&path, &HtmlOptions::default())),
+            Err(BuildError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_url() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            let body = "[next](next.md)\n";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        let url = format!("http://{}/docs/intro.md", address);
+        let html = run(render_url(&url, &HtmlOptions::default())).unwrap();
+        server.join().unwrap();
+        assert!(html.contains("<title>intro</title>"));
+        assert!(html.contains(&format!("href=\"http://{}/docs/next.md\"", address)));
+    }
+
+    #[test]
+    fn test_fetch_timeout() {
+        // a server that takes the connection and never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/slow.md", listener.local_addr().unwrap());
+        let started = std::time::Instant::now();
+        assert!(matches!(
+            fetch(&url, Duration::from_millis(200)),
+            Err(BuildError::Io(_))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(listener);
+    }
+}
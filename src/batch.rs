@@ -0,0 +1,592 @@
+use crate::assets::{self, ImageHook};
+use crate::crossref::LinkMap;
+use crate::date;
+use crate::document::{Document, ParseError};
+use crate::embeds;
+use crate::frontmatter::FrontMatter;
+use crate::options::{HtmlOptions, ImageSizes};
+use crate::slug::slugify;
+use crate::translator;
+use crate::unicode;
+
+use glob::Pattern;
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// which files underneath the source directory take part in a build
+// paths are matched relative to the source directory, so `drafts/**` works as expected
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub html: HtmlOptions,
+    // also build documents marked `draft: true` or dated in the future
+    pub drafts: bool,
+    // name copied images after a hash of their contents
+    pub hash_assets: bool,
+    // resizes or converts images as they are copied
+    pub image_hook: ImageHook,
+    // compose accented letters the same way whatever wrote the file
+    pub nfc: bool,
+}
+
+impl BuildOptions {
+    pub fn matches(&self, relative: &Path) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|p| p.matches_path(relative));
+        included && !self.exclude.iter().any(|p| p.matches_path(relative))
+    }
+
+    pub fn publishes(&self, front_matter: &FrontMatter) -> bool {
+        if self.drafts {
+            return true;
+        }
+        let draft = front_matter.get("draft").and_then(|v| v.as_bool()) == Some(true);
+        let scheduled = front_matter
+            .get_str("date")
+            .is_some_and(|d| date::is_future(d, &date::today()));
+        !draft && !scheduled
+    }
+}
+
+#[derive(Debug)]
+pub enum BuildError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Io(e) => write!(f, "{}", e),
+            BuildError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<io::Error> for BuildError {
+    fn from(e: io::Error) -> Self {
+        BuildError::Io(e)
+    }
+}
+
+impl From<ParseError> for BuildError {
+    fn from(e: ParseError) -> Self {
+        BuildError::Parse(e)
+    }
+}
+
+// paths in the report are relative to the source directory
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub converted: Vec<PathBuf>,
+    // drafts and documents dated in the future
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, BuildError)>,
+}
+
+impl BuildReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+// walks `src`, converting every matching `.md` file into an `.html` file
+// at the same relative location underneath `out`. everything is read before
+// anything is written, so links between documents can be pointed at their pages
+pub fn build(src: &Path, out: &Path, options: &BuildOptions) -> BuildReport {
+    let (docs, mut report) = load_documents(src, options);
+    let links =
+        LinkMap::from_documents(docs.iter().map(|(relative, doc)| (relative.as_path(), doc)));
+    let mut pages = vec![];
+    for (relative, mut doc) in docs {
+        tracing::debug!(path = %relative.display(), "converting");
+        match prepare_document(src, out, &relative, &mut doc, &links, options) {
+            Ok(sizes) => pages.push((relative, doc, sizes)),
+            Err(e) => report.failed.push((relative, e)),
+        }
+    }
+    for (relative, dest, html) in render_documents(src, out, pages, options) {
+        match write_file(&dest, &html) {
+            Ok(()) => report.converted.push(relative),
+            Err(e) => report.failed.push((relative, e)),
+        }
+    }
+    report
+}
+
+// pages don't depend on each other, so with the `parallel` feature they are
+// rendered side by side. they come back in the order they went in
+fn render_documents(
+    src: &Path,
+    out: &Path,
+    pages: Vec<(PathBuf, Document, ImageSizes)>,
+    options: &BuildOptions,
+) -> Vec<(PathBuf, PathBuf, String)> {
+    let render = |(relative, doc, sizes): (PathBuf, Document, ImageSizes)| {
+        let _span = tracing::debug_span!("render", path = %relative.display()).entered();
+        let dest = out.join(output_file(&relative, &doc.front_matter));
+        let html = render_document(&src.join(&relative), doc, &with_sizes(&options.html, sizes));
+        (relative, dest, html)
+    };
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        pages.into_par_iter().map(render).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        pages.into_iter().map(render).collect()
+    }
+}
+
+// reads a text file whatever encoding it was saved in, see `unicode::decode`
+pub fn read_text(path: &Path) -> io::Result<String> {
+    Ok(decode_text(path, fs::read(path)?))
+}
+
+// the text of what was read from `path`, decoded like `read_text` does
+pub(crate) fn decode_text(path: &Path, bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            let (text, encoding) = unicode::decode(e.as_bytes());
+            tracing::debug!(path = %path.display(), "read as {}", encoding.name());
+            text.into_owned()
+        }
+    }
+}
+
+// reads and parses `relative` from the `src` directory, handing back the source
+// alongside the document. none when the document is not published yet
+pub fn load(
+    src: &Path,
+    relative: &Path,
+    options: &BuildOptions,
+) -> Result<Option<(String, Document)>, BuildError> {
+    let _span = tracing::debug_span!("load", path = %relative.display()).entered();
+    let mut text = read_text(&src.join(relative))?;
+    if options.nfc {
+        text = unicode::nfc(&text).into_owned();
+    }
+    let doc = Document::parse(&text)?;
+    if options.publishes(&doc.front_matter) {
+        Ok(Some((text, doc)))
+    } else {
+        Ok(None)
+    }
+}
+
+// every markdown file underneath `src` that the options let through, relative to `src`,
+// along with anything that could not be read while looking
+pub fn sources(src: &Path, options: &BuildOptions) -> (Vec<PathBuf>, Vec<(PathBuf, BuildError)>) {
+    let (mut found, mut failed) = (vec![], vec![]);
+    for entry in WalkDir::new(src).sort_by_file_name() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let path = e.path().unwrap_or(src).to_path_buf();
+                failed.push((path, BuildError::Io(e.into())));
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !entry.file_type().is_file() || !is_markdown(path) {
+            continue;
+        }
+        let relative = path.strip_prefix(src).unwrap_or(path);
+        if options.matches(relative) {
+            found.push(relative.to_path_buf());
+        }
+    }
+    (found, failed)
+}
+
+// every document of `src` that gets published, along with a report of the ones
+// that were skipped or could not be read
+fn load_documents(src: &Path, options: &BuildOptions) -> (Vec<(PathBuf, Document)>, BuildReport) {
+    let (relatives, failed) = sources(src, options);
+    let mut report = BuildReport {
+        failed,
+        ..Default::default()
+    };
+    let mut docs = vec![];
+    for relative in relatives {
+        match load(src, &relative, options) {
+            Ok(Some((_, doc))) => docs.push((relative, doc)),
+            Ok(None) => report.skipped.push(relative),
+            Err(e) => report.failed.push((relative, e)),
+        }
+    }
+    (docs, report)
+}
+
+// the map `build` points links between the documents of `src` with, for
+// converting them one at a time with `convert_file` afterwards
+pub fn link_map(src: &Path, options: &BuildOptions) -> LinkMap {
+    let (docs, _) = load_documents(src, options);
+    LinkMap::from_documents(docs.iter().map(|(relative, doc)| (relative.as_path(), doc)))
+}
+
+// converts `relative` from the `src` directory into `out` on its own, along with the
+// images it uses. links to other documents are pointed at their pages with `links`,
+// which is brought up to date with this one's headings and output path first.
+// false when the document is not published yet and nothing was written
+pub fn convert_file(
+    src: &Path,
+    out: &Path,
+    relative: &Path,
+    links: &mut LinkMap,
+    options: &BuildOptions,
+) -> Result<bool, BuildError> {
+    match load(src, relative, options)? {
+        Some((_, mut doc)) => {
+            links.insert(relative, &output_file(relative, &doc.front_matter), &doc);
+            let sizes = prepare_document(src, out, relative, &mut doc, links, options)?;
+            let dest = out.join(output_file(relative, &doc.front_matter));
+            write_file(
+                &dest,
+                &render_document(&src.join(relative), doc, &with_sizes(&options.html, sizes)),
+            )?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// fills in the document's embeds, copies its images over and points its links
+// at the built pages. this touches the output folder, so it happens one document
+// at a time.
+// hands back the sizes the image hook gave for the images
+fn prepare_document(
+    src: &Path,
+    out: &Path,
+    relative: &Path,
+    doc: &mut Document,
+    links: &LinkMap,
+    options: &BuildOptions,
+) -> Result<ImageSizes, BuildError> {
+    embeds::embed_files(&mut doc.blocks, src, relative);
+    let copied = assets::copy_images(
+        &mut doc.blocks,
+        src,
+        out,
+        relative,
+        options.hash_assets,
+        &options.image_hook,
+    )?;
+    links.rewrite(&mut doc.blocks, relative);
+    Ok(copied.sizes)
+}
+
+// the options for one document, along with the sizes of its images
+fn with_sizes(options: &HtmlOptions, sizes: ImageSizes) -> Cow<'_, HtmlOptions> {
+    if sizes.is_empty() {
+        return Cow::Borrowed(options);
+    }
+    let mut options = options.clone();
+    options.image_sizes.extend(sizes);
+    Cow::Owned(options)
+}
+
+pub fn write_file(path: &Path, contents: &str) -> Result<(), BuildError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+// `Document::parse` of a file that is mapped into memory rather than read into
+// a string, so a very big one isn't held twice while it's parsed. files that
+// aren't utf-8 are decoded like `read_text` does, which copies them after all
+#[cfg(feature = "mmap")]
+pub fn parse_file_mmap(path: &Path) -> Result<Document, BuildError> {
+    let file = fs::File::open(path)?;
+    // safety: the map is only read. another process writing to the file while
+    // it's parsed can tear the text, like a read racing a write, and cutting
+    // it short faults
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    match std::str::from_utf8(&map) {
+        Ok(text) => Ok(Document::parse(text)?),
+        Err(_) => Ok(Document::parse(&read_text(path)?)?),
+    }
+}
+
+pub fn render_file(src: &Path, options: &HtmlOptions) -> Result<String, BuildError> {
+    let doc = Document::parse(&read_text(src)?)?;
+    Ok(render_document(src, doc, options))
+}
+
+// renders a document read from `src` into a complete html page, titled by
+// its front matter or first heading, or else after the file itself
+pub fn render_document(src: &Path, doc: Document, options: &HtmlOptions) -> String {
+    let title = match doc.title() {
+        Some(title) => title,
+        None => src
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    let options = options.with_front_matter(&doc.front_matter);
+    let body = translator::translate_with_options(&doc.blocks, &options);
+    if options.accessibility {
+        page(&title, &skip_to_content(&body))
+    } else {
+        page(&title, &body)
+    }
+}
+
+// a link keyboard users can take straight past everything above the content
+fn skip_to_content(body: &str) -> String {
+    format!(
+        "<a class=\"skip-link\" href=\"#content\">Skip to content</a>\n<main id=\"content\">\n{}\n</main>",
+        body
+    )
+}
+
+// where the html for a source file (relative to the source directory) ends up
+pub fn output_path(out: &Path, relative: &Path) -> PathBuf {
+    out.join(relative).with_extension("html")
+}
+
+// like `output_path`, relative to the output directory, but a `slug` in the
+// front matter renames the page while keeping it in the same folder
+pub fn output_file(relative: &Path, front_matter: &FrontMatter) -> PathBuf {
+    match front_matter.get_str("slug").map(slugify) {
+        Some(slug) if !slug.is_empty() => relative.with_file_name(format!("{}.html", slug)),
+        _ => output_path(Path::new(""), relative),
+    }
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md")
+}
+
+pub fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+        title, body
+    )
+}
+
+// files on disk for the tests of the modules that read and write them
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    // an empty directory of its own for each test, `name` tells them apart
+    pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("prose-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub(crate) fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{scratch_dir, write};
+    use super::*;
+
+    #[test]
+    fn test_build_preserves_tree() {
+        let dir = scratch_dir("tree");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(&src.join("index.md"), "# Home\n");
+        write(&src.join("posts/first.md"), "hello *there*");
+        write(
+            &src.join("posts/second.md"),
+            "---\ntitle: Second Post\n---\nhi\n",
+        );
+        write(&src.join("posts/notes.txt"), "not markdown\n");
+        write(&src.join("posts/cat.png"), "meow");
+        write(&src.join("posts/third.md"), "![cat](cat.png)\n");
+
+        let report = build(&src, &out, &BuildOptions::default());
+        assert!(report.is_success());
+        assert_eq!(
+            report.converted,
+            vec![
+                PathBuf::from("index.md"),
+                PathBuf::from("posts/first.md"),
+                PathBuf::from("posts/second.md"),
+                PathBuf::from("posts/third.md")
+            ]
+        );
+        let first = fs::read_to_string(out.join("posts/first.html")).unwrap();
+        assert!(first.contains("<title>first</title>"));
+        assert!(first.contains("<p>hello <i>there</i></p>"));
+        let second = fs::read_to_string(out.join("posts/second.html")).unwrap();
+        assert!(second.contains("<title>Second Post</title>"));
+        assert!(!second.contains("title:"));
+        assert!(!out.join("posts/notes.html").exists());
+        assert!(!out.join("posts/notes.txt").exists());
+        assert!(out.join("posts/cat.png").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_include_exclude() {
+        let dir = scratch_dir("globs");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(&src.join("a.md"), "a\n");
+        write(&src.join("drafts/b.md"), "b\n");
+        write(&src.join("notes/c.md"), "c\n");
+
+        let options = BuildOptions {
+            include: vec![Pattern::new("**/*.md").unwrap()],
+            exclude: vec![Pattern::new("drafts/**").unwrap()],
+            ..Default::default()
+        };
+        let report = build(&src, &out, &options);
+        assert_eq!(
+            report.converted,
+            vec![PathBuf::from("a.md"), PathBuf::from("notes/c.md")]
+        );
+        assert!(!out.join("drafts").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_links_between_documents() {
+        let dir = scratch_dir("links");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(
+            &src.join("index.md"),
+            "see [setup](guide/setup.md#Install It)\n",
+        );
+        write(
+            &src.join("guide/setup.md"),
+            "---\nslug: Getting Started\n---\n## Install it\n[home](../index.md)\n",
+        );
+
+        let report = build(&src, &out, &BuildOptions::default());
+        assert!(report.is_success());
+        let index = fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"guide/getting-started.html#install-it\">setup</a>"));
+        let setup = fs::read_to_string(out.join("guide/getting-started.html")).unwrap();
+        assert!(setup.contains("<a href=\"../index.html\">home</a>"));
+        assert!(!out.join("guide/setup.html").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_convert_file_links_like_build() {
+        let dir = scratch_dir("convert-links");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(
+            &src.join("index.md"),
+            "see [setup](guide/setup.md#Install It)\n",
+        );
+        write(
+            &src.join("guide/setup.md"),
+            "---\nslug: Getting Started\n---\n## Install it\n[home](../index.md)\n",
+        );
+
+        assert!(build(&src, &out, &BuildOptions::default()).is_success());
+        let built = fs::read_to_string(out.join("index.html")).unwrap();
+        fs::remove_dir_all(&out).unwrap();
+        let mut links = link_map(&src, &BuildOptions::default());
+        assert!(convert_file(
+            &src,
+            &out,
+            Path::new("index.md"),
+            &mut links,
+            &BuildOptions::default()
+        )
+        .unwrap());
+        assert_eq!(fs::read_to_string(out.join("index.html")).unwrap(), built);
+        assert!(built.contains("<a href=\"guide/getting-started.html#install-it\">setup</a>"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_skips_drafts() {
+        let dir = scratch_dir("drafts");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(&src.join("a.md"), "---\ndate: 2021-03-04\n---\na\n");
+        write(&src.join("b.md"), "---\ndraft: true\n---\nb\n");
+        write(&src.join("c.md"), "---\ndate: 9999-01-01\n---\nc\n");
+
+        let report = build(&src, &out, &BuildOptions::default());
+        assert_eq!(report.converted, vec![PathBuf::from("a.md")]);
+        assert_eq!(
+            report.skipped,
+            vec![PathBuf::from("b.md"), PathBuf::from("c.md")]
+        );
+        assert!(!out.join("b.html").exists());
+
+        let options = BuildOptions {
+            drafts: true,
+            ..Default::default()
+        };
+        assert_eq!(build(&src, &out, &options).converted.len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_reports_failures() {
+        let dir = scratch_dir("failures");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(&src.join("good.md"), "fine\n");
+        write(&src.join("bad.md"), "2 * 3\n");
+
+        let report = build(&src, &out, &BuildOptions::default());
+        assert!(!report.is_success());
+        assert_eq!(report.converted, vec![PathBuf::from("good.md")]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, PathBuf::from("bad.md"));
+        assert!(matches!(report.failed[0].1, BuildError::Parse(_)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_parse_file_mmap() {
+        let dir = scratch_dir("mmap");
+        write(&dir.join("a.md"), "---\ntitle: A\n---\n# Hi\nthere");
+        write(&dir.join("empty.md"), "");
+        fs::write(dir.join("latin1.md"), b"caf\xe9\n").unwrap();
+        let doc = parse_file_mmap(&dir.join("a.md")).unwrap();
+        assert_eq!(
+            doc,
+            Document::parse("---\ntitle: A\n---\n# Hi\nthere").unwrap()
+        );
+        assert!(parse_file_mmap(&dir.join("empty.md")).is_ok());
+        assert_eq!(
+            parse_file_mmap(&dir.join("latin1.md")).unwrap().text(),
+            "café"
+        );
+        assert!(matches!(
+            parse_file_mmap(&dir.join("missing.md")),
+            Err(BuildError::Io(_))
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_document_skip_link() {
+        let doc = Document::parse("# Hi\n").unwrap();
+        let options = HtmlOptions {
+            accessibility: true,
+            ..Default::default()
+        };
+        let html = render_document(Path::new("hi.md"), doc, &options);
+        assert!(html.contains(
+            "<body>\n<a class=\"skip-link\" href=\"#content\">Skip to content</a>\n<main id=\"content\">\n<h1"
+        ));
+    }
+}
@@ -0,0 +1,413 @@
+use crate::escape;
+use crate::prelude::*;
+
+use alloc::collections::BTreeMap;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{fs, io, path::Path};
+
+// someone who wrote a cited work, `literal` names like "The Rust Team" only have a family
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Name {
+    pub family: String,
+    pub given: String,
+}
+
+// one work that can be cited with `[@key]`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub authors: Vec<Name>,
+    pub title: Option<String>,
+    pub year: Option<String>,
+    // the journal, book or proceedings the work appeared in
+    pub container: Option<String>,
+    pub publisher: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Bibliography {
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug)]
+pub enum BibliographyError {
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for BibliographyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            BibliographyError::Io(e) => write!(f, "{}", e),
+            BibliographyError::Invalid(e) => write!(f, "invalid bibliography: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for BibliographyError {}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for BibliographyError {
+    fn from(e: io::Error) -> Self {
+        BibliographyError::Io(e)
+    }
+}
+
+impl Bibliography {
+    // `.json` files are read as csl json, anything else as bibtex
+    #[cfg(feature = "std")]
+    pub fn load(path: &Path) -> Result<Bibliography, BibliographyError> {
+        let src = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => parse_csl_json(&src),
+            _ => parse_bibtex(&src),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, entry: Entry) {
+        self.entries.insert(entry.key.clone(), entry);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl Entry {
+    // how the work is cited in running text, `Doe and Smith, 2020`
+    pub fn citation(&self) -> String {
+        let authors = match self.authors.as_slice() {
+            [] => self.title.clone().unwrap_or_else(|| self.key.clone()),
+            [one] => one.family.clone(),
+            [one, two] => format!("{} and {}", one.family, two.family),
+            [first, ..] => format!("{} et al.", first.family),
+        };
+        format!("{}, {}", authors, self.year.as_deref().unwrap_or("n.d."))
+    }
+
+    // the html for the work's place in the references list
+    pub fn reference(&self) -> String {
+        let mut out = String::new();
+        if !self.authors.is_empty() {
+            let names = self
+                .authors
+                .iter()
+                .enumerate()
+                .map(|(i, name)| match (i, name.given.is_empty()) {
+                    (_, true) => name.family.clone(),
+                    (0, false) => format!("{}, {}", name.family, name.given),
+                    (_, false) => format!("{} {}", name.given, name.family),
+                })
+                .collect::<Vec<String>>();
+            let names = match names.split_last() {
+                Some((last, rest)) if !rest.is_empty() => {
+                    format!("{} and {}", rest.join(", "), last)
+                }
+                _ => names.join(""),
+            };
+            out.push_str(&escape(&names));
+            out.push(' ');
+        }
+        out.push_str(&format!(
+            "({}).",
+            escape(self.year.as_deref().unwrap_or("n.d."))
+        ));
+        if let Some(title) = &self.title {
+            out.push_str(&format!(" {}.", escape(title)));
+        }
+        if let Some(container) = &self.container {
+            out.push_str(&format!(" <i>{}</i>.", escape(container)));
+        }
+        if let Some(publisher) = &self.publisher {
+            out.push_str(&format!(" {}.", escape(publisher)));
+        }
+        if let Some(url) = &self.url {
+            out.push_str(&format!(" <a href=\"{0}\">{0}</a>", escape(url)));
+        }
+        out
+    }
+
+    // the order of a references list: by author, then year, then title
+    pub fn sort_key(&self) -> (String, String, String) {
+        (
+            self.authors
+                .first()
+                .map_or_else(String::new, |name| name.family.to_lowercase()),
+            self.year.clone().unwrap_or_default(),
+            self.title.clone().unwrap_or_default().to_lowercase(),
+        )
+    }
+}
+
+// reads the `@type{key, field = {value}, ...}` entries of a .bib file.
+// @comment, @string and @preamble blocks are skipped over
+pub fn parse_bibtex(src: &str) -> Result<Bibliography, BibliographyError> {
+    let mut bibliography = Bibliography::default();
+    let mut rest = src;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let open = rest
+            .find(['{', '('])
+            .ok_or_else(|| invalid("entry without a body"))?;
+        let kind = rest[..open].trim().to_lowercase();
+        let body_len = closing(&rest[open..]).ok_or_else(|| invalid("unclosed entry"))?;
+        let body = &rest[open + 1..open + body_len];
+        rest = &rest[open + body_len + 1..];
+        if matches!(kind.as_str(), "comment" | "string" | "preamble") {
+            continue;
+        }
+        bibliography.insert(parse_bibtex_entry(body)?);
+    }
+    Ok(bibliography)
+}
+
+fn parse_bibtex_entry(body: &str) -> Result<Entry, BibliographyError> {
+    let (key, mut rest) = body.split_once(',').unwrap_or((body, ""));
+    let mut entry = Entry {
+        key: key.trim().to_string(),
+        ..Default::default()
+    };
+    let mut doi = None;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            break;
+        }
+        let (name, value) = rest
+            .split_once('=')
+            .ok_or_else(|| invalid(&format!("field without a value in {}", entry.key)))?;
+        let value = value.trim_start();
+        let (field, len) = match value.chars().next() {
+            Some('{') => {
+                let len = closing(value).ok_or_else(|| invalid("unclosed field"))?;
+                (&value[1..len], len + 1)
+            }
+            Some('"') => {
+                let len = value[1..]
+                    .find('"')
+                    .ok_or_else(|| invalid("unclosed field"))?;
+                (&value[1..len + 1], len + 2)
+            }
+            _ => {
+                let len = value.find(',').unwrap_or(value.len());
+                (value[..len].trim(), len)
+            }
+        };
+        rest = &value[len..];
+        let value = clean(field);
+        match name.trim().to_lowercase().as_str() {
+            "author" => entry.authors = value.split(" and ").map(bibtex_name).collect(),
+            "title" => entry.title = Some(value),
+            "year" => entry.year = Some(value),
+            "date" if entry.year.is_none() => entry.year = Some(value.chars().take(4).collect()),
+            "journal" | "booktitle" => entry.container = Some(value),
+            "publisher" => entry.publisher = Some(value),
+            "url" => entry.url = Some(value),
+            "doi" => doi = Some(value),
+            _ => {}
+        }
+    }
+    if entry.url.is_none() {
+        entry.url = doi.map(|doi| format!("https://doi.org/{}", doi));
+    }
+    Ok(entry)
+}
+
+// the length up to the bracket closing the one `s` starts with
+fn closing(s: &str) -> Option<usize> {
+    let (open, close) = match s.chars().next()? {
+        '(' => ('(', ')'),
+        _ => ('{', '}'),
+    };
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+// drops the braces bibtex uses to protect capitals and tidies up whitespace
+fn clean(value: &str) -> String {
+    value
+        .replace(['{', '}'], "")
+        .replace("\\&", "&")
+        .replace('~', " ")
+        .replace("--", "–")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// `Doe, Jane` and `Jane Doe` both name the same person
+fn bibtex_name(name: &str) -> Name {
+    let name = name.trim();
+    match name.split_once(',') {
+        Some((family, given)) => Name {
+            family: family.trim().to_string(),
+            given: given.trim().to_string(),
+        },
+        None => match name.rsplit_once(' ') {
+            Some((given, family)) => Name {
+                family: family.to_string(),
+                given: given.to_string(),
+            },
+            None => Name {
+                family: name.to_string(),
+                given: String::new(),
+            },
+        },
+    }
+}
+
+// reads the array of items a reference manager exports as csl json
+pub fn parse_csl_json(src: &str) -> Result<Bibliography, BibliographyError> {
+    let items: Vec<serde_json::Value> =
+        serde_json::from_str(src).map_err(|e| invalid(&e.to_string()))?;
+    let mut bibliography = Bibliography::default();
+    for item in items {
+        let text = |field: &str| item[field].as_str().map(str::to_string);
+        let key = text("id")
+            .or_else(|| item["id"].as_i64().map(|id| id.to_string()))
+            .ok_or_else(|| invalid("item without an id"))?;
+        let authors = item["author"]
+            .as_array()
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| Name {
+                        family: name["family"]
+                            .as_str()
+                            .or_else(|| name["literal"].as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                        given: name["given"].as_str().unwrap_or_default().to_string(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let year = match &item["issued"]["date-parts"][0][0] {
+            serde_json::Value::Number(year) => Some(year.to_string()),
+            serde_json::Value::String(year) => Some(year.clone()),
+            _ => None,
+        };
+        bibliography.insert(Entry {
+            key,
+            authors,
+            title: text("title"),
+            year,
+            container: text("container-title"),
+            publisher: text("publisher"),
+            url: text("URL").or_else(|| text("DOI").map(|doi| format!("https://doi.org/{}", doi))),
+        });
+    }
+    Ok(bibliography)
+}
+
+fn invalid(message: &str) -> BibliographyError {
+    BibliographyError::Invalid(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bibtex() {
+        let bibliography = parse_bibtex(
+            r#"
+@comment{ignored {entirely}}
+@article{doe2020,
+  author = {Doe, Jane and John {McSmith}},
+  title = "Fast {Markdown}",
+  journal = {Journal of Text \& Markup},
+  year = 2020,
+  doi = {10.1000/xyz},
+}
+@book(knuth, author = {Donald E. Knuth}, title = {The {TeX}book}, publisher = {Addison--Wesley}, date = {1984-01-01})
+"#,
+        )
+        .unwrap();
+        assert_eq!(bibliography.len(), 2);
+        assert_eq!(
+            bibliography.get("doe2020"),
+            Some(&Entry {
+                key: String::from("doe2020"),
+                authors: vec![
+                    Name {
+                        family: String::from("Doe"),
+                        given: String::from("Jane")
+                    },
+                    Name {
+                        family: String::from("McSmith"),
+                        given: String::from("John")
+                    },
+                ],
+                title: Some(String::from("Fast Markdown")),
+                year: Some(String::from("2020")),
+                container: Some(String::from("Journal of Text & Markup")),
+                publisher: None,
+                url: Some(String::from("https://doi.org/10.1000/xyz")),
+            })
+        );
+        let knuth = bibliography.get("knuth").unwrap();
+        assert_eq!(knuth.authors[0].family, "Knuth");
+        assert_eq!(knuth.year.as_deref(), Some("1984"));
+        assert_eq!(knuth.publisher.as_deref(), Some("Addison–Wesley"));
+        assert!(parse_bibtex("@article{broken, title = {oops}").is_err());
+    }
+
+    #[test]
+    fn test_parse_csl_json() {
+        let bibliography = parse_csl_json(
+            r#"[{"id": "rust", "author": [{"literal": "The Rust Team"}], "title": "The Book",
+                 "issued": {"date-parts": [[2018, 5]]}, "URL": "https://doc.rust-lang.org/book/"}]"#,
+        )
+        .unwrap();
+        let entry = bibliography.get("rust").unwrap();
+        assert_eq!(entry.citation(), "The Rust Team, 2018");
+        assert_eq!(
+            entry.reference(),
+            "The Rust Team (2018). The Book. <a href=\"https://doc.rust-lang.org/book/\">https://doc.rust-lang.org/book/</a>"
+        );
+        assert!(parse_csl_json("{}").is_err());
+    }
+
+    #[test]
+    fn test_citation() {
+        let name = |family: &str| Name {
+            family: family.to_string(),
+            given: String::from("A."),
+        };
+        let mut entry = Entry {
+            key: String::from("k"),
+            authors: vec![name("Doe")],
+            ..Default::default()
+        };
+        assert_eq!(entry.citation(), "Doe, n.d.");
+        entry.year = Some(String::from("2020"));
+        entry.authors.push(name("Roe"));
+        assert_eq!(entry.citation(), "Doe and Roe, 2020");
+        entry.authors.push(name("Poe"));
+        assert_eq!(entry.citation(), "Doe et al., 2020");
+        assert_eq!(entry.reference(), "Doe, A., A. Roe and A. Poe (2020).");
+    }
+}
@@ -0,0 +1,95 @@
+use crate::config::Config;
+use crate::exit::{Failure, Status};
+use clap::Args;
+use glob::Pattern;
+use markdown_to_html::batch::{self, BuildOptions};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct BuildArgs {
+    /// Directory containing the markdown sources
+    pub dir: PathBuf,
+    /// Directory the html is written to [default: public]
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+    /// Only convert files matching this glob (relative to DIR), may be repeated
+    #[arg(long, value_parser = Pattern::new)]
+    pub include: Vec<Pattern>,
+    /// Skip files matching this glob (relative to DIR), may be repeated
+    #[arg(long, value_parser = Pattern::new)]
+    pub exclude: Vec<Pattern>,
+    /// Also build drafts and documents dated in the future
+    #[arg(long)]
+    pub drafts: bool,
+    /// Name copied images after a hash of their contents, so they can be cached forever
+    #[arg(long)]
+    pub hash_assets: bool,
+    /// Normalize text to NFC, so accented letters match however they were typed
+    #[arg(long)]
+    pub nfc: bool,
+    /// Resolve relative links and images against this url
+    #[arg(long)]
+    pub base_url: Option<String>,
+}
+
+impl BuildArgs {
+    pub fn out(&self, config: &Config) -> PathBuf {
+        self.out
+            .clone()
+            .or_else(|| config.out.clone())
+            .unwrap_or_else(|| PathBuf::from("public"))
+    }
+
+    pub fn options(&self, config: &Config) -> Result<BuildOptions, String> {
+        let include = if self.include.is_empty() {
+            config.include()?
+        } else {
+            self.include.clone()
+        };
+        let exclude = if self.exclude.is_empty() {
+            config.exclude()?
+        } else {
+            self.exclude.clone()
+        };
+        let mut html = config.html();
+        if let Some(base_url) = &self.base_url {
+            html.base_url = Some(base_url.clone());
+        }
+        Ok(BuildOptions {
+            include,
+            exclude,
+            html,
+            drafts: self.drafts,
+            hash_assets: self.hash_assets || config.hash_assets,
+            nfc: self.nfc || config.nfc,
+            ..BuildOptions::default()
+        })
+    }
+}
+
+pub fn run(args: BuildArgs, config: &Config) -> ExitCode {
+    let options = match args.options(config) {
+        Ok(options) => options,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Failure::Io.into();
+        }
+    };
+    let report = batch::build(&args.dir, &args.out(config), &options);
+    let mut status = Status::default();
+    for (path, e) in &report.failed {
+        tracing::error!(path = %args.dir.join(path).display(), "{}", e);
+        status.fail(Failure::of(e));
+    }
+    tracing::info!(
+        converted = report.converted.len(),
+        skipped = report.skipped.len(),
+        failed = report.failed.len(),
+        "converted {} file(s), {} skipped, {} failed",
+        report.converted.len(),
+        report.skipped.len(),
+        report.failed.len()
+    );
+    status.exit_code()
+}
@@ -0,0 +1,111 @@
+use crate::exit::{Failure, Status};
+use clap::Args;
+use markdown_to_html::batch;
+use markdown_to_html::linkcheck::{self, BrokenLink};
+use markdown_to_html::Position;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct CheckLinksArgs {
+    /// Files or directories to check
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Where links starting with `/` are looked up
+    #[arg(long, default_value = ".")]
+    root: PathBuf,
+    /// Also request every external http(s) url (needs the `http` feature)
+    #[arg(long)]
+    external: bool,
+    /// Seconds to wait for each external url
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+}
+
+// prints `file:line:column: url: reason` for every broken link
+pub fn run(args: CheckLinksArgs) -> ExitCode {
+    let mut external = match External::new(&args) {
+        Ok(checker) => checker,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Failure::Io.into();
+        }
+    };
+
+    let mut status = Status::default();
+    for path in crate::markdown_files(&args.paths) {
+        let src = match batch::read_text(&path) {
+            Ok(src) => src,
+            Err(e) => {
+                tracing::error!(path = %path.display(), "{}", e);
+                status.fail(Failure::Io);
+                continue;
+            }
+        };
+        let mut broken = linkcheck::check_internal(&args.root, &path, &src);
+        broken.extend(external.check(&src));
+        broken.sort_by_key(|broken| broken.link.span.start);
+        for broken in broken {
+            report(&path, &src, "", &broken);
+            status.fail(Failure::Parse);
+        }
+        // a stale anchor still lands on the right page, so it does not fail the check
+        for broken in linkcheck::check_anchors(&args.root, &path, &src) {
+            report(&path, &src, "warning: ", &broken);
+        }
+    }
+    status.exit_code()
+}
+
+fn report(path: &Path, src: &str, prefix: &str, broken: &BrokenLink) {
+    let position = Position::of(src, broken.link.span.start);
+    println!(
+        "{}:{}:{}: {}{}: {}",
+        path.display(),
+        position.line,
+        position.column,
+        prefix,
+        broken.link.url,
+        broken.reason
+    );
+}
+
+// external urls can only be requested when prose is built with the `http` feature
+#[cfg(feature = "http")]
+struct External(Option<linkcheck::UrlChecker>);
+
+#[cfg(not(feature = "http"))]
+struct External;
+
+#[cfg(feature = "http")]
+impl External {
+    fn new(args: &CheckLinksArgs) -> Result<External, String> {
+        Ok(External(args.external.then(|| {
+            linkcheck::UrlChecker::new(std::time::Duration::from_secs(args.timeout))
+        })))
+    }
+
+    fn check(&mut self, src: &str) -> Vec<BrokenLink> {
+        match &mut self.0 {
+            Some(checker) => checker.check_external(src),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(not(feature = "http"))]
+impl External {
+    fn new(args: &CheckLinksArgs) -> Result<External, String> {
+        if args.external {
+            Err(String::from(
+                "checking external links needs prose built with the `http` feature",
+            ))
+        } else {
+            Ok(External)
+        }
+    }
+
+    fn check(&mut self, _: &str) -> Vec<BrokenLink> {
+        vec![]
+    }
+}
@@ -0,0 +1,294 @@
+use glob::Pattern;
+use markdown_to_html::bibliography::Bibliography;
+use markdown_to_html::filters;
+use markdown_to_html::lint::Builtin;
+use markdown_to_html::options::{AlertClasses, HtmlOptions, PermalinkPosition, Permalinks};
+use markdown_to_html::vars::Variables;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// picked up from the working directory when `--config` is not given
+pub const DEFAULT_PATH: &str = "prose.toml";
+
+// defaults for every command, flags given on the command line win over these
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub out: Option<PathBuf>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub hash_assets: bool,
+    // normalize every document to nfc before parsing
+    pub nfc: bool,
+    pub html: HtmlConfig,
+    pub lint: LintConfig,
+    pub site: SiteConfig,
+    // values for `{{name}}` placeholders, front matter fields take precedence
+    pub variables: Variables,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HtmlConfig {
+    pub code_class_prefix: Option<String>,
+    pub base_url: Option<String>,
+    pub md_links_to_html: bool,
+    pub number_headings: bool,
+    pub list_values: bool,
+    pub csv_tables: bool,
+    // `lang` and `dir` attributes for every document, front matter can override them
+    pub lang: Option<String>,
+    pub dir: Option<String>,
+    // aria roles for footnotes, warnings for missing alt text and skip links
+    pub accessibility: bool,
+    // (c), -> and 1/2 in the text become ©, → and ½
+    pub typographer: bool,
+    // links become `text [1]` with their urls listed at the end, for printing
+    pub link_appendix: bool,
+    // images without alt text get one made from their file name
+    pub alt_from_filename: bool,
+    // lines next to each other are one paragraph, broken with <br />
+    pub hard_breaks: bool,
+    // lines with only an image on them are the <img> alone, not in a <p>
+    pub standalone_images: bool,
+    // urls written out after links, no permalinks and page break classes
+    pub print: bool,
+    // give headings ids and a `¶` link to themselves
+    pub permalinks: bool,
+    // what the permalinks show instead of `¶`
+    pub permalink_symbol: Option<String>,
+    // `"before"` or `"after"` the heading's text
+    pub permalink_position: Option<String>,
+    // the classes alerts get, `"mkdocs"` or a table of templates
+    pub alerts: Option<AlertsConfig>,
+    // built in filters run over every page's html, `external-links`
+    pub filters: Vec<String>,
+    // a bibtex or csl json file for `[@key]` citations, relative to the config file
+    pub bibliography: Option<PathBuf>,
+    #[serde(skip)]
+    pub works: Bibliography,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AlertsConfig {
+    // one of `AlertClasses::NAMES`
+    Named(String),
+    // `{ container = "callout callout-{}", title = "callout-title" }`
+    Custom { container: String, title: String },
+}
+
+impl AlertsConfig {
+    fn classes(&self) -> Option<AlertClasses> {
+        match self {
+            AlertsConfig::Named(name) => AlertClasses::named(name),
+            AlertsConfig::Custom { container, title } => Some(AlertClasses {
+                container: container.clone(),
+                title: title.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    pub only: Vec<String>,
+    pub disable: Vec<String>,
+    pub max_line_length: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SiteConfig {
+    pub title: Option<String>,
+    pub templates: Option<PathBuf>,
+    pub url: Option<String>,
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        match path {
+            Some(path) => Config::read(path),
+            None if Path::new(DEFAULT_PATH).is_file() => Config::read(Path::new(DEFAULT_PATH)),
+            None => Ok(Config::default()),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Config, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut config: Config =
+            toml::from_str(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if let Some(name) = config
+            .html
+            .filters
+            .iter()
+            .find(|name| filters::named(name).is_none())
+        {
+            return Err(format!(
+                "{}: unknown filter {}, expected one of: {}",
+                path.display(),
+                name,
+                filters::NAMES.join(", ")
+            ));
+        }
+        if let Some(AlertsConfig::Named(name)) = &config.html.alerts {
+            if AlertClasses::named(name).is_none() {
+                return Err(format!(
+                    "{}: unknown alert classes {}, expected one of: {}",
+                    path.display(),
+                    name,
+                    AlertClasses::NAMES.join(", ")
+                ));
+            }
+        }
+        if let Some(position) = &config.html.permalink_position {
+            if PermalinkPosition::named(position).is_none() {
+                return Err(format!(
+                    "{}: unknown permalink position {}, expected one of: {}",
+                    path.display(),
+                    position,
+                    PermalinkPosition::NAMES.join(", ")
+                ));
+            }
+        }
+        if let Some(bibliography) = &config.html.bibliography {
+            let bibliography = path.parent().unwrap_or(Path::new("")).join(bibliography);
+            config.html.works = Bibliography::load(&bibliography)
+                .map_err(|e| format!("{}: {}", bibliography.display(), e))?;
+        }
+        Ok(config)
+    }
+
+    pub fn include(&self) -> Result<Vec<Pattern>, String> {
+        patterns(&self.include)
+    }
+
+    pub fn exclude(&self) -> Result<Vec<Pattern>, String> {
+        patterns(&self.exclude)
+    }
+
+    pub fn html(&self) -> HtmlOptions {
+        let mut options = HtmlOptions::default();
+        if let Some(prefix) = &self.html.code_class_prefix {
+            options.code_class_prefix = prefix.clone();
+        }
+        options.base_url = self.html.base_url.clone();
+        options.md_links_to_html = self.html.md_links_to_html;
+        options.number_headings = self.html.number_headings;
+        options.list_values = self.html.list_values;
+        options.csv_tables = self.html.csv_tables;
+        options.lang = self.html.lang.clone();
+        options.dir = self.html.dir.clone();
+        options.accessibility = self.html.accessibility;
+        options.typographer = self.html.typographer;
+        options.link_appendix = self.html.link_appendix;
+        options.alt_from_filename = self.html.alt_from_filename;
+        options.hard_breaks = self.html.hard_breaks;
+        options.standalone_images = self.html.standalone_images;
+        options.print = self.html.print;
+        if self.html.permalinks {
+            let mut permalinks = Permalinks::default();
+            if let Some(symbol) = &self.html.permalink_symbol {
+                permalinks.symbol = symbol.clone();
+            }
+            if let Some(position) = self
+                .html
+                .permalink_position
+                .as_deref()
+                .and_then(PermalinkPosition::named)
+            {
+                permalinks.position = position;
+            }
+            options.permalinks = Some(permalinks);
+        }
+        if let Some(classes) = self.html.alerts.as_ref().and_then(AlertsConfig::classes) {
+            options.alert_classes = classes;
+        }
+        options.variables = self.variables.clone();
+        options.bibliography = self.html.works.clone();
+        for filter in self
+            .html
+            .filters
+            .iter()
+            .filter_map(|name| filters::named(name))
+        {
+            options.filter(filter);
+        }
+        options
+    }
+}
+
+impl LintConfig {
+    pub fn only(&self) -> Result<Vec<Builtin>, String> {
+        rules(&self.only)
+    }
+
+    pub fn disable(&self) -> Result<Vec<Builtin>, String> {
+        rules(&self.disable)
+    }
+}
+
+fn patterns(globs: &[String]) -> Result<Vec<Pattern>, String> {
+    globs
+        .iter()
+        .map(|glob| Pattern::new(glob).map_err(|e| format!("bad pattern {}: {}", glob, e)))
+        .collect()
+}
+
+fn rules(names: &[String]) -> Result<Vec<Builtin>, String> {
+    names
+        .iter()
+        .map(|name| Builtin::from_name(name).ok_or_else(|| format!("unknown lint rule {}", name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+out = "site"
+exclude = ["drafts/**"]
+
+[html]
+code_class_prefix = "language-"
+filters = ["lazy-images"]
+
+[lint]
+disable = ["long-line"]
+max_line_length = 100
+
+[site]
+title = "Notes"
+
+[variables]
+version = "1.2"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.out, Some(PathBuf::from("site")));
+        assert_eq!(
+            config.exclude().unwrap(),
+            vec![Pattern::new("drafts/**").unwrap()]
+        );
+        assert_eq!(config.html().code_class_prefix, "language-");
+        assert_eq!(
+            config.html().filters.apply(String::from("<img />")),
+            "<img loading=\"lazy\" />"
+        );
+        assert_eq!(config.lint.disable().unwrap(), vec![Builtin::LongLine]);
+        assert_eq!(config.lint.max_line_length, Some(100));
+        assert_eq!(config.site.title.as_deref(), Some("Notes"));
+        assert_eq!(config.html().variables["version"], "1.2");
+
+        assert!(toml::from_str::<Config>("outt = \"typo\"").is_err());
+        let config: Config = toml::from_str("[lint]\nonly = [\"nope\"]").unwrap();
+        assert!(config.lint.only().is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use crate::config::Config;
+use crate::exit::Failure;
+use clap::Args;
+use markdown_to_html::batch;
+use markdown_to_html::frontmatter::FrontMatter;
+use markdown_to_html::notebook;
+use markdown_to_html::Document;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Jupyter notebook to convert
+    file: PathBuf,
+    /// Include what the code cells printed, images too
+    #[arg(long)]
+    outputs: bool,
+    /// File the html page is written to instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+pub fn run(args: ConvertArgs, config: &Config) -> ExitCode {
+    let blocks = match batch::read_text(&args.file) {
+        Ok(src) => notebook::parse(&src, args.outputs),
+        Err(e) => {
+            tracing::error!(path = %args.file.display(), "{}", e);
+            return Failure::Io.into();
+        }
+    };
+    let blocks = match blocks {
+        Ok(blocks) => blocks,
+        Err(e) => {
+            tracing::error!(path = %args.file.display(), "{}", e);
+            return Failure::Parse.into();
+        }
+    };
+    let doc = Document {
+        front_matter: FrontMatter::default(),
+        blocks,
+    };
+    let html = batch::render_document(&args.file, doc, &config.html());
+    match args.out {
+        Some(out) => {
+            if let Err(e) = fs::write(&out, html) {
+                tracing::error!(path = %out.display(), "{}", e);
+                return Failure::Io.into();
+            }
+        }
+        None => print!("{}", html),
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,56 @@
+use markdown_to_html::batch::BuildError;
+use std::process::ExitCode;
+
+// the exit codes are documented in the readme, build systems rely on them staying put
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Failure {
+    // a document did not parse, or a check like lint or fmt --check found problems
+    Parse = 1,
+    // a file could not be read or written, or the configuration is unusable
+    Io = 2,
+}
+
+impl Failure {
+    pub fn of(e: &BuildError) -> Failure {
+        match e {
+            BuildError::Io(_) => Failure::Io,
+            BuildError::Parse(_) => Failure::Parse,
+        }
+    }
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> Self {
+        ExitCode::from(failure as u8)
+    }
+}
+
+// remembers the worst thing that went wrong while working through many files
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Status {
+    failure: Option<Failure>,
+}
+
+impl Status {
+    pub fn fail(&mut self, failure: Failure) {
+        self.failure = self.failure.max(Some(failure));
+    }
+
+    pub fn exit_code(self) -> ExitCode {
+        self.failure.map_or(ExitCode::SUCCESS, ExitCode::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let mut status = Status::default();
+        assert_eq!(status.exit_code(), ExitCode::SUCCESS);
+        status.fail(Failure::Io);
+        status.fail(Failure::Parse);
+        assert_eq!(status.exit_code(), ExitCode::from(2));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::exit::{Failure, Status};
+use clap::Args;
+use markdown_to_html::batch::{self, BuildError};
+use markdown_to_html::{formatter, frontmatter, unicode, Document};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct FmtArgs {
+    /// Files or directories to format, reads stdin and writes stdout when empty
+    paths: Vec<PathBuf>,
+    /// Do not write anything, exit with an error if a file is not formatted
+    #[arg(long)]
+    check: bool,
+    /// Relabel footnotes 1, 2, 3 in the order they are first referenced
+    #[arg(long)]
+    renumber_footnotes: bool,
+}
+
+pub fn run(args: FmtArgs) -> ExitCode {
+    if args.paths.is_empty() {
+        return format_stdin(args.renumber_footnotes);
+    }
+
+    let mut status = Status::default();
+    for path in crate::markdown_files(&args.paths) {
+        let result = batch::read_text(&path)
+            .map_err(BuildError::from)
+            .and_then(|md| format(&md, args.renumber_footnotes).map(|formatted| (md, formatted)));
+        let (md, formatted) = match result {
+            Ok(pair) => pair,
+            Err(e) => {
+                tracing::error!(path = %path.display(), "{}", e);
+                status.fail(Failure::of(&e));
+                continue;
+            }
+        };
+        if md == formatted {
+            continue;
+        }
+        if args.check {
+            println!("{}", path.display());
+            status.fail(Failure::Parse);
+        } else if let Err(e) = fs::write(&path, formatted) {
+            tracing::error!(path = %path.display(), "{}", e);
+            status.fail(Failure::Io);
+        } else {
+            tracing::info!(path = %path.display(), "formatted");
+        }
+    }
+    status.exit_code()
+}
+
+fn format_stdin(renumber_footnotes: bool) -> ExitCode {
+    let mut bytes = vec![];
+    if let Err(e) = io::stdin().read_to_end(&mut bytes) {
+        tracing::error!("{}", e);
+        return Failure::Io.into();
+    }
+    let (md, _) = unicode::decode(&bytes);
+    match format(&md, renumber_footnotes) {
+        Ok(formatted) => {
+            print!("{}", formatted);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            tracing::error!("{}", e);
+            Failure::of(&e).into()
+        }
+    }
+}
+
+pub fn format(md: &str, renumber_footnotes: bool) -> Result<String, BuildError> {
+    let mut doc = Document::parse(md)?;
+    doc.normalize();
+    if renumber_footnotes {
+        doc.renumber_footnotes();
+    }
+    // front matter is passed through untouched
+    let (_, body) = frontmatter::split(md);
+    Ok(format!(
+        "{}{}",
+        &md[..md.len() - body.len()],
+        formatter::format(&doc.blocks)
+    ))
+}
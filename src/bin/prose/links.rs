@@ -0,0 +1,54 @@
+use crate::exit::{Failure, Status};
+use clap::Args;
+use markdown_to_html::batch;
+use markdown_to_html::links::{self, LinkKind};
+use markdown_to_html::Position;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct LinksArgs {
+    /// Files or directories to collect links from
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Only list links that leave the site
+    #[arg(long, conflicts_with = "internal")]
+    external: bool,
+    /// Only list links to other files or anchors on the site
+    #[arg(long)]
+    internal: bool,
+}
+
+// prints one tab separated `file:line:column  kind  url` row per link, for easy scripting
+pub fn run(args: LinksArgs) -> ExitCode {
+    let mut status = Status::default();
+    for path in crate::markdown_files(&args.paths) {
+        let src = match batch::read_text(&path) {
+            Ok(src) => src,
+            Err(e) => {
+                tracing::error!(path = %path.display(), "{}", e);
+                status.fail(Failure::Io);
+                continue;
+            }
+        };
+        for link in links::links(&src) {
+            if (args.external && !link.is_external()) || (args.internal && link.is_external()) {
+                continue;
+            }
+            let position = Position::of(&src, link.span.start);
+            let kind = match link.kind {
+                LinkKind::Link => "link",
+                LinkKind::Image => "image",
+            };
+            println!(
+                "{}:{}:{}\t{}\t{}",
+                path.display(),
+                position.line,
+                position.column,
+                kind,
+                link.url
+            );
+        }
+    }
+    status.exit_code()
+}
@@ -0,0 +1,89 @@
+use crate::config::Config;
+use crate::exit::{Failure, Status};
+use clap::Args;
+use markdown_to_html::batch;
+use markdown_to_html::lint::{self, Builtin, LintConfig};
+use markdown_to_html::Position;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// Files or directories to lint
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Turn off a rule, may be repeated
+    #[arg(long, value_parser = parse_rule)]
+    disable: Vec<Builtin>,
+    /// Only run these rules, may be repeated
+    #[arg(long, value_parser = parse_rule)]
+    only: Vec<Builtin>,
+    /// Longest line allowed by the long-line rule [default: 80]
+    #[arg(long)]
+    max_line_length: Option<usize>,
+}
+
+fn parse_rule(name: &str) -> Result<Builtin, String> {
+    Builtin::from_name(name).ok_or_else(|| {
+        let names = Builtin::ALL
+            .iter()
+            .map(|rule| rule.name())
+            .collect::<Vec<&str>>()
+            .join(", ");
+        format!("unknown rule, expected one of: {}", names)
+    })
+}
+
+pub fn run(args: LintArgs, config: &Config) -> ExitCode {
+    let config = match lint_config(&args, config) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Failure::Io.into();
+        }
+    };
+
+    let mut status = Status::default();
+    for path in crate::markdown_files(&args.paths) {
+        let src = match batch::read_text(&path) {
+            Ok(src) => src,
+            Err(e) => {
+                tracing::error!(path = %path.display(), "{}", e);
+                status.fail(Failure::Io);
+                continue;
+            }
+        };
+        for finding in lint::lint(&src, &config) {
+            let position = Position::of(&src, finding.span.start);
+            println!(
+                "{}:{}:{}: {}: {}",
+                path.display(),
+                position.line,
+                position.column,
+                finding.rule,
+                finding.message
+            );
+            status.fail(Failure::Parse);
+        }
+    }
+    status.exit_code()
+}
+
+fn lint_config(args: &LintArgs, config: &Config) -> Result<LintConfig, String> {
+    let mut rules = if !args.only.is_empty() {
+        args.only.clone()
+    } else if !config.lint.only.is_empty() {
+        config.lint.only()?
+    } else {
+        Builtin::ALL.to_vec()
+    };
+    let disabled = config.lint.disable()?;
+    rules.retain(|rule| !args.disable.contains(rule) && !disabled.contains(rule));
+    Ok(LintConfig {
+        rules,
+        max_line_length: args
+            .max_line_length
+            .or(config.lint.max_line_length)
+            .unwrap_or(LintConfig::default().max_line_length),
+    })
+}
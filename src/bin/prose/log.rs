@@ -0,0 +1,61 @@
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+// every `-v` turns the logging up a level and every `-q` turns it down,
+// starting from info so the usual progress messages show
+fn level(verbose: u8, quiet: u8) -> Option<Level> {
+    match i16::from(verbose) - i16::from(quiet) {
+        i16::MIN..=-3 => None,
+        -2 => Some(Level::ERROR),
+        -1 => Some(Level::WARN),
+        0 => Some(Level::INFO),
+        1 => Some(Level::DEBUG),
+        _ => Some(Level::TRACE),
+    }
+}
+
+// logs go to stderr so they never mix with a command's output
+pub fn init(verbose: u8, quiet: u8, format: LogFormat) {
+    let level = match level(verbose, quiet) {
+        Some(level) => level,
+        None => return,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .with_target(false);
+    // at `-vv` every parse and translate says how long it took when it's done,
+    // for finding the documents a build spends its time on. leaving the time
+    // out of text logs would leave these timings out too
+    match (format, level) {
+        (LogFormat::Text, Level::TRACE) => builder.with_span_events(FmtSpan::CLOSE).init(),
+        (LogFormat::Text, _) => builder.without_time().init(),
+        (LogFormat::Json, Level::TRACE) => builder.json().with_span_events(FmtSpan::CLOSE).init(),
+        (LogFormat::Json, _) => builder.json().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(level(0, 0), Some(Level::INFO));
+        assert_eq!(level(1, 0), Some(Level::DEBUG));
+        assert_eq!(level(5, 0), Some(Level::TRACE));
+        assert_eq!(level(0, 1), Some(Level::WARN));
+        assert_eq!(level(1, 3), Some(Level::ERROR));
+        assert_eq!(level(0, 3), None);
+    }
+}
@@ -0,0 +1,465 @@
+use crate::exit::Failure;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{DocumentSymbolRequest, FoldingRangeRequest, Formatting, Request as _};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolResponse, FoldingRange,
+    FoldingRangeProviderCapability, InitializeParams, OneOf, PublishDiagnosticsParams, Range,
+    ServerCapabilities, SymbolKind, TextDocumentIdentifier, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Url,
+};
+use markdown_to_html::incremental::IncrementalDocument;
+use markdown_to_html::linkcheck::{self, BrokenLink};
+use markdown_to_html::{HeadingLevel, Markdown, Span};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+// a language server over stdin and stdout: headings as document symbols,
+// broken links as diagnostics, folding for sections and long blocks, and
+// formatting the way `prose fmt` does
+pub fn run() -> ExitCode {
+    match serve() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            tracing::error!("{}", e);
+            Failure::Io.into()
+        }
+    }
+}
+
+fn serve() -> Result<(), Box<dyn Error + Sync + Send>> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    let params: InitializeParams =
+        serde_json::from_value(connection.initialize(serde_json::to_value(capabilities)?)?)?;
+    // links starting with `/` are looked up from the workspace
+    #[allow(deprecated)]
+    let root = params
+        .root_uri
+        .and_then(|uri| uri.to_file_path().ok())
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let mut server = Server {
+        connection: &connection,
+        root,
+        documents: HashMap::new(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.request(request)?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    // the writer thread only finishes once every sender is gone
+    drop(server);
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    root: PathBuf,
+    documents: HashMap<Url, IncrementalDocument>,
+}
+
+impl<'a> Server<'a> {
+    fn request(&mut self, request: Request) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let known = [
+            DocumentSymbolRequest::METHOD,
+            FoldingRangeRequest::METHOD,
+            Formatting::METHOD,
+        ];
+        if !known.contains(&request.method.as_str()) {
+            return self.respond(Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("prose does not handle {}", request.method),
+            ));
+        }
+        let uri = match serde_json::from_value::<DocumentParams>(request.params) {
+            Ok(params) => params.text_document.uri,
+            Err(e) => {
+                return self.respond(Response::new_err(
+                    request.id,
+                    ErrorCode::InvalidParams as i32,
+                    e.to_string(),
+                ))
+            }
+        };
+        let doc = self.documents.get(&uri);
+        let id = request.id;
+        self.respond(match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => Response::new_ok(
+                id,
+                doc.map(|doc| DocumentSymbolResponse::Nested(symbols(doc))),
+            ),
+            FoldingRangeRequest::METHOD => Response::new_ok(id, doc.map(folding_ranges)),
+            _ => Response::new_ok(id, doc.and_then(format)),
+        })
+    }
+
+    fn notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams = match params(notification) {
+                    Some(params) => params,
+                    None => return Ok(()),
+                };
+                let doc = IncrementalDocument::parse(&params.text_document.text);
+                self.documents.insert(params.text_document.uri.clone(), doc);
+                params.text_document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams = match params(notification) {
+                    Some(params) => params,
+                    None => return Ok(()),
+                };
+                let uri = params.text_document.uri;
+                if let Some(doc) = self.documents.get_mut(&uri) {
+                    for change in params.content_changes {
+                        match change.range {
+                            Some(range) => {
+                                let span = Span {
+                                    start: offset(doc.src(), range.start),
+                                    end: offset(doc.src(), range.end),
+                                };
+                                doc.edit(span, &change.text);
+                            }
+                            None => *doc = IncrementalDocument::parse(&change.text),
+                        }
+                    }
+                }
+                uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams = match params(notification) {
+                    Some(params) => params,
+                    None => return Ok(()),
+                };
+                self.documents.remove(&params.text_document.uri);
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        self.publish_diagnostics(uri)
+    }
+
+    fn publish_diagnostics(&self, uri: Url) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let doc = match self.documents.get(&uri) {
+            Some(doc) => doc,
+            None => return Ok(()),
+        };
+        let path = uri
+            .to_file_path()
+            .unwrap_or_else(|_| self.root.join("untitled.md"));
+        let params = PublishDiagnosticsParams {
+            diagnostics: diagnostics(doc, &self.root, &path),
+            uri,
+            version: None,
+        };
+        self.connection
+            .sender
+            .send(Message::Notification(Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            )))?;
+        Ok(())
+    }
+
+    fn respond(&self, response: Response) -> Result<(), Box<dyn Error + Sync + Send>> {
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+}
+
+// the one thing every request prose answers asks about
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentParams {
+    text_document: TextDocumentIdentifier,
+}
+
+// broken links are errors, stale anchors warnings like `prose check-links` has
+// them, and the place the parser gave up gets pointed out too
+fn diagnostics(doc: &IncrementalDocument, root: &Path, path: &Path) -> Vec<Diagnostic> {
+    let src = doc.src();
+    let diagnostic = |span: Span, severity, message: String| Diagnostic {
+        range: range(src, span),
+        severity: Some(severity),
+        source: Some(String::from("prose")),
+        message,
+        ..Default::default()
+    };
+    let link = |severity, broken: BrokenLink| {
+        diagnostic(
+            broken.link.span,
+            severity,
+            format!("{}: {}", broken.link.url, broken.reason),
+        )
+    };
+    let mut diagnostics = linkcheck::check_internal(root, path, src)
+        .into_iter()
+        .map(|broken| link(DiagnosticSeverity::ERROR, broken))
+        .chain(
+            linkcheck::check_anchors(root, path, src)
+                .into_iter()
+                .map(|broken| link(DiagnosticSeverity::WARNING, broken)),
+        )
+        .collect::<Vec<_>>();
+    let parsed = doc.blocks().last().map_or(0, |(_, span)| span.end);
+    if parsed < src.len() && !src[parsed..].trim().is_empty() {
+        let end = src[parsed..].find('\n').map_or(src.len(), |at| parsed + at);
+        diagnostics.push(diagnostic(
+            Span { start: parsed, end },
+            DiagnosticSeverity::ERROR,
+            String::from("could not parse markdown from here on"),
+        ));
+    }
+    diagnostics
+}
+
+// headings nest under the closest heading above them with a lower level
+#[allow(deprecated)]
+fn symbols(doc: &IncrementalDocument) -> Vec<DocumentSymbol> {
+    let src = doc.src();
+    let mut stack: Vec<(HeadingLevel, DocumentSymbol)> = vec![];
+    let mut top = vec![];
+    let close = |stack: &mut Vec<(HeadingLevel, DocumentSymbol)>, top: &mut Vec<DocumentSymbol>| {
+        let (_, symbol) = stack.pop().unwrap();
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.get_or_insert_with(Vec::new).push(symbol),
+            None => top.push(symbol),
+        }
+    };
+    for (block, span) in doc.blocks() {
+        if let Markdown::Heading(level, text) = block {
+            while stack.last().is_some_and(|(open, _)| open >= level) {
+                close(&mut stack, &mut top);
+            }
+            let range = range(src, trim_newline(src, *span));
+            stack.push((
+                *level,
+                DocumentSymbol {
+                    name: text.plain_text().trim().to_string(),
+                    detail: None,
+                    kind: SymbolKind::STRING,
+                    tags: None,
+                    deprecated: None,
+                    range,
+                    selection_range: range,
+                    children: None,
+                },
+            ));
+        }
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut top);
+    }
+    top
+}
+
+// each section runs up to the next heading of the same level or higher,
+// and lists, tables and code blocks over more than one line fold as well
+fn folding_ranges(doc: &IncrementalDocument) -> Vec<FoldingRange> {
+    let src = doc.src();
+    let line = |offset: usize| src[..offset].matches('\n').count() as u32;
+    let fold = |start: usize, end: usize| {
+        let (start_line, end_line) = (line(start), line(end));
+        (end_line > start_line).then(|| FoldingRange {
+            start_line,
+            end_line,
+            ..Default::default()
+        })
+    };
+    let blocks = doc.blocks();
+    let mut ranges = vec![];
+    for (i, (block, span)) in blocks.iter().enumerate() {
+        let span = trim_newline(src, *span);
+        match block {
+            Markdown::Heading(level, _) => {
+                let end = blocks[i + 1..]
+                    .iter()
+                    .find(|(block, _)| matches!(block, Markdown::Heading(next, _) if next <= level))
+                    .map_or(src.len(), |(_, next)| next.start);
+                let end = src[..end].trim_end().len();
+                ranges.extend(fold(span.start, end));
+            }
+            Markdown::UnorderedList(_)
+            | Markdown::OrderedList(_)
+            | Markdown::Table(_, _, _)
+            | Markdown::Alert(_, _)
+            | Markdown::Codeblock(_, _) => ranges.extend(fold(span.start, span.end)),
+            _ => {}
+        }
+    }
+    ranges
+}
+
+// one edit replacing the whole document, none when it is formatted already
+// or does not parse
+fn format(doc: &IncrementalDocument) -> Option<Vec<TextEdit>> {
+    let src = doc.src();
+    let formatted = crate::fmt::format(src, false).ok()?;
+    if formatted == src {
+        return Some(vec![]);
+    }
+    Some(vec![TextEdit {
+        range: range(
+            src,
+            Span {
+                start: 0,
+                end: src.len(),
+            },
+        ),
+        new_text: formatted,
+    }])
+}
+
+fn trim_newline(src: &str, span: Span) -> Span {
+    Span {
+        start: span.start,
+        end: span.start + src[span.start..span.end].trim_end_matches('\n').len(),
+    }
+}
+
+fn range(src: &str, span: Span) -> Range {
+    Range {
+        start: position(src, span.start),
+        end: position(src, span.end),
+    }
+}
+
+// lsp counts columns in utf-16 code units
+fn position(src: &str, offset: usize) -> lsp_types::Position {
+    let before = &src[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    lsp_types::Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+// the params of a notification, which has no response to put an error in. one
+// that doesn't make sense is warned about and ignored, the server keeps going
+fn params<P: serde::de::DeserializeOwned>(notification: Notification) -> Option<P> {
+    match serde_json::from_value(notification.params) {
+        Ok(params) => Some(params),
+        Err(e) => {
+            tracing::warn!(
+                "ignoring {} with invalid params: {}",
+                notification.method,
+                e
+            );
+            None
+        }
+    }
+}
+
+fn offset(src: &str, position: lsp_types::Position) -> usize {
+    let line_start = src
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = src[line_start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character {
+            return line_start + i;
+        }
+        units += c.len_utf16() as u32;
+    }
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let src = "a\nhé😀llo\n";
+        let position = lsp_types::Position {
+            line: 1,
+            character: 4,
+        };
+        assert_eq!(offset(src, position), 9);
+        assert_eq!(self::position(src, 9), position);
+        assert_eq!(
+            offset(
+                src,
+                lsp_types::Position {
+                    line: 1,
+                    character: 99
+                }
+            ),
+            12
+        );
+    }
+
+    #[test]
+    fn test_invalid_notification() {
+        let (connection, _client) = Connection::memory();
+        let mut server = Server {
+            connection: &connection,
+            root: PathBuf::new(),
+            documents: HashMap::new(),
+        };
+        for method in [
+            DidOpenTextDocument::METHOD,
+            DidChangeTextDocument::METHOD,
+            DidCloseTextDocument::METHOD,
+        ] {
+            let notification = Notification::new(method.to_string(), serde_json::json!({}));
+            assert!(server.notification(notification).is_ok());
+        }
+        assert!(server.documents.is_empty());
+    }
+
+    #[test]
+    fn test_symbols_and_folding() {
+        let doc = IncrementalDocument::parse(
+            "# One\ntext\n## Two\n- a\n- b\n\n# Three\n```\ncode\n```\n",
+        );
+        let symbols = symbols(&doc);
+        assert_eq!(
+            symbols
+                .iter()
+                .map(|symbol| symbol.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["One", "Three"]
+        );
+        assert_eq!(symbols[0].children.as_ref().unwrap()[0].name, "Two");
+        assert_eq!(
+            folding_ranges(&doc)
+                .iter()
+                .map(|range| (range.start_line, range.end_line))
+                .collect::<Vec<_>>(),
+            vec![(0, 4), (2, 4), (3, 4), (6, 9), (7, 9)]
+        );
+    }
+}
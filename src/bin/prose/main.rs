@@ -0,0 +1,257 @@
+mod build;
+mod check_links;
+mod config;
+mod convert;
+mod exit;
+mod fmt;
+mod links;
+mod lint;
+mod log;
+#[cfg(feature = "lsp")]
+mod lsp;
+mod meta;
+mod reload;
+mod serve;
+mod site;
+mod toc;
+mod watch;
+
+use clap::{ArgAction, Parser, Subcommand};
+use config::Config;
+use exit::Failure;
+use log::LogFormat;
+use markdown_to_html::batch::{self, BuildError};
+use markdown_to_html::frontmatter::FrontMatter;
+use markdown_to_html::{
+    assets, document, email as mail, embeds, mediawiki, telegram, textile, translator, unicode,
+    Document, Markdown,
+};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use walkdir::WalkDir;
+
+#[derive(Parser)]
+#[command(
+    name = "prose",
+    version,
+    about = "Turning your markdown into lovely HTML!"
+)]
+struct Cli {
+    /// Read default options from this file instead of ./prose.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Log more, may be repeated
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Log less, may be repeated
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    quiet: u8,
+    /// How log lines written to stderr are formatted
+    #[arg(long, global = true, value_enum, default_value_t)]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a single markdown file to stdout
+    Render {
+        file: PathBuf,
+        /// Mark each block with a data-sourcepos="line:column" attribute
+        #[arg(long)]
+        sourcepos: bool,
+        /// Write inline-styled HTML without classes, for email newsletters
+        #[arg(long)]
+        email: bool,
+        /// Write Telegram MarkdownV2, for bots posting the document as a message
+        #[arg(long, conflicts_with = "email")]
+        telegram: bool,
+        /// Move every heading this many levels deeper (or shallower when negative)
+        #[arg(
+            long,
+            value_name = "DELTA",
+            allow_negative_numbers = true,
+            default_value_t = 0
+        )]
+        shift_headings: isize,
+        /// Put images into the HTML as data: URLs, fetching remote ones, for a self-contained file
+        #[arg(long)]
+        embed_images: bool,
+    },
+    /// Convert every markdown file in a directory, preserving the tree
+    Build(build::BuildArgs),
+    /// Build a directory, then re-render files whenever they are saved
+    Watch(build::BuildArgs),
+    /// Serve a directory over http, rendering markdown files on request
+    Serve(serve::ServeArgs),
+    /// Generate a static site with index pages for every tag and year
+    Site(site::SiteArgs),
+    /// Rewrite markdown files in a consistent style
+    Fmt(fmt::FmtArgs),
+    /// Check markdown files for common problems
+    Lint(lint::LintArgs),
+    /// Print the heading outline of a markdown file
+    Toc(toc::TocArgs),
+    /// List every link and image url along with where it appears
+    Links(links::LinksArgs),
+    /// Report links and images that point at files or urls that are not there
+    CheckLinks(check_links::CheckLinksArgs),
+    /// Print the front matter of a markdown file
+    Meta(meta::MetaArgs),
+    /// Convert a jupyter notebook into an html page
+    Convert(convert::ConvertArgs),
+    /// Run a language server for editors over stdin and stdout
+    #[cfg(feature = "lsp")]
+    Lsp,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    log::init(cli.verbose, cli.quiet, cli.log_format);
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Failure::Io.into();
+        }
+    };
+    match cli.command {
+        Command::Render {
+            file,
+            sourcepos,
+            email,
+            telegram,
+            shift_headings,
+            embed_images,
+        } => render(
+            &file,
+            sourcepos,
+            email,
+            telegram,
+            shift_headings,
+            embed_images,
+            &config,
+        ),
+        Command::Build(args) => build::run(args, &config),
+        Command::Watch(args) => watch::run(args, &config),
+        Command::Serve(args) => serve::run(args, &config),
+        Command::Site(args) => site::run(args, &config),
+        Command::Fmt(args) => fmt::run(args),
+        Command::Lint(args) => lint::run(args, &config),
+        Command::Toc(args) => toc::run(args),
+        Command::Links(args) => links::run(args),
+        Command::CheckLinks(args) => check_links::run(args),
+        Command::Meta(args) => meta::run(args),
+        Command::Convert(args) => convert::run(args, &config),
+        #[cfg(feature = "lsp")]
+        Command::Lsp => lsp::run(),
+    }
+}
+
+fn render(
+    file: &Path,
+    sourcepos: bool,
+    email: bool,
+    telegram: bool,
+    shift_headings: isize,
+    embed_images: bool,
+    config: &Config,
+) -> ExitCode {
+    #[cfg(feature = "parallel")]
+    use translator::translate_parallel as translate;
+    #[cfg(not(feature = "parallel"))]
+    use translator::translate_with_options as translate;
+
+    let parsed = batch::read_text(file)
+        .map_err(BuildError::from)
+        .and_then(|src| {
+            let src = if config.nfc {
+                unicode::nfc(&src).into_owned()
+            } else {
+                src
+            };
+            let mut doc = match markup(file) {
+                Some(parse) => Document {
+                    front_matter: FrontMatter::default(),
+                    blocks: parse(&src),
+                },
+                None => Document::parse(&src)?,
+            };
+            // embedded notes have no place in this file's source to point back to
+            if !sourcepos {
+                let folder = file.parent().unwrap_or(Path::new(""));
+                let name = Path::new(file.file_name().unwrap_or_default());
+                embeds::embed_files(&mut doc.blocks, folder, name);
+            }
+            doc.shift_headings(shift_headings);
+            if embed_images {
+                assets::embed_images(&mut doc.blocks, file);
+            }
+            Ok((doc, src))
+        });
+    match parsed {
+        Ok((doc, src)) => {
+            let options = config.html().with_front_matter(&doc.front_matter);
+            if email {
+                print!("{}", mail::translate(&doc.blocks, &options));
+            } else if telegram {
+                println!("{}", telegram::translate(&doc.blocks, &options));
+            } else if sourcepos && markup(file).is_none() {
+                let spans = document::spanned_blocks(&src)
+                    .into_iter()
+                    .map(|(_, span)| span)
+                    .collect::<Vec<_>>();
+                print!(
+                    "{}",
+                    translator::translate_with_source_positions(
+                        &doc.blocks,
+                        &spans,
+                        &src,
+                        &options
+                    )
+                );
+            } else {
+                print!("{}", translate(&doc.blocks, &options));
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            tracing::error!(path = %file.display(), "{}", e);
+            Failure::of(&e).into()
+        }
+    }
+}
+
+// other markup `prose render` reads, picked by the file's extension
+fn markup(path: &Path) -> Option<fn(&str) -> Vec<Markdown>> {
+    match path.extension()?.to_str()? {
+        "textile" => Some(textile::parse),
+        "wiki" | "mediawiki" => Some(mediawiki::parse),
+        _ => None,
+    }
+}
+
+// expands directories into the markdown files found underneath them
+pub fn markdown_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .flat_map(|path| {
+            if path.is_dir() {
+                WalkDir::new(path)
+                    .sort_by_file_name()
+                    .into_iter()
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.into_path())
+                    .filter(|path| path.is_file() && batch::is_markdown(path))
+                    .collect()
+            } else {
+                vec![path.clone()]
+            }
+        })
+        .collect()
+}
+
+pub fn parse_file(path: &Path) -> Result<Document, BuildError> {
+    Ok(Document::parse(&batch::read_text(path)?)?)
+}
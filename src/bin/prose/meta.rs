@@ -0,0 +1,50 @@
+use crate::exit::Failure;
+use clap::Args;
+use markdown_to_html::frontmatter::Value;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct MetaArgs {
+    /// Markdown file to read the front matter from
+    file: PathBuf,
+    /// Print the front matter as a json object
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: MetaArgs) -> ExitCode {
+    let doc = match crate::parse_file(&args.file) {
+        Ok(doc) => doc,
+        Err(e) => {
+            tracing::error!(path = %args.file.display(), "{}", e);
+            return Failure::of(&e).into();
+        }
+    };
+    if args.json {
+        let object = doc
+            .front_matter
+            .iter()
+            .map(|(key, value)| (key.clone(), to_json(value)))
+            .collect::<serde_json::Map<String, serde_json::Value>>();
+        println!("{}", serde_json::to_string_pretty(&object).unwrap());
+    } else {
+        for (key, value) in doc.front_matter.iter() {
+            println!("{}: {}", key, to_json(value));
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::String(s) => serde_json::Value::from(s.as_str()),
+        Value::Bool(b) => serde_json::Value::from(*b),
+        // whole numbers should read `3` rather than `3.0`
+        Value::Number(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => {
+            serde_json::Value::from(*n as i64)
+        }
+        Value::Number(n) => serde_json::Value::from(*n),
+        Value::List(values) => values.iter().map(to_json).collect(),
+    }
+}
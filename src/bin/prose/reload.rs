@@ -0,0 +1,90 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const EVENTS_PATH: &str = "/__prose/events";
+
+// tiny client that listens on the event stream and refreshes the page
+const SNIPPET: &str = "<script>new EventSource(\"/__prose/events\").onmessage = function () { location.reload(); };</script>";
+
+// keeps track of every browser tab connected to the event stream
+#[derive(Clone, Default)]
+pub struct Reloader {
+    clients: Arc<Mutex<Vec<Sender<()>>>>,
+}
+
+impl Reloader {
+    pub fn subscribe(&self) -> Receiver<()> {
+        let (tx, rx) = mpsc::channel();
+        self.clients.lock().unwrap().push(tx);
+        rx
+    }
+
+    // tabs that have gone away have dropped their receiver, so we forget them here
+    pub fn reload_all(&self) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(()).is_ok());
+    }
+
+    // watches `root` on a background thread, reloading every client on each change
+    pub fn watch(&self, root: &Path) -> notify::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+        let reloader = self.clone();
+        thread::spawn(move || {
+            // the watcher stops once dropped, so it lives as long as this thread
+            let _watcher = watcher;
+            for event in rx.into_iter().flatten() {
+                if let EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) =
+                    event.kind
+                {
+                    reloader.reload_all();
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+pub fn inject(html: String) -> String {
+    match html.rfind("</body>") {
+        Some(at) => {
+            let mut html = html;
+            html.insert_str(at, SNIPPET);
+            html
+        }
+        None => html + SNIPPET,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject() {
+        assert_eq!(
+            inject(String::from("<body><p>hi</p></body>")),
+            format!("<body><p>hi</p>{}</body>", SNIPPET)
+        );
+        assert_eq!(
+            inject(String::from("<p>hi</p>")),
+            format!("<p>hi</p>{}", SNIPPET)
+        );
+    }
+
+    #[test]
+    fn test_reload_all_forgets_closed_clients() {
+        let reloader = Reloader::default();
+        let open = reloader.subscribe();
+        drop(reloader.subscribe());
+        reloader.reload_all();
+        assert_eq!(open.try_recv(), Ok(()));
+        assert_eq!(reloader.clients.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::config::Config;
+use crate::exit::Failure;
+use crate::reload::{self, Reloader};
+use clap::Args;
+use markdown_to_html::options::HtmlOptions;
+use markdown_to_html::{batch, escape};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::Duration;
+
+// how often an idle event stream is pinged, which is also how we notice closed tabs
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Directory to serve
+    dir: PathBuf,
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+    /// Do not refresh the browser when files change
+    #[arg(long)]
+    no_live_reload: bool,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn html(body: String) -> Self {
+        Response {
+            status: "200 OK",
+            content_type: "text/html; charset=utf-8",
+            location: None,
+            body: body.into_bytes(),
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            location: None,
+            body: status.as_bytes().to_vec(),
+        }
+    }
+
+    fn redirect(location: String) -> Self {
+        Response {
+            status: "301 Moved Permanently",
+            content_type: "text/plain; charset=utf-8",
+            location: Some(location),
+            body: vec![],
+        }
+    }
+}
+
+pub fn run(args: ServeArgs, config: &Config) -> ExitCode {
+    let options = config.html();
+    let root = match args.dir.canonicalize() {
+        Ok(root) => root,
+        Err(e) => {
+            tracing::error!(path = %args.dir.display(), "{}", e);
+            return Failure::Io.into();
+        }
+    };
+    let listener = match TcpListener::bind((args.host.as_str(), args.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("could not listen on {}:{}: {}", args.host, args.port, e);
+            return Failure::Io.into();
+        }
+    };
+    let reloader = if args.no_live_reload {
+        None
+    } else {
+        let reloader = Reloader::default();
+        if let Err(e) = reloader.watch(&root) {
+            tracing::warn!("live reload disabled, could not watch files: {}", e);
+        }
+        Some(reloader)
+    };
+    tracing::info!(
+        "serving {} at http://{}:{}/",
+        root.display(),
+        args.host,
+        args.port
+    );
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let root = root.clone();
+                let reloader = reloader.clone();
+                let options = options.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream, &root, reloader.as_ref(), &options) {
+                        tracing::warn!("{}", e);
+                    }
+                });
+            }
+            Err(e) => tracing::warn!("{}", e),
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn handle(
+    mut stream: TcpStream,
+    root: &Path,
+    reloader: Option<&Reloader>,
+    options: &HtmlOptions,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // we do not care about any of the headers, but they have to be read off the socket
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next());
+    let mut response = match (method, target, reloader) {
+        (Some("GET"), Some(reload::EVENTS_PATH), Some(reloader)) => {
+            return stream_events(stream, reloader)
+        }
+        (Some("GET"), Some(target), _) => respond(root, target, options),
+        _ => Response::error("405 Method Not Allowed"),
+    };
+    tracing::debug!(
+        method = method.unwrap_or_default(),
+        target = target.unwrap_or_default(),
+        status = response.status,
+        "request"
+    );
+    if reloader.is_some() && response.content_type.starts_with("text/html") {
+        if let Ok(html) = String::from_utf8(response.body) {
+            response.body = reload::inject(html).into_bytes();
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "html is not utf-8",
+            ));
+        }
+    }
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    if let Some(location) = response.location {
+        write!(stream, "Location: {}\r\n", location)?;
+    }
+    write!(stream, "\r\n")?;
+    stream.write_all(&response.body)
+}
+
+// holds the connection open, sending an event every time a watched file changes
+fn stream_events(mut stream: TcpStream, reloader: &Reloader) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n"
+    )?;
+    stream.flush()?;
+    let events = reloader.subscribe();
+    loop {
+        match events.recv_timeout(KEEPALIVE) {
+            Ok(()) => write!(stream, "data: reload\n\n")?,
+            Err(RecvTimeoutError::Timeout) => write!(stream, ": ping\n\n")?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        stream.flush()?;
+    }
+}
+
+fn respond(root: &Path, target: &str, options: &HtmlOptions) -> Response {
+    let url_path = target.split(['?', '#']).next().unwrap_or("/");
+    let path = match resolve(root, url_path) {
+        Some(path) => path,
+        None => return Response::error("404 Not Found"),
+    };
+
+    if path.is_dir() {
+        // relative links in the listing only work when the url ends in a slash
+        if !url_path.ends_with('/') {
+            return Response::redirect(format!("{}/", url_path));
+        }
+        match listing(&path, url_path) {
+            Ok(html) => Response::html(html),
+            Err(_) => Response::error("500 Internal Server Error"),
+        }
+    } else if !path.is_file() {
+        Response::error("404 Not Found")
+    } else if batch::is_markdown(&path) {
+        match batch::render_file(&path, options) {
+            Ok(html) => Response::html(html),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), "{}", e);
+                Response::html(batch::page("error", &format!("<p>{}</p>", e)))
+            }
+        }
+    } else {
+        match fs::read(&path) {
+            Ok(body) => Response {
+                status: "200 OK",
+                content_type: content_type(&path),
+                location: None,
+                body,
+            },
+            Err(_) => Response::error("500 Internal Server Error"),
+        }
+    }
+}
+
+// maps a url path onto a path inside root, refusing anything that would escape it
+fn resolve(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(url_path)?;
+    let mut resolved = root.to_path_buf();
+    for part in decoded.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            part if part.contains('\\') => return None,
+            part => resolved.push(part),
+        }
+    }
+    Some(resolved)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn listing(dir: &Path, url_path: &str) -> io::Result<String> {
+    let mut entries = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_dir = entry.path().is_dir();
+            (!is_dir, name)
+        })
+        .filter(|(_, name)| !name.starts_with('.'))
+        .collect::<Vec<(bool, String)>>();
+    // directories first, then files, each alphabetical
+    entries.sort();
+
+    let mut items = String::new();
+    if url_path != "/" {
+        items.push_str("<li><a href=\"../\">../</a></li>");
+    }
+    for (is_file, name) in entries {
+        let name = escape(&name);
+        let suffix = if is_file { "" } else { "/" };
+        items.push_str(&format!(
+            "<li><a href=\"{}{}\">{}{}</a></li>",
+            name, suffix, name, suffix
+        ));
+    }
+    let title = format!("Index of {}", escape(url_path));
+    Ok(batch::page(
+        &title,
+        &format!("<h1>{}</h1><ul>{}</ul>", title, items),
+    ))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let root = Path::new("/srv/notes");
+        assert_eq!(resolve(root, "/"), Some(PathBuf::from("/srv/notes")));
+        assert_eq!(
+            resolve(root, "/posts/first%20post.md"),
+            Some(PathBuf::from("/srv/notes/posts/first post.md"))
+        );
+        assert_eq!(resolve(root, "/posts/../../etc/passwd"), None);
+        assert_eq!(resolve(root, "/%2e%2e/secret"), None);
+        assert_eq!(resolve(root, "/bad%zz"), None);
+    }
+}
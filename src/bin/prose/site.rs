@@ -0,0 +1,67 @@
+use crate::build::BuildArgs;
+use crate::config::Config;
+use crate::exit::{Failure, Status};
+use clap::Args;
+use markdown_to_html::site::{self, SiteOptions, Templates};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct SiteArgs {
+    #[command(flatten)]
+    build: BuildArgs,
+    /// Directory holding page.html and list.html templates, built in ones are used otherwise
+    #[arg(long)]
+    templates: Option<PathBuf>,
+    /// Site name shown in page titles and navigation [default: Home]
+    #[arg(long)]
+    title: Option<String>,
+    /// Address the site is published at, needed for sitemap.xml
+    #[arg(long)]
+    url: Option<String>,
+}
+
+impl SiteArgs {
+    fn options(&self, config: &Config) -> Result<SiteOptions, String> {
+        let templates = match self.templates.as_ref().or(config.site.templates.as_ref()) {
+            Some(dir) => Templates::load(dir).map_err(|e| format!("{}: {}", dir.display(), e))?,
+            None => Templates::default(),
+        };
+        Ok(SiteOptions {
+            build: self.build.options(config)?,
+            templates,
+            title: self
+                .title
+                .clone()
+                .or_else(|| config.site.title.clone())
+                .unwrap_or_else(|| String::from("Home")),
+            url: self.url.clone().or_else(|| config.site.url.clone()),
+        })
+    }
+}
+
+pub fn run(args: SiteArgs, config: &Config) -> ExitCode {
+    let options = match args.options(config) {
+        Ok(options) => options,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Failure::Io.into();
+        }
+    };
+    let report = site::generate(&args.build.dir, &args.build.out(config), &options);
+    let mut status = Status::default();
+    for (path, e) in &report.failed {
+        tracing::error!(path = %path.display(), "{}", e);
+        status.fail(Failure::of(e));
+    }
+    tracing::info!(
+        pages = report.converted.len(),
+        skipped = report.skipped.len(),
+        failed = report.failed.len(),
+        "generated {} page(s), {} skipped, {} failed",
+        report.converted.len(),
+        report.skipped.len(),
+        report.failed.len()
+    );
+    status.exit_code()
+}
@@ -0,0 +1,50 @@
+use crate::exit::Failure;
+use clap::Args;
+use markdown_to_html::toc;
+use serde_json::json;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Args)]
+pub struct TocArgs {
+    /// Markdown file to read the headings from
+    file: PathBuf,
+    /// Print the outline as json instead of a markdown list
+    #[arg(long)]
+    json: bool,
+    /// Print the outline as a collapsible html <details> widget
+    #[arg(long, conflicts_with = "json")]
+    html: bool,
+    /// Put 1., 1.1, 1.1.1 numbers in front of the headings
+    #[arg(long)]
+    number: bool,
+}
+
+pub fn run(args: TocArgs) -> ExitCode {
+    let doc = match crate::parse_file(&args.file) {
+        Ok(doc) => doc,
+        Err(e) => {
+            tracing::error!(path = %args.file.display(), "{}", e);
+            return Failure::of(&e).into();
+        }
+    };
+    let mut entries = toc::toc(&doc.blocks);
+    if args.number {
+        let numbers = toc::numbers(&entries);
+        for (entry, number) in entries.iter_mut().zip(numbers) {
+            entry.text = format!("{} {}", number, entry.text);
+        }
+    }
+    if args.json {
+        let entries = entries
+            .iter()
+            .map(|entry| json!({"level": entry.level, "text": entry.text, "slug": entry.slug}))
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&entries).unwrap());
+    } else if args.html {
+        println!("{}", toc::to_html(&entries));
+    } else {
+        print!("{}", toc::to_markdown(&entries));
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,85 @@
+use crate::build::{self, BuildArgs};
+use crate::config::Config;
+use crate::exit::Failure;
+use markdown_to_html::batch;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// editors tend to fire several events per save, so we gather everything
+// that arrives within this window and render each file once
+const SETTLE: Duration = Duration::from_millis(100);
+
+pub fn run(args: BuildArgs, config: &Config) -> ExitCode {
+    let dir = match args.dir.canonicalize() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::error!(path = %args.dir.display(), "{}", e);
+            return Failure::Io.into();
+        }
+    };
+    let options = match args.options(config) {
+        Ok(options) => options,
+        Err(e) => {
+            tracing::error!("{}", e);
+            return Failure::Io.into();
+        }
+    };
+    let out = args.out(config);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            tracing::error!("could not start watcher: {}", e);
+            return Failure::Io.into();
+        }
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::Recursive) {
+        tracing::error!("could not watch {}: {}", dir.display(), e);
+        return Failure::Io.into();
+    }
+
+    build::run(args, config);
+    // later saves only reload the files that changed
+    let mut links = batch::link_map(&dir, &options);
+    tracing::info!("watching {} for changes...", dir.display());
+
+    while let Ok(event) = rx.recv() {
+        let mut changed = BTreeSet::new();
+        collect(event, &mut changed);
+        while let Ok(event) = rx.recv_timeout(SETTLE) {
+            collect(event, &mut changed);
+        }
+
+        for path in changed {
+            let relative = match path.strip_prefix(&dir) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            if !batch::is_markdown(&path) || !path.is_file() || !options.matches(relative) {
+                continue;
+            }
+            match batch::convert_file(&dir, &out, relative, &mut links, &options) {
+                Ok(true) => tracing::info!(path = %relative.display(), "rendered"),
+                Ok(false) => tracing::info!(path = %relative.display(), "skipped draft"),
+                Err(e) => tracing::error!(path = %relative.display(), "{}", e),
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn collect(event: notify::Result<notify::Event>, changed: &mut BTreeSet<PathBuf>) {
+    match event {
+        Ok(event) => {
+            if let EventKind::Create(_) | EventKind::Modify(_) = event.kind {
+                changed.extend(event.paths);
+            }
+        }
+        Err(e) => tracing::warn!("watch error: {}", e),
+    }
+}
@@ -0,0 +1,181 @@
+use crate::assets;
+use crate::batch;
+use crate::document::Document;
+use crate::slug::slugify;
+use crate::toc;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::Url;
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+// what a multi-file build knows about each of its documents, so links between
+// them can point at the page that was really written and the heading's real anchor
+#[derive(Clone, Debug, Default)]
+pub struct LinkMap {
+    targets: BTreeMap<PathBuf, Target>,
+}
+
+#[derive(Clone, Debug)]
+struct Target {
+    output: PathBuf,
+    slugs: Vec<String>,
+}
+
+impl LinkMap {
+    // `source` and `output` are relative to the content and output directories
+    pub fn insert(&mut self, source: &Path, output: &Path, doc: &Document) {
+        self.targets.insert(
+            source.to_path_buf(),
+            Target {
+                output: output.to_path_buf(),
+                slugs: toc::toc(&doc.blocks)
+                    .into_iter()
+                    .map(|entry| entry.slug)
+                    .collect(),
+            },
+        );
+    }
+
+    // builds the map for a set of documents that get their usual output paths
+    pub fn from_documents<'a>(docs: impl IntoIterator<Item = (&'a Path, &'a Document)>) -> Self {
+        let mut map = LinkMap::default();
+        for (source, doc) in docs {
+            map.insert(source, &batch::output_file(source, &doc.front_matter), doc);
+        }
+        map
+    }
+
+    // the url a link written in the document `from` should really have,
+    // none when it does not point at a document in the build
+    pub fn resolve(&self, from: &Path, url: &str) -> Option<String> {
+        let (rest, fragment) = match url.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (url, None),
+        };
+        let (file, query) = match rest.split_once('?') {
+            Some((file, query)) => (file, format!("?{}", query)),
+            None => (rest, String::new()),
+        };
+        let source = if file.is_empty() {
+            from.to_path_buf()
+        } else {
+            assets::resolve(from, file)?
+        };
+        let target = self.targets.get(&source)?;
+        let page = if file.is_empty() {
+            String::new()
+        } else {
+            let here = self.targets.get(from).map_or_else(
+                || batch::output_path(Path::new(""), from),
+                |from| from.output.clone(),
+            );
+            relative_url(&here, &target.output)
+        };
+        let fragment = fragment.map_or(String::new(), |fragment| {
+            format!("#{}", anchor(&target.slugs, fragment))
+        });
+        Some(format!("{}{}{}", page, query, fragment))
+    }
+
+    // points every link in `blocks` (read from `from`) that reaches another
+    // document of the build at that document's page
+    pub fn rewrite(&self, blocks: &mut [Markdown], from: &Path) {
+        for text in blocks.iter_mut().flat_map(Markdown::texts_mut) {
+            for inline in text.iter_mut() {
+                if let MarkdownInline::Link(_, url) = inline {
+                    if let Some(resolved) = self.resolve(from, &url.decoded()) {
+                        *url = Url::from(resolved);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a fragment can be the anchor itself or the heading it came from, `#Getting Started`
+fn anchor<'a>(slugs: &'a [String], fragment: &'a str) -> &'a str {
+    if slugs.iter().any(|slug| slug == fragment) {
+        return fragment;
+    }
+    let slug = slugify(fragment);
+    slugs
+        .iter()
+        .find(|candidate| **candidate == slug)
+        .map_or(fragment, String::as_str)
+}
+
+// how to get from the page at `from` to the one at `to`, both relative to the output directory
+fn relative_url(from: &Path, to: &Path) -> String {
+    let names = |path: &Path| -> Vec<String> {
+        path.components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect()
+    };
+    let (from, to) = (names(from.parent().unwrap_or(Path::new(""))), names(to));
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    format!(
+        "{}{}",
+        "../".repeat(from.len() - common),
+        to[common..].join("/")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(md: &str) -> Document {
+        Document::parse(md).unwrap()
+    }
+
+    #[test]
+    fn test_relative_url() {
+        assert_eq!(
+            relative_url(Path::new("posts/a.html"), Path::new("posts/b.html")),
+            "b.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("posts/2021/a.html"), Path::new("about.html")),
+            "../../about.html"
+        );
+        assert_eq!(
+            relative_url(Path::new("index.html"), Path::new("guide/setup.html")),
+            "guide/setup.html"
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let guide = doc("---\nslug: getting-started\n---\n# Setup Guide\n## Install it\n");
+        let index = doc("# Home\n");
+        let map = LinkMap::from_documents(vec![
+            (Path::new("docs/guide.md"), &guide),
+            (Path::new("index.md"), &index),
+        ]);
+
+        let from = Path::new("index.md");
+        assert_eq!(
+            map.resolve(from, "docs/guide.md#install-it").as_deref(),
+            Some("docs/getting-started.html#install-it")
+        );
+        assert_eq!(
+            map.resolve(from, "./docs/guide.md?v=1#Install It")
+                .as_deref(),
+            Some("docs/getting-started.html?v=1#install-it")
+        );
+        assert_eq!(
+            map.resolve(Path::new("docs/guide.md"), "../index.md")
+                .as_deref(),
+            Some("../index.html")
+        );
+        assert_eq!(map.resolve(from, "#Home").as_deref(), Some("#home"));
+        assert_eq!(map.resolve(from, "#nowhere").as_deref(), Some("#nowhere"));
+        assert_eq!(map.resolve(from, "missing.md"), None);
+        assert_eq!(map.resolve(from, "https://example.com/a.md"), None);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::document;
+use crate::formatter;
+use crate::frontmatter;
+use crate::prelude::*;
+use crate::Markdown;
+use crate::Span;
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    // the whole `---` fenced block at the top, fences included
+    FrontMatter,
+    // spaces in front of a nested list item
+    Indent,
+    // `#`, `-`, `1.`, a code fence, `[^label]:` or a table's `---` row cells
+    Marker,
+    // the pipes between table cells
+    Delimiter,
+    Whitespace,
+    // inline markdown, exactly as written
+    Text,
+    Newline,
+    // whatever follows the point where the parser gave up
+    Unparsed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub block: Markdown,
+    pub span: Span,
+    pub tokens: Vec<Token>,
+}
+
+// every byte of a document accounted for, so tools can change one block
+// and write the rest back exactly as the author left it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxTree {
+    src: String,
+    front_matter: Option<Token>,
+    nodes: Vec<Node>,
+    unparsed: Option<Token>,
+}
+
+impl SyntaxTree {
+    pub fn parse(src: &str) -> SyntaxTree {
+        let (_, body) = frontmatter::split(src);
+        let body_offset = src.len() - body.len();
+        let nodes = document::spanned_blocks(src)
+            .into_iter()
+            .map(|(block, span)| Node {
+                tokens: lex(src, &block, span),
+                block,
+                span,
+            })
+            .collect::<Vec<Node>>();
+        let parsed = nodes.last().map_or(body_offset, |node| node.span.end);
+        SyntaxTree {
+            src: src.to_string(),
+            front_matter: token(TokenKind::FrontMatter, 0, body_offset),
+            nodes,
+            unparsed: token(TokenKind::Unparsed, parsed, src.len()),
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn text(&self, span: Span) -> &str {
+        &self.src[span.start..span.end]
+    }
+
+    // every token in order, together they spell out the whole source
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.front_matter
+            .iter()
+            .chain(self.nodes.iter().flat_map(|node| &node.tokens))
+            .chain(self.unparsed.iter())
+    }
+
+    // swaps the block at `index` for `block` in the formatter's spelling,
+    // leaving every other byte where it was
+    pub fn replace(&mut self, index: usize, block: &Markdown) {
+        let span = self.nodes[index].span;
+        let mut src = self.src.clone();
+        src.replace_range(span.start..span.end, &formatter::format_block(block));
+        *self = SyntaxTree::parse(&src);
+    }
+}
+
+// the source, byte for byte
+impl fmt::Display for SyntaxTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+fn token(kind: TokenKind, start: usize, end: usize) -> Option<Token> {
+    if start < end {
+        Some(Token {
+            kind,
+            span: Span { start, end },
+        })
+    } else {
+        None
+    }
+}
+
+// breaks a block up line by line into its markers, whitespace and text
+fn lex(src: &str, block: &Markdown, span: Span) -> Vec<Token> {
+    let mut lexer = Lexer {
+        src,
+        at: span.start,
+        tokens: vec![],
+    };
+    let lines = src[span.start..span.end].split_inclusive('\n').count();
+    for i in 0..lines {
+        let end = src[lexer.at..span.end]
+            .find('\n')
+            .map_or(span.end, |newline| lexer.at + newline);
+        match block {
+            Markdown::Codeblock(_, _) => {
+                if i == 0 && src[lexer.at..end].starts_with("```") {
+                    lexer.push(TokenKind::Marker, lexer.at + 3);
+                }
+                let fence = if i == lines - 1 && src[..end].ends_with("```") {
+                    end - 3
+                } else {
+                    end
+                };
+                lexer.push(TokenKind::Text, fence);
+                lexer.push(TokenKind::Marker, end);
+            }
+            Markdown::Table(_, _, _) => lexer.table_row(end, i == 1),
+            _ => {
+                lexer.indent(end);
+                let marker = match block {
+                    Markdown::Heading(_, _) => {
+                        src[lexer.at..end].len() - src[lexer.at..end].trim_start_matches('#').len()
+                    }
+                    Markdown::UnorderedList(_) | Markdown::OrderedList(_) => {
+                        let digits = src[lexer.at..end]
+                            .find(|c: char| !c.is_ascii_digit())
+                            .unwrap_or(0);
+                        match src[lexer.at + digits..end].chars().next() {
+                            Some('.') if digits > 0 => digits + 1,
+                            Some('-') if digits == 0 => 1,
+                            _ => 0,
+                        }
+                    }
+                    Markdown::FootnoteDef(_, _) => {
+                        src[lexer.at..end].find("]:").map_or(0, |at| at + 2)
+                    }
+                    // `> [!NOTE]` is all marker, the lines under it just the `>`
+                    Markdown::Alert(_, _) if i == 0 => end - lexer.at,
+                    Markdown::Alert(_, _) => usize::from(src[lexer.at..end].starts_with('>')),
+                    _ => 0,
+                };
+                lexer.push(TokenKind::Marker, lexer.at + marker);
+                lexer.whitespace(end);
+                lexer.text(end);
+            }
+        }
+        lexer.push(TokenKind::Newline, (end + 1).min(span.end));
+    }
+    lexer.tokens
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    at: usize,
+    tokens: Vec<Token>,
+}
+
+impl<'a> Lexer<'a> {
+    // a token running from where the last one stopped up to `end`, if that is anywhere
+    fn push(&mut self, kind: TokenKind, end: usize) {
+        if let Some(token) = token(kind, self.at, end) {
+            self.tokens.push(token);
+            self.at = end;
+        }
+    }
+
+    fn indent(&mut self, end: usize) {
+        let rest = &self.src[self.at..end];
+        self.push(
+            TokenKind::Indent,
+            self.at + rest.len() - rest.trim_start_matches(' ').len(),
+        );
+    }
+
+    fn whitespace(&mut self, end: usize) {
+        let rest = &self.src[self.at..end];
+        self.push(
+            TokenKind::Whitespace,
+            self.at + rest.len() - rest.trim_start().len(),
+        );
+    }
+
+    // text up to `end`, with any trailing whitespace as a token of its own
+    fn text(&mut self, end: usize) {
+        let trimmed = self.src[self.at..end].trim_end().len();
+        self.push(TokenKind::Text, self.at + trimmed);
+        self.push(TokenKind::Whitespace, end);
+    }
+
+    // pipes are delimiters unless they are escaped, the cells of the
+    // `|---|:-:|` row are markers
+    fn table_row(&mut self, end: usize, delimiter_row: bool) {
+        self.whitespace(end);
+        while self.at < end {
+            let rest = &self.src[self.at..end];
+            if rest.starts_with('|') {
+                self.push(TokenKind::Delimiter, self.at + 1);
+                self.whitespace(end);
+                continue;
+            }
+            let mut cell = rest.len();
+            let mut escaped = false;
+            for (i, c) in rest.char_indices() {
+                match c {
+                    '|' if !escaped => {
+                        cell = i;
+                        break;
+                    }
+                    '\\' => escaped = !escaped,
+                    _ => escaped = false,
+                }
+            }
+            let kind = if delimiter_row {
+                TokenKind::Marker
+            } else {
+                TokenKind::Text
+            };
+            let trimmed = rest[..cell].trim_end().len();
+            self.push(kind, self.at + trimmed);
+            self.push(TokenKind::Whitespace, self.at + cell - trimmed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MarkdownInline;
+
+    fn spelled(tree: &SyntaxTree) -> String {
+        tree.tokens().map(|token| tree.text(token.span)).collect()
+    }
+
+    fn kinds(tree: &SyntaxTree, node: usize) -> Vec<(TokenKind, &str)> {
+        tree.nodes()[node]
+            .tokens
+            .iter()
+            .map(|token| (token.kind, tree.text(token.span)))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let src = "---\ntitle: x\n---\n#   Hi  \n\n- a\n  1. b\n\n|  a | b\\|c |\n| :-- |---|\n| d |e|\n```rust\nlet x;\n```\n[^n]:   note\ntext *here*   \n2 * 3\nlost";
+        let tree = SyntaxTree::parse(src);
+        assert_eq!(tree.to_string(), src);
+        assert_eq!(spelled(&tree), src);
+        let mut end = 0;
+        for token in tree.tokens() {
+            assert_eq!(token.span.start, end);
+            end = token.span.end;
+        }
+        assert_eq!(
+            tree.tokens().last().map(|token| token.kind),
+            Some(TokenKind::Unparsed)
+        );
+    }
+
+    #[test]
+    fn test_tokens() {
+        use TokenKind::*;
+        let tree = SyntaxTree::parse(
+            "#   Hi  \n- a\n  1. b\n| a | b\\|c |\n|:-:|---|\n```rust\nx\n```\n[^n]: note\n",
+        );
+        assert_eq!(
+            kinds(&tree, 0),
+            vec![
+                (Marker, "#"),
+                (Whitespace, "   "),
+                (Text, "Hi"),
+                (Whitespace, "  "),
+                (Newline, "\n")
+            ]
+        );
+        assert_eq!(
+            kinds(&tree, 1),
+            vec![
+                (Marker, "-"),
+                (Whitespace, " "),
+                (Text, "a"),
+                (Newline, "\n"),
+                (Indent, "  "),
+                (Marker, "1."),
+                (Whitespace, " "),
+                (Text, "b"),
+                (Newline, "\n")
+            ]
+        );
+        assert_eq!(
+            kinds(&tree, 2)[..9],
+            [
+                (Delimiter, "|"),
+                (Whitespace, " "),
+                (Text, "a"),
+                (Whitespace, " "),
+                (Delimiter, "|"),
+                (Whitespace, " "),
+                (Text, "b\\|c"),
+                (Whitespace, " "),
+                (Delimiter, "|"),
+            ]
+        );
+        assert_eq!(kinds(&tree, 2)[11], (Marker, ":-:"));
+        assert_eq!(
+            kinds(&tree, 3),
+            vec![
+                (Marker, "```"),
+                (Text, "rust"),
+                (Newline, "\n"),
+                (Text, "x"),
+                (Newline, "\n"),
+                (Marker, "```")
+            ]
+        );
+        assert_eq!(kinds(&tree, 5)[0], (Marker, "[^n]:"));
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut tree = SyntaxTree::parse("#  Title\n\n*   spaced   *  \n- a\n");
+        tree.replace(
+            3,
+            &Markdown::UnorderedList(vec![
+                vec![MarkdownInline::Plaintext(String::from("b"))].into()
+            ]),
+        );
+        assert_eq!(tree.to_string(), "#  Title\n\n*   spaced   *  \n- b\n");
+    }
+}
@@ -0,0 +1,62 @@
+use crate::prelude::*;
+// the records of a csv (or tsv, with a tab `delimiter`) file. fields can be
+// quoted to hold delimiters, newlines or `""` escaped quotes. blank lines are skipped
+pub fn records(src: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = src.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => record.push(core::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(core::mem::take(&mut field));
+                end_record(&mut records, core::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    record.push(field);
+    end_record(&mut records, record);
+    records
+}
+
+fn end_record(records: &mut Vec<Vec<String>>, record: Vec<String>) {
+    if !(record.len() == 1 && record[0].is_empty()) {
+        records.push(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        assert_eq!(
+            records(
+                "name,note\r\nhg,\"says \"\"hi\"\", twice\"\n\nmax,\"two\nlines\"\n",
+                ','
+            ),
+            vec![
+                vec![String::from("name"), String::from("note")],
+                vec![String::from("hg"), String::from("says \"hi\", twice")],
+                vec![String::from("max"), String::from("two\nlines")],
+            ]
+        );
+        assert_eq!(
+            records("a\tb, c\n", '\t'),
+            vec![vec![String::from("a"), String::from("b, c")]]
+        );
+        assert_eq!(records("", ','), Vec::<Vec<String>>::new());
+    }
+}
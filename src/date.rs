@@ -0,0 +1,64 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// front matter dates are plain strings, `YYYY-MM-DD` ones compare correctly as text
+pub fn is_iso_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+// the utc calendar date of a timestamp, the days to date conversion is
+// Howard Hinnant's `civil_from_days`
+pub fn iso_date(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let z = secs.div_euclid(86_400) + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub fn today() -> String {
+    iso_date(SystemTime::now())
+}
+
+// whether a front matter date (optionally followed by a time) lies after `today`
+pub fn is_future(date: &str, today: &str) -> bool {
+    match date.get(..10) {
+        Some(day) if is_iso_date(day) => day > today,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iso_date() {
+        assert_eq!(iso_date(UNIX_EPOCH), "1970-01-01");
+        let leap_day = UNIX_EPOCH + std::time::Duration::from_secs(951_782_400);
+        assert_eq!(iso_date(leap_day), "2000-02-29");
+        assert!(is_iso_date("2021-03-04"));
+        assert!(!is_iso_date("March 4th"));
+    }
+
+    #[test]
+    fn test_is_future() {
+        assert!(is_future("2021-03-05", "2021-03-04"));
+        assert!(is_future("2021-03-05T09:00", "2021-03-04"));
+        assert!(!is_future("2021-03-04", "2021-03-04"));
+        assert!(!is_future("someday", "2021-03-04"));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::normalize;
+use crate::prelude::*;
+use crate::Markdown;
+use crate::Span;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+// one block that changed, with where it was in the old source and where it is
+// in the new one. added blocks have no old span and removed ones no new span
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub change: Change,
+    pub old: Option<Span>,
+    pub new: Option<Span>,
+}
+
+// the blocks that changed between two versions of a document, as handed out
+// by `document::spanned_blocks`, in order. blocks are matched up the way `diff`
+// matches lines, and a removed block followed by an added one in its place is
+// reported as modified. blank lines never count as a change
+pub fn diff(old: &[(Markdown, Span)], new: &[(Markdown, Span)]) -> Vec<Hunk> {
+    // blocks are compared in their normal form, so respacing or renumbering
+    // a list doesn't count as a change
+    let canonical = |blocks: &[(Markdown, Span)]| {
+        blocks
+            .iter()
+            .filter(|(block, _)| !blank(block))
+            .map(|(block, span)| {
+                let mut block = block.clone();
+                normalize::normalize_block(&mut block);
+                (block, *span)
+            })
+            .collect::<Vec<_>>()
+    };
+    let (old, new) = (canonical(old), canonical(new));
+
+    // the longest common subsequence of the two, counted from the back
+    let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i].0 == new[j].0 {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut hunks = vec![];
+    let (mut removed, mut added) = (vec![], vec![]);
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].0 == new[j].0 {
+            pair(&mut hunks, &mut removed, &mut added);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            added.push(new[j].1);
+            j += 1;
+        } else {
+            removed.push(old[i].1);
+            i += 1;
+        }
+    }
+    pair(&mut hunks, &mut removed, &mut added);
+    hunks
+}
+
+// a run of changes between two unchanged blocks: as many as line up are
+// modified, whatever is left over was removed or added
+fn pair(hunks: &mut Vec<Hunk>, removed: &mut Vec<Span>, added: &mut Vec<Span>) {
+    let modified = removed.len().min(added.len());
+    for k in 0..removed.len().max(added.len()) {
+        hunks.push(Hunk {
+            change: if k < modified {
+                Change::Modified
+            } else if k < removed.len() {
+                Change::Removed
+            } else {
+                Change::Added
+            },
+            old: removed.get(k).copied(),
+            new: added.get(k).copied(),
+        });
+    }
+    removed.clear();
+    added.clear();
+}
+
+fn blank(block: &Markdown) -> bool {
+    matches!(block, Markdown::Line(text) if text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::spanned_blocks;
+
+    #[test]
+    fn test_diff() {
+        let old = "# Title\n\nkept\n\nchanged\n\ngone\n";
+        let new = "# Title\n\nnew\n\nkept\n\nchanged!\n";
+        let hunks = diff(&spanned_blocks(old), &spanned_blocks(new));
+        let text =
+            |src: &'static str, span: Option<Span>| span.map(|s| src[s.start..s.end].trim_end());
+        assert_eq!(
+            hunks
+                .iter()
+                .map(|hunk| (hunk.change, text(old, hunk.old), text(new, hunk.new)))
+                .collect::<Vec<_>>(),
+            vec![
+                (Change::Added, None, Some("new")),
+                (Change::Modified, Some("changed"), Some("changed!")),
+                (Change::Removed, Some("gone"), None),
+            ]
+        );
+        assert!(diff(&spanned_blocks(old), &spanned_blocks(old)).is_empty());
+        assert!(diff(
+            &spanned_blocks("3. a\n4. b  \n"),
+            &spanned_blocks("1. a\n2. b\n")
+        )
+        .is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::frontmatter;
+use crate::options::HtmlOptions;
+use crate::parser;
+use crate::raw_format;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use dioxus::prelude::*;
+
+// a whole document, front matter and all, rendered again when either prop changes
+#[component]
+pub fn MarkdownView(markdown: String, #[props(default)] options: HtmlOptions) -> Element {
+    let (front_matter, md) = frontmatter::split(&markdown);
+    let options = options.with_front_matter(&front_matter);
+    let blocks = parser::parse_markdown(md)
+        .map(|(_, blocks)| blocks)
+        .unwrap_or_default();
+    rsx! {
+        div { class: "prose-preview", {element(&blocks, &options)} }
+    }
+}
+
+// the same elements the translator writes out, built with `rsx!`. text is
+// escaped by dioxus, and footnote definitions stay where they were written
+// instead of moving to the end
+pub fn element(md: &[Markdown], options: &HtmlOptions) -> Element {
+    rsx! {
+        for block in md {
+            {block_element(block, options)}
+        }
+    }
+}
+
+fn block_element(block: &Markdown, options: &HtmlOptions) -> Element {
+    match block {
+        Markdown::Heading(level, text) => {
+            let text = text_element(text, options);
+            match level {
+                HeadingLevel::H1 => rsx! { h1 { {text} } },
+                HeadingLevel::H2 => rsx! { h2 { {text} } },
+                HeadingLevel::H3 => rsx! { h3 { {text} } },
+                HeadingLevel::H4 => rsx! { h4 { {text} } },
+                HeadingLevel::H5 => rsx! { h5 { {text} } },
+                HeadingLevel::H6 => rsx! { h6 { {text} } },
+            }
+        }
+        Markdown::UnorderedList(items) => rsx! {
+            ul {
+                for item in items {
+                    li { {item_element(item, options)} }
+                }
+            }
+        },
+        Markdown::OrderedList(items) => rsx! {
+            ol {
+                for (_, item) in items {
+                    li { {item_element(item, options)} }
+                }
+            }
+        },
+        Markdown::Codeblock(lang, code) if raw_format(lang) == Some("html") => rsx! {
+            div { dangerous_inner_html: "{code}" }
+        },
+        Markdown::Codeblock(lang, _) if raw_format(lang).is_some() => VNode::empty(),
+        Markdown::Codeblock(lang, code) => rsx! {
+            pre {
+                code { class: "{options.code_class_prefix}{lang}", "{code}" }
+            }
+        },
+        // an empty line leaves nothing behind, not even the paragraph
+        Markdown::Line(text) if text.is_empty() => VNode::empty(),
+        Markdown::Line(text) => rsx! {
+            p { {text_element(text, options)} }
+        },
+        Markdown::FootnoteDef(label, text) => rsx! {
+            div { class: "footnote", id: "fn-{label}", {text_element(text, options)} }
+        },
+        Markdown::Table(header, aligns, rows) => rsx! {
+            table {
+                thead {
+                    tr {
+                        for (cell, align) in header.iter().zip(aligns) {
+                            th { style: align_style(*align), {text_element(cell, options)} }
+                        }
+                    }
+                }
+                tbody {
+                    for row in rows {
+                        {row_element(row, aligns, options)}
+                    }
+                }
+            }
+        },
+        Markdown::Custom(name, text) => {
+            let text = text.trim_end_matches('\n');
+            rsx! {
+                div { class: "{name}", "{text}" }
+            }
+        }
+        Markdown::Alert(alert, lines) => rsx! {
+            div { class: options.alert_classes.container(*alert),
+                p { class: options.alert_classes.title(*alert), "{alert.title()}" }
+                for line in lines.iter().filter(|line| !line.is_empty()) {
+                    p { {text_element(line, options)} }
+                }
+            }
+        },
+    }
+}
+
+fn row_element(cells: &[MarkdownText], aligns: &[Align], options: &HtmlOptions) -> Element {
+    rsx! {
+        tr {
+            for (cell, align) in cells.iter().zip(aligns) {
+                td { style: align_style(*align), {text_element(cell, options)} }
+            }
+        }
+    }
+}
+
+fn item_element(item: &ListItem, options: &HtmlOptions) -> Element {
+    rsx! {
+        {text_element(&item.text, options)}
+        {element(&item.children, options)}
+    }
+}
+
+fn text_element(text: &[MarkdownInline], options: &HtmlOptions) -> Element {
+    rsx! {
+        for part in text {
+            {inline_element(part, options)}
+        }
+    }
+}
+
+fn inline_element(part: &MarkdownInline, options: &HtmlOptions) -> Element {
+    match part {
+        MarkdownInline::Bold(text) => {
+            let text = options.decoded(text);
+            rsx! { b { "{text}" } }
+        }
+        MarkdownInline::Italic(text) => {
+            let text = options.decoded(text);
+            rsx! { i { "{text}" } }
+        }
+        MarkdownInline::Inserted(text) => {
+            let text = options.decoded(text);
+            rsx! { ins { "{text}" } }
+        }
+        MarkdownInline::InlineCode(code) => rsx! { code { "{code}" } },
+        MarkdownInline::Link(text, url) => {
+            let text = options.decoded(text);
+            let href = options.href(&options.substitute_url(url));
+            rsx! { a { href, "{text}" } }
+        }
+        MarkdownInline::Image(text, url) => {
+            let alt = options.decoded(&options.alt(text, url));
+            let src = options.src(&options.substitute_url(url));
+            rsx! { img { src, alt } }
+        }
+        MarkdownInline::Plaintext(text) => {
+            let text = options.decoded(text);
+            rsx! { "{text}" }
+        }
+        MarkdownInline::FootnoteRef(label) => rsx! {
+            sup { class: "footnote-ref",
+                a { href: "#fn-{label}", "{label}" }
+            }
+        },
+        MarkdownInline::Citation(keys) => {
+            let keys = keys.join("; @");
+            rsx! { cite { class: "citation", "[@{keys}]" } }
+        }
+    }
+}
+
+fn align_style(align: Align) -> &'static str {
+    match align {
+        Align::Default => "",
+        Align::Left => "text-align: left",
+        Align::Center => "text-align: center",
+        Align::Right => "text-align: right",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_view() {
+        let markdown = "# Title\n- a\n- **b**\n  1. c\n| x | y |\n|:--|--:|\n| 1 | 2 |\n```{=html}\n<b>raw</b>\n```\n";
+        let mut dom = VirtualDom::new_with_props(
+            MarkdownView,
+            MarkdownViewProps {
+                markdown: markdown.to_string(),
+                options: HtmlOptions::default(),
+            },
+        );
+        dom.rebuild_in_place();
+        let html = dioxus_ssr::render(&dom);
+        assert_eq!(
+            html,
+            "<div class=\"prose-preview\"><h1>Title</h1><ul><li>a</li><li><b>b</b><ol><li>c</li></ol></li></ul><table><thead><tr><th style=\"text-align: left\">x</th><th style=\"text-align: right\">y</th></tr></thead><tbody><tr><td style=\"text-align: left\">1</td><td style=\"text-align: right\">2</td></tr></tbody></table><div><b>raw</b>\n</div></div>"
+        );
+        // the translator's elements, but for cells aligned with a style and
+        // raw html needing an element to go in
+        assert_eq!(
+            html.replace(" style=\"text-align: left\"", " align=\"left\"")
+                .replace(" style=\"text-align: right\"", " align=\"right\"")
+                .replace("<div><b>raw</b>\n</div>", "<b>raw</b>\n"),
+            format!(
+                "<div class=\"prose-preview\">{}</div>",
+                crate::markdown(markdown)
+            )
+        );
+    }
+}
@@ -0,0 +1,773 @@
+use crate::entities;
+use crate::frontmatter::{self, FrontMatter};
+use crate::normalize;
+use crate::options::HtmlOptions;
+use crate::parser::{self, ParserOptions};
+use crate::prelude::*;
+use crate::readability::{self, Readability};
+use crate::slug::Slugger;
+use crate::toc;
+use crate::unicode;
+use crate::HeadingLevel;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use crate::Position;
+use crate::Span;
+use crate::Url;
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
+use core::fmt;
+
+// a line of its own that ends the excerpt of a document
+pub const MORE: &str = "<!--more-->";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub front_matter: FrontMatter,
+    pub blocks: Vec<Markdown>,
+}
+
+// where in the whole document (front matter included) the parser gave up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: Position,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "could not parse markdown starting at line {}",
+            self.position.line
+        )
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+impl Document {
+    // unlike `markdown`, which renders whatever it managed to read,
+    // this refuses documents that only parse part of the way
+    pub fn parse(src: &str) -> Result<Document, ParseError> {
+        Document::parse_with(src, &ParserOptions::default())
+    }
+
+    // `parse` along with the block parsers and source filters in `options`.
+    // filters only see what comes after the front matter, and where a parse
+    // error points is counted in the filtered text
+    pub fn parse_with(src: &str, options: &ParserOptions) -> Result<Document, ParseError> {
+        let (front_matter, body) = frontmatter::split(src);
+        let body_offset = src.len() - body.len();
+        let src = if options.filters.is_empty() {
+            Cow::Borrowed(src)
+        } else {
+            Cow::Owned(format!(
+                "{}{}",
+                &src[..body_offset],
+                options.filters.apply(body.to_string())
+            ))
+        };
+        let md = terminated(&src[body_offset..]);
+        match parser::parse_markdown_with(&md, options) {
+            Ok(("", blocks)) => Ok(Document {
+                front_matter,
+                blocks,
+            }),
+            Ok((rest, _)) => Err(ParseError {
+                position: Position::of(&src, body_offset + md.len() - rest.len()),
+            }),
+            Err(_) => Err(ParseError {
+                position: Position::of(&src, body_offset),
+            }),
+        }
+    }
+
+    // several documents in one file, each opened by its own front matter, for
+    // slide decks and changelogs. text above the first front matter is a
+    // document without any. a `---` line only opens the next document after a
+    // blank line, so `---` underlined headings stay headings
+    pub fn parse_bundle(src: &str) -> Result<Vec<Document>, ParseError> {
+        Document::parse_bundle_with(src, &ParserOptions::default())
+    }
+
+    // `parse_bundle` with the block parsers and source filters in `options`,
+    // where a parse error points is counted from the top of the whole file
+    pub fn parse_bundle_with(
+        src: &str,
+        options: &ParserOptions,
+    ) -> Result<Vec<Document>, ParseError> {
+        let src = unicode::strip_bom(src);
+        let mut starts = bundle_starts(src);
+        if starts.first() != Some(&0) && !src.trim().is_empty() {
+            starts.insert(0, 0);
+        }
+        let ends = starts.iter().skip(1).copied().chain(Some(src.len()));
+        starts
+            .iter()
+            .zip(ends)
+            .map(|(&start, end)| {
+                Document::parse_with(src[start..end].trim_end(), options).map_err(|e| ParseError {
+                    position: Position {
+                        line: e.position.line + src[..start].matches('\n').count(),
+                        column: e.position.column,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    // the opening of a document for index pages and feeds: everything above
+    // a `<!--more-->` line, or failing that the first paragraph of text
+    pub fn excerpt(&self) -> Vec<Markdown> {
+        if let Some(more) = self.blocks.iter().position(is_more) {
+            return self.blocks[..more].to_vec();
+        }
+        self.blocks
+            .iter()
+            .skip_while(|block| !is_text(block))
+            .take_while(|block| is_text(block))
+            .cloned()
+            .collect()
+    }
+
+    // the front matter's `title`, or else the text of the first `#` heading
+    pub fn title(&self) -> Option<String> {
+        if let Some(title) = self.front_matter.get_str("title") {
+            return Some(title.to_string());
+        }
+        self.blocks
+            .iter()
+            .find_map(|block| match block {
+                Markdown::Heading(HeadingLevel::H1, text) => Some(shown(text)),
+                _ => None,
+            })
+            .filter(|title| !title.is_empty())
+    }
+
+    // the front matter's `description`, or else the first paragraph of text
+    // as one line, for `<meta name="description">` and feeds
+    pub fn description(&self) -> Option<String> {
+        if let Some(description) = self.front_matter.get_str("description") {
+            return Some(description.to_string());
+        }
+        let paragraph = self
+            .blocks
+            .iter()
+            .skip_while(|block| !is_text(block))
+            .take_while(|block| is_text(block))
+            .flat_map(Markdown::texts)
+            .map(shown)
+            .collect::<Vec<_>>();
+        (!paragraph.is_empty()).then(|| paragraph.join(" "))
+    }
+
+    // the first image's url, resolved against `base_url` the way the html has
+    // it, for open graph images and card thumbnails
+    pub fn lead_image(&self, options: &HtmlOptions) -> Option<String> {
+        self.blocks
+            .iter()
+            .flat_map(Markdown::texts)
+            .flatten()
+            .find_map(|inline| match inline {
+                MarkdownInline::Image(_, url) => Some(options.src(&options.substitute_url(url))),
+                _ => None,
+            })
+    }
+
+    // the front matter's `date`, as written. there is nothing in the text to
+    // fall back on
+    pub fn date(&self) -> Option<&str> {
+        self.front_matter
+            .get_str("date")
+            .map(str::trim)
+            .filter(|date| !date.is_empty())
+    }
+
+    // the words a reader sees, one line per heading, list item or line of text.
+    // code is left out, it makes for poor search results and reading estimates.
+    // references like `&amp;` are read as the character they stand for
+    pub fn text(&self) -> String {
+        self.blocks
+            .iter()
+            .filter(|block| !is_more(block))
+            .flat_map(|block| match block {
+                // a row reads as one line
+                Markdown::Table(header, _, rows) => core::iter::once(header)
+                    .chain(rows)
+                    .map(|row| {
+                        row.iter()
+                            .map(|text| text.plain_text())
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    })
+                    .collect::<Vec<String>>(),
+                block => block
+                    .texts()
+                    .into_iter()
+                    .map(|text| text.plain_text())
+                    .collect(),
+            })
+            .map(|line| entities::decode(line.trim()).into_owned())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // sentence and word counts along with reading scores for `text`
+    pub fn readability(&self) -> Readability {
+        readability::readability(&self.text())
+    }
+
+    // one document out of several, a book out of its chapters, keeping the
+    // front matter of the first. a footnote label already used by an earlier
+    // document is numbered, `[^note-2]`, and links to a document's own
+    // headings follow them to the slugs they end up with in the whole
+    pub fn concat(documents: impl IntoIterator<Item = Document>) -> Document {
+        let mut front_matter = None;
+        let mut blocks = vec![];
+        let mut labels = BTreeSet::new();
+        let mut slugger = Slugger::default();
+        for mut doc in documents {
+            let own = footnote_labels(&doc.blocks);
+            let mut renamed = BTreeMap::new();
+            for label in own.iter().filter(|label| labels.contains(*label)) {
+                let new = (2..)
+                    .map(|n| format!("{}-{}", label, n))
+                    .find(|new| !labels.contains(new) && !own.contains(new))
+                    .expect("a free label");
+                renamed.insert(label.clone(), new);
+            }
+            let slugs = toc::toc(&doc.blocks)
+                .into_iter()
+                .map(|entry| (entry.slug, slugger.slug(&entry.text)))
+                .filter(|(local, whole)| local != whole)
+                .collect::<BTreeMap<_, _>>();
+
+            for block in &mut doc.blocks {
+                if let Markdown::FootnoteDef(label, _) = block {
+                    if let Some(new) = renamed.get(label) {
+                        *label = new.clone();
+                    }
+                }
+                for inline in block.texts_mut().into_iter().flatten() {
+                    match inline {
+                        MarkdownInline::FootnoteRef(label) => {
+                            if let Some(new) = renamed.get(label) {
+                                *label = new.clone();
+                            }
+                        }
+                        MarkdownInline::Link(_, url) => {
+                            if let Some(slug) = url.strip_prefix('#').and_then(|s| slugs.get(s)) {
+                                *url = Url::from(format!("#{}", slug));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            labels.extend(own.into_iter().filter(|label| !renamed.contains_key(label)));
+            labels.extend(renamed.into_values());
+
+            if front_matter.is_none() {
+                front_matter = Some(doc.front_matter);
+            } else {
+                // keeps the last paragraph of one document apart from the next
+                blocks.push(Markdown::Line(MarkdownText::new()));
+            }
+            blocks.extend(doc.blocks);
+        }
+        Document {
+            front_matter: front_matter.unwrap_or_default(),
+            blocks,
+        }
+    }
+
+    // moves every heading `delta` levels deeper, or shallower when negative,
+    // staying within h1 to h6. `1` turns a chapter's `#` title into a `##`
+    // that fits under a section of the book it goes in
+    pub fn shift_headings(&mut self, delta: isize) {
+        for block in &mut self.blocks {
+            if let Markdown::Heading(level, _) = block {
+                *level = level.shift(delta);
+            }
+        }
+    }
+
+    // relabels footnotes `[^1]`, `[^2]`, ... in the order they are first
+    // referenced, whatever the author called them, and puts the definitions
+    // in that order too, in the places definitions were. notes that are never
+    // referenced come last, and references to notes that aren't defined are
+    // left alone
+    pub fn renumber_footnotes(&mut self) {
+        let defined = self
+            .blocks
+            .iter()
+            .filter_map(|block| match block {
+                Markdown::FootnoteDef(label, _) => Some(label.clone()),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        let undefined = footnote_labels(&self.blocks)
+            .into_iter()
+            .filter(|label| !defined.contains(label))
+            .collect::<BTreeSet<_>>();
+
+        let mut numbers = (1..)
+            .map(|n: usize| n.to_string())
+            .filter(|number| !undefined.contains(number));
+        let mut renamed = BTreeMap::new();
+        for label in footnote_order(&self.blocks) {
+            if defined.contains(&label) && !renamed.contains_key(&label) {
+                renamed.insert(label, numbers.next().expect("a free number"));
+            }
+        }
+
+        let mut slots = vec![];
+        for (i, block) in self.blocks.iter_mut().enumerate() {
+            if let Markdown::FootnoteDef(label, _) = block {
+                if let Some(new) = renamed.get(label) {
+                    *label = new.clone();
+                }
+                slots.push(i);
+            }
+            for inline in block.texts_mut().into_iter().flatten() {
+                if let MarkdownInline::FootnoteRef(label) = inline {
+                    if let Some(new) = renamed.get(label) {
+                        *label = new.clone();
+                    }
+                }
+            }
+        }
+        let mut definitions = slots
+            .iter()
+            .map(|&i| core::mem::replace(&mut self.blocks[i], Markdown::Line(MarkdownText::new())))
+            .collect::<Vec<_>>();
+        // stable, so a label defined twice keeps its first definition first
+        definitions.sort_by_key(|block| match block {
+            Markdown::FootnoteDef(label, _) => label.parse::<usize>().unwrap_or(usize::MAX),
+            _ => usize::MAX,
+        });
+        for (i, definition) in slots.into_iter().zip(definitions) {
+            self.blocks[i] = definition;
+        }
+    }
+
+    // rewrites the blocks into their one canonical form, see `normalize`.
+    // `prose fmt` writes this and `diff` compares it
+    pub fn normalize(&mut self) {
+        self.blocks = normalize::normalize(core::mem::take(&mut self.blocks));
+    }
+}
+
+// every footnote label the blocks define or refer to
+fn footnote_labels(blocks: &[Markdown]) -> BTreeSet<String> {
+    let mut labels = BTreeSet::new();
+    for block in blocks {
+        if let Markdown::FootnoteDef(label, _) = block {
+            labels.insert(label.clone());
+        }
+        for inline in block.texts().into_iter().flatten() {
+            if let MarkdownInline::FootnoteRef(label) = inline {
+                labels.insert(label.clone());
+            }
+        }
+    }
+    labels
+}
+
+// footnote labels in the order a reader meets them: references in the text,
+// then the ones inside each note as the notes are listed, then notes that
+// nothing refers to
+fn footnote_order(blocks: &[Markdown]) -> Vec<String> {
+    let references = |text: &MarkdownText| {
+        text.iter()
+            .filter_map(|inline| match inline {
+                MarkdownInline::FootnoteRef(label) => Some(label.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut definitions = BTreeMap::new();
+    let mut order = vec![];
+    for block in blocks {
+        match block {
+            Markdown::FootnoteDef(label, text) => {
+                definitions.entry(label.as_str()).or_insert(text);
+            }
+            block => order.extend(block.texts().into_iter().flat_map(references)),
+        }
+    }
+    let mut i = 0;
+    while i < order.len() {
+        if let Some(text) = definitions.get(order[i].as_str()) {
+            for label in references(text) {
+                if !order.contains(&label) {
+                    order.push(label);
+                }
+            }
+        }
+        i += 1;
+    }
+    for block in blocks {
+        if let Markdown::FootnoteDef(label, _) = block {
+            order.push(label.clone());
+        }
+    }
+    order
+}
+
+// text as a reader sees it, `&amp;` and all
+fn shown(text: &MarkdownText) -> String {
+    entities::decode(text.plain_text().trim()).into_owned()
+}
+
+fn is_more(block: &Markdown) -> bool {
+    matches!(block, Markdown::Line(text) if text.plain_text().trim() == MORE)
+}
+
+fn is_text(block: &Markdown) -> bool {
+    matches!(block, Markdown::Line(text) if !text.plain_text().trim().is_empty())
+}
+
+// every block along with where it sits in `src`, skipping over the front matter.
+// this is for tools pointing at problems, so it keeps whatever parsed before a failure
+pub fn spanned_blocks(src: &str) -> Vec<(Markdown, Span)> {
+    let (_, body) = frontmatter::split(src);
+    let body_offset = src.len() - body.len();
+    match parser::parse_markdown_spanned(&terminated(body)) {
+        Ok((_, blocks)) => blocks
+            .into_iter()
+            .map(|(block, span)| {
+                (
+                    block,
+                    Span {
+                        start: span.start + body_offset,
+                        end: (span.end + body_offset).min(src.len()),
+                    },
+                )
+            })
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+// a stretch of prose in the source, the words a reader sees
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextRun {
+    pub text: String,
+    pub span: Span,
+}
+
+// every piece of prose in `src` with where it sits, for spellcheckers and
+// style checkers: plain, bold, italic and inserted text, link text and image alt text.
+// code, urls, footnote references and citations are left out
+pub fn text_runs(src: &str) -> Vec<TextRun> {
+    let mut runs = vec![];
+    for (block, span) in spanned_blocks(src) {
+        // runs show up in the source in order, so each is looked for after the last
+        let mut rest = span;
+        for inline in block.texts().into_iter().flatten() {
+            let text = match inline {
+                MarkdownInline::Plaintext(text)
+                | MarkdownInline::Bold(text)
+                | MarkdownInline::Italic(text)
+                | MarkdownInline::Inserted(text)
+                | MarkdownInline::Link(text, _)
+                | MarkdownInline::Image(text, _) => text,
+                _ => continue,
+            };
+            if text.trim().is_empty() {
+                continue;
+            }
+            // text the parser rewrote can't be pointed at, so it's skipped
+            let start = match src[rest.start..rest.end].find(text.as_str()) {
+                Some(at) => rest.start + at,
+                None => continue,
+            };
+            rest.start = start + text.len();
+            runs.push(TextRun {
+                text: text.clone(),
+                span: Span {
+                    start,
+                    end: rest.start,
+                },
+            });
+        }
+    }
+    runs
+}
+
+// where the front matter of each document in a bundle starts, leaving out
+// `---` lines in code blocks and in the front matter itself
+fn bundle_starts(src: &str) -> Vec<usize> {
+    let mut starts = vec![];
+    let (mut offset, mut skip_to) = (0, 0);
+    let (mut blank_before, mut in_code) = (true, false);
+    for line in src.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if offset >= skip_to && blank_before && !in_code && line.trim_end() == "---" {
+            if let Ok((rest, _)) = frontmatter::parse_front_matter(&src[offset..]) {
+                starts.push(offset);
+                skip_to = src.len() - rest.len();
+            }
+        }
+        blank_before = line.trim().is_empty();
+        offset += line.len();
+    }
+    starts
+}
+
+// the parser wants a newline at the end, only a document without one is copied
+fn terminated(md: &str) -> Cow<'_, str> {
+    if md.ends_with('\n') {
+        Cow::Borrowed(md)
+    } else {
+        Cow::Owned(format!("{}\n", md))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeadingLevel;
+
+    #[test]
+    fn test_parse_with_filters() {
+        let mut options = ParserOptions::default();
+        options
+            .filter(|src| src.replace("%% draft %%\n", ""))
+            .filter(|src| src.replace("{{year}}", "2021"));
+        let doc = Document::parse_with(
+            "---\ntitle: {{year}}\n---\n%% draft %%\n# In {{year}}\n",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(doc.front_matter.get_str("title"), Some("{{year}}"));
+        assert_eq!(doc.text(), "In 2021");
+    }
+
+    #[test]
+    fn test_readability() {
+        let doc = Document::parse("# Cats\nThe cat sat.\n```\nlet x = 1;\n```\n").unwrap();
+        let score = doc.readability();
+        assert_eq!((score.sentences, score.words), (2, 4));
+    }
+
+    #[test]
+    fn test_text_runs() {
+        let src = "---\ntitle: a\n---\n# Helo\nsee `code` and **this** [link](https://x.com)\n";
+        let runs = text_runs(src);
+        assert_eq!(
+            runs.iter().map(|run| run.text.as_str()).collect::<Vec<_>>(),
+            vec!["Helo", "see ", " and ", "this", "link"]
+        );
+        for run in &runs {
+            assert_eq!(&src[run.span.start..run.span.end], run.text);
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        let one = Document::parse(
+            "---\ntitle: Book\n---\n# Usage\nsee[^n] [below](#usage)\n\n[^n]: one\n",
+        )
+        .unwrap();
+        let two = Document::parse(
+            "---\ntitle: Two\n---\n# Usage\nsee[^n] [above](#usage)\n\n[^n]: two\n",
+        )
+        .unwrap();
+        let book = Document::concat(vec![one, two]);
+        assert_eq!(book.front_matter.get_str("title"), Some("Book"));
+        let html = crate::translator::translate(&book.blocks);
+        assert!(html.contains("<a href=\"#usage\">below</a>"));
+        assert!(html.contains("<a href=\"#usage-1\">above</a>"));
+        assert!(html.contains("<li id=\"fn-n\">one"));
+        assert!(html.contains("<li id=\"fn-n-2\">two"));
+        assert_eq!(
+            toc::toc(&book.blocks)
+                .into_iter()
+                .map(|entry| entry.slug)
+                .collect::<Vec<_>>(),
+            vec!["usage", "usage-1"]
+        );
+    }
+
+    #[test]
+    fn test_renumber_footnotes() {
+        let mut doc = Document::parse(
+            "b[^zeta] a[^alpha] again[^zeta] gone[^2]\n\n[^alpha]: first, see[^inner]\n[^zeta]: zero\n[^unused]: never\n[^inner]: nested\n",
+        )
+        .unwrap();
+        doc.renumber_footnotes();
+        assert_eq!(
+            crate::formatter::format(&doc.blocks),
+            "b[^1] a[^3] again[^1] gone[^2]\n\n[^1]: zero\n[^3]: first, see[^4]\n[^4]: nested\n[^5]: never\n"
+        );
+    }
+
+    #[test]
+    fn test_shift_headings() {
+        let levels = |doc: &Document| {
+            doc.blocks
+                .iter()
+                .filter_map(|block| match block {
+                    Markdown::Heading(level, _) => Some(level.get()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut doc = Document::parse(
+            "# a
+## b
+###### c
+",
+        )
+        .unwrap();
+        doc.shift_headings(1);
+        assert_eq!(levels(&doc), vec![2, 3, 6]);
+        doc.shift_headings(-2);
+        assert_eq!(levels(&doc), vec![1, 1, 4]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let doc = Document::parse("---\ntitle: Hello\n---\n# Hi\nthere").unwrap();
+        assert_eq!(doc.front_matter.get_str("title"), Some("Hello"));
+        assert_eq!(
+            doc.blocks,
+            vec![
+                Markdown::Heading(
+                    HeadingLevel::H1,
+                    vec![MarkdownInline::Plaintext(String::from("Hi"))].into()
+                ),
+                Markdown::Line(vec![MarkdownInline::Plaintext(String::from("there"))].into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        let doc =
+            Document::parse("# Hi *there*\n\n- [one](a.md)\n- two\n```\ncode\n```\nbye\n").unwrap();
+        assert_eq!(doc.text(), "Hi there\none\ntwo\nbye");
+        let doc = Document::parse("Q&amp;A &copy; 2021\n").unwrap();
+        assert_eq!(doc.text(), "Q&A © 2021");
+        let doc = Document::parse("| a | b |\n|---|---|\n| *c* | d |\n").unwrap();
+        assert_eq!(doc.text(), "a b\nc d");
+    }
+
+    #[test]
+    fn test_metadata() {
+        let doc = Document::parse(
+            "## Intro\n# Tom &amp; Jerry\n\n```\ncode\n```\nA **cat**\nand a mouse.\n\nMore.\n",
+        )
+        .unwrap();
+        assert_eq!(doc.title().as_deref(), Some("Tom & Jerry"));
+        assert_eq!(doc.description().as_deref(), Some("A cat and a mouse."));
+        assert_eq!(doc.date(), None);
+
+        let doc = Document::parse(
+            "---\ntitle: Cartoons\ndescription: About cats\ndate: 2021-03-04\n---\n# Tom\nText\n",
+        )
+        .unwrap();
+        assert_eq!(doc.title().as_deref(), Some("Cartoons"));
+        assert_eq!(doc.description().as_deref(), Some("About cats"));
+        assert_eq!(doc.date(), Some("2021-03-04"));
+    }
+
+    #[test]
+    fn test_lead_image() {
+        let doc =
+            Document::parse("# Hi\n[a](a.md)\n- ![cat](img/cat.png) ![dog](dog.png)\n").unwrap();
+        let options = HtmlOptions {
+            base_url: Some(String::from("https://example.com/blog/")),
+            ..Default::default()
+        };
+        assert_eq!(
+            doc.lead_image(&options).as_deref(),
+            Some("https://example.com/blog/img/cat.png")
+        );
+        assert_eq!(
+            Document::parse("no images\n").unwrap().lead_image(&options),
+            None
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let doc = Document::parse("# Title\n\nfirst *para*\ngoes on\n\nsecond\n").unwrap();
+        assert_eq!(
+            doc.excerpt(),
+            vec![
+                Markdown::Line(
+                    vec![
+                        MarkdownInline::Plaintext(String::from("first ")),
+                        MarkdownInline::Italic(String::from("para"))
+                    ]
+                    .into()
+                ),
+                Markdown::Line(vec![MarkdownInline::Plaintext(String::from("goes on"))].into()),
+            ]
+        );
+
+        let doc = Document::parse("# Title\nintro\n<!--more-->\nrest\n").unwrap();
+        assert_eq!(doc.excerpt(), doc.blocks[..2].to_vec());
+        assert_eq!(
+            Document::parse("# Only a title\n").unwrap().excerpt(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_parse_bundle() {
+        let docs = Document::parse_bundle(
+            "intro\n\n---\ntitle: One\n---\nSetext\n---\n\n```\n---\nnot: front\n---\n```\n\n---\ntitle: Two\n---\nbye\n",
+        )
+        .unwrap();
+        assert_eq!(docs.len(), 3);
+        assert!(docs[0].front_matter.is_empty());
+        assert_eq!(docs[0].text(), "intro");
+        assert_eq!(docs[1].front_matter.get_str("title"), Some("One"));
+        assert_eq!(docs[1].text(), "Setext\n---");
+        assert!(docs[1]
+            .blocks
+            .iter()
+            .any(|block| matches!(block, Markdown::Codeblock(_, _))));
+        assert_eq!(docs[2].front_matter.get_str("title"), Some("Two"));
+        assert_eq!(docs[2].text(), "bye");
+        assert_eq!(
+            Document::parse_bundle("---\na: 1\n---\nfine\n\n---\nb: 2\n---\n2 * 3\n"),
+            Err(ParseError {
+                position: Position { line: 9, column: 1 }
+            })
+        );
+        assert_eq!(Document::parse_bundle(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Document::parse("---\ntitle: Hello\n---\nfine\n2 * 3\nlost\n"),
+            Err(ParseError {
+                position: Position { line: 5, column: 1 }
+            })
+        );
+        assert_eq!(
+            Document::parse("2 * 3\n").unwrap_err().to_string(),
+            "could not parse markdown starting at line 1"
+        );
+    }
+
+    #[test]
+    fn test_spanned_blocks() {
+        let src = "---\ntitle: x\n---\n# Hi\nthere";
+        let blocks = spanned_blocks(src);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(&src[blocks[0].1.start..blocks[0].1.end], "# Hi\n");
+        assert_eq!(&src[blocks[1].1.start..blocks[1].1.end], "there");
+    }
+}
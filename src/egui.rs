@@ -0,0 +1,187 @@
+use crate::entities::decode;
+use crate::raw_format;
+use crate::Align;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use egui::{Grid, RichText, TextStyle, Ui};
+
+// lays parsed blocks out top to bottom in `ui`. images are loaded by uri,
+// so apps that want them shown install egui's image loaders
+pub fn show(ui: &mut Ui, md: &[Markdown]) {
+    let mut blocks = md.iter().peekable();
+    while let Some(block) = blocks.next() {
+        show_block(ui, block);
+        // the parser reads the newline after a closing fence as an empty line
+        if let (Markdown::Codeblock(_, _), Some(Markdown::Line(text))) = (block, blocks.peek()) {
+            if text.is_empty() {
+                blocks.next();
+            }
+        }
+    }
+}
+
+fn show_block(ui: &mut Ui, block: &Markdown) {
+    match block {
+        Markdown::Heading(level, text) => {
+            // each level down is a little smaller, never below the body text
+            let heading = TextStyle::Heading.resolve(ui.style()).size;
+            let body = TextStyle::Body.resolve(ui.style()).size;
+            let size = (heading - (level.get() as f32 - 1.0) * 2.0).max(body);
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing.x = 0.0;
+                for part in text {
+                    show_inline(ui, rich_text(part).size(size).strong(), part);
+                }
+            });
+        }
+        Markdown::UnorderedList(items) => {
+            for item in items {
+                show_item(ui, item, String::from("•"));
+            }
+        }
+        Markdown::OrderedList(items) => {
+            for (number, item) in items {
+                show_item(ui, item, format!("{}.", number));
+            }
+        }
+        // raw html or latex has nothing to show here
+        Markdown::Codeblock(lang, _) if raw_format(lang).is_some() => {}
+        Markdown::Codeblock(_, code) => {
+            ui.code(code.trim_end_matches('\n'));
+        }
+        // an empty line is the gap between paragraphs
+        Markdown::Line(text) if text.is_empty() => ui.add_space(ui.spacing().item_spacing.y),
+        Markdown::Line(text) => show_text(ui, text),
+        Markdown::FootnoteDef(label, text) => {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(RichText::new(format!("[^{}]:", label)).weak());
+                show_text(ui, text);
+            });
+        }
+        Markdown::Table(header, aligns, rows) => {
+            let id = ui.next_auto_id();
+            Grid::new(id).striped(true).show(ui, |ui| {
+                show_row(ui, header, aligns, true);
+                for row in rows {
+                    show_row(ui, row, aligns, false);
+                }
+            });
+        }
+        Markdown::Custom(_, text) => {
+            ui.label(text.trim_end_matches('\n'));
+        }
+        Markdown::Alert(alert, lines) => {
+            ui.group(|ui| {
+                ui.label(RichText::new(alert.title()).strong());
+                for line in lines.iter().filter(|line| !line.is_empty()) {
+                    show_text(ui, line);
+                }
+            });
+        }
+    }
+}
+
+// the marker in front, nested lists indented underneath
+fn show_item(ui: &mut Ui, item: &ListItem, marker: String) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label(RichText::new(marker).weak());
+        show_text(ui, &item.text);
+    });
+    if !item.children.is_empty() {
+        let id = ui.next_auto_id();
+        ui.indent(id, |ui| show(ui, &item.children));
+    }
+}
+
+fn show_row(ui: &mut Ui, cells: &[MarkdownText], aligns: &[Align], header: bool) {
+    for (cell, align) in cells.iter().zip(aligns) {
+        let layout = match align {
+            Align::Right => egui::Layout::right_to_left(egui::Align::Center),
+            Align::Center => egui::Layout::top_down(egui::Align::Center),
+            Align::Default | Align::Left => egui::Layout::left_to_right(egui::Align::Center),
+        };
+        ui.with_layout(layout, |ui| {
+            for part in cell {
+                let text = rich_text(part);
+                show_inline(ui, if header { text.strong() } else { text }, part);
+            }
+        });
+    }
+    ui.end_row();
+}
+
+fn show_text(ui: &mut Ui, text: &[MarkdownInline]) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for part in text {
+            show_inline(ui, rich_text(part), part);
+        }
+    });
+}
+
+fn show_inline(ui: &mut Ui, text: RichText, part: &MarkdownInline) {
+    match part {
+        MarkdownInline::Link(_, url) => {
+            ui.hyperlink_to(text, url);
+        }
+        MarkdownInline::Image(alt, url) => {
+            ui.add(egui::Image::new(url.to_string()).max_width(ui.available_width()))
+                .on_hover_text(alt);
+        }
+        _ => {
+            ui.label(text);
+        }
+    }
+}
+
+// how a piece of inline markdown reads as egui text
+pub fn rich_text(part: &MarkdownInline) -> RichText {
+    match part {
+        MarkdownInline::Plaintext(text) => RichText::new(decode(text)),
+        MarkdownInline::Bold(text) => RichText::new(decode(text)).strong(),
+        MarkdownInline::Italic(text) => RichText::new(decode(text)).italics(),
+        MarkdownInline::Inserted(text) => RichText::new(decode(text)).underline(),
+        MarkdownInline::InlineCode(code) => RichText::new(code).code(),
+        MarkdownInline::Link(text, _) => RichText::new(decode(text)),
+        MarkdownInline::Image(alt, _) => RichText::new(alt).weak(),
+        MarkdownInline::FootnoteRef(label) => RichText::new(format!("[^{}]", label)).weak(),
+        MarkdownInline::Citation(keys) => RichText::new(format!("[@{}]", keys.join("; @"))).weak(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    #[test]
+    fn test_rich_text() {
+        assert_eq!(
+            rich_text(&MarkdownInline::Citation(vec![
+                String::from("a"),
+                String::from("b")
+            ]))
+            .text(),
+            "[@a; @b]"
+        );
+    }
+
+    #[test]
+    fn test_show() {
+        let (_, md) = parse_markdown(
+            "# Hi *there*\n- [one](a.md)\n  1. two\n\n| a | b |\n|--:|:-:|\n| c | d |\n```\nlet x;\n```\n[^n]: note\n",
+        )
+        .unwrap();
+        let ctx = egui::Context::default();
+        let mut height = 0.0;
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                show(ui, &md);
+                height = ui.min_rect().height();
+            });
+        });
+        assert!(height > 0.0);
+    }
+}
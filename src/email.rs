@@ -0,0 +1,153 @@
+use crate::options::HtmlOptions;
+use crate::prelude::*;
+use crate::translator;
+use crate::Markdown;
+
+const MONOSPACE: &str = "font-family: Menlo, Consolas, monospace; font-size: 14px;";
+
+// html that email clients show the way a browser would: they drop
+// stylesheets and `<section>`s, so every element carries its own `style`
+// and there are no classes left to style
+pub fn translate(md: &[Markdown], options: &HtmlOptions) -> String {
+    format!(
+        "<div style=\"font-family: Helvetica, Arial, sans-serif; font-size: 16px; line-height: 1.5; color: #222222;\">{}</div>",
+        restyle(&translator::translate_with_options(md, options))
+    )
+}
+
+// swaps the class of every tag the translator writes for inline styles
+pub fn restyle(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() * 2);
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            out.push_str("</");
+            out.push_str(element(name));
+            out.push('>');
+            continue;
+        }
+        if tag.starts_with('!') {
+            out.push('<');
+            out.push_str(tag);
+            out.push('>');
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/').trim_end();
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = &tag[..name_end];
+        let (attributes, class) = without_class(&tag[name_end..]);
+
+        out.push('<');
+        out.push_str(element(name));
+        out.push_str(&attributes);
+        let style = style(name, class);
+        if !style.is_empty() && !attributes.contains(" style=") {
+            out.push_str(" style=\"");
+            out.push_str(style);
+            out.push('"');
+        }
+        if self_closing {
+            out.push_str(" /");
+        }
+        out.push('>');
+    }
+    out.push_str(rest);
+    out
+}
+
+fn element(name: &str) -> &str {
+    if name == "section" {
+        "div"
+    } else {
+        name
+    }
+}
+
+// the attributes without their `class="..."`, along with the class
+fn without_class(attributes: &str) -> (String, &str) {
+    match attributes.find(" class=\"") {
+        Some(start) => {
+            let value = start + " class=\"".len();
+            let end = attributes[value..]
+                .find('"')
+                .map_or(attributes.len(), |end| value + end);
+            (
+                format!(
+                    "{}{}",
+                    &attributes[..start],
+                    &attributes[(end + 1).min(attributes.len())..]
+                ),
+                &attributes[value..end],
+            )
+        }
+        None => (attributes.to_string(), ""),
+    }
+}
+
+fn style(name: &str, class: &str) -> &'static str {
+    match (name, class) {
+        ("h1", _) => "margin: 0 0 16px; font-size: 28px; line-height: 1.25;",
+        ("h2", _) => "margin: 24px 0 16px; font-size: 24px; line-height: 1.25;",
+        ("h3", _) => "margin: 24px 0 16px; font-size: 20px; line-height: 1.25;",
+        ("h4", _) | ("h5", _) | ("h6", _) => "margin: 24px 0 16px; font-size: 16px;",
+        ("p", _) => "margin: 0 0 16px;",
+        ("a", "anchor") => "color: #999999; text-decoration: none;",
+        ("a", "footnote-backref") => "color: #0366d6; text-decoration: none;",
+        ("a", _) => "color: #0366d6; text-decoration: underline;",
+        ("img", _) => "max-width: 100%; height: auto; border: 0;",
+        ("pre", _) => "margin: 0 0 16px; padding: 12px; background-color: #f6f8fa; border-radius: 4px; overflow: auto;",
+        // code blocks are the ones with a language class, the pre around them has the background
+        ("code", "") => "font-family: Menlo, Consolas, monospace; font-size: 14px; padding: 2px 4px; background-color: #f6f8fa; border-radius: 3px;",
+        ("code", _) => MONOSPACE,
+        ("ul", _) | ("ol", _) => "margin: 0 0 16px; padding-left: 24px;",
+        ("li", _) => "margin: 0 0 4px;",
+        ("table", _) => "margin: 0 0 16px; border-collapse: collapse;",
+        ("th", _) => "padding: 6px 12px; border: 1px solid #dddddd; font-weight: bold;",
+        ("td", _) => "padding: 6px 12px; border: 1px solid #dddddd;",
+        ("section", _) => "margin-top: 24px; padding-top: 8px; border-top: 1px solid #dddddd; font-size: 14px;",
+        ("sup", _) => "font-size: 12px; line-height: 0;",
+        ("span", "heading-number") => "color: #666666;",
+        ("cite", _) => "font-style: normal;",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    #[test]
+    fn test_restyle() {
+        assert_eq!(
+            restyle("<p>a <code>b</code></p><pre><code class=\"lang-rust\">c</code></pre><img src=\"x.png\" alt=\"\" />"),
+            format!(
+                "<p style=\"{}\">a <code style=\"{}\">b</code></p><pre style=\"{}\"><code style=\"{}\">c</code></pre><img src=\"x.png\" alt=\"\" style=\"{}\" />",
+                style("p", ""),
+                style("code", ""),
+                style("pre", ""),
+                MONOSPACE,
+                style("img", "")
+            )
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        let (_, md) = parse_markdown("# Hi\nnote[^1]\n[^1]: here\n").unwrap();
+        let html = translate(&md, &HtmlOptions::default());
+        assert!(!html.contains("class="));
+        assert!(!html.contains("<section"));
+        assert!(html.contains("<div style=\"margin-top: 24px;"));
+        assert!(html.contains("<h1 style=\"margin: 0 0 16px;"));
+    }
+}
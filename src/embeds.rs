@@ -0,0 +1,220 @@
+use crate::document::Document;
+use crate::prelude::*;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use crate::Url;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+const IMAGES: [&str; 9] = [
+    "png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "bmp", "ico",
+];
+
+// obsidian style embeds, which the parser keeps as text. `![[cat.png]]` becomes
+// the image, and a line with nothing but `![[note]]` on it becomes the blocks of
+// that note, from the markdown `resolve` hands back for its name. notes can embed
+// notes of their own, but never one that is already being embedded, so a cycle
+// stops where it would go round again. `name` is the document's own, as notes
+// would embed it, so none of them can embed it back. embeds that can't be had
+// stay as written
+pub fn embed(
+    blocks: &mut Vec<Markdown>,
+    name: &str,
+    mut resolve: impl FnMut(&str) -> Option<String>,
+) {
+    embed_within(blocks, &mut resolve, &mut vec![name.to_string()]);
+}
+
+fn embed_within(
+    blocks: &mut Vec<Markdown>,
+    resolve: &mut dyn FnMut(&str) -> Option<String>,
+    // the notes being embedded, outermost first
+    embedding: &mut Vec<String>,
+) {
+    let mut i = 0;
+    while i < blocks.len() {
+        for text in blocks[i].texts_mut() {
+            embed_images(text);
+        }
+        let name = match &blocks[i] {
+            Markdown::Line(text) => note(text),
+            _ => None,
+        };
+        match name.and_then(|name| note_blocks(&name, resolve, embedding)) {
+            Some(note) => {
+                let len = note.len();
+                blocks.splice(i..=i, note);
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+fn note_blocks(
+    name: &str,
+    resolve: &mut dyn FnMut(&str) -> Option<String>,
+    embedding: &mut Vec<String>,
+) -> Option<Vec<Markdown>> {
+    if embedding.iter().any(|outer| same_note(outer, name)) {
+        tracing::warn!(
+            "{} -> {} goes round in a circle, the last embed is left as written",
+            embedding.join(" -> "),
+            name
+        );
+        return None;
+    }
+    let src = match resolve(name) {
+        Some(src) => src,
+        None => {
+            tracing::warn!("there is no note {} to embed", name);
+            return None;
+        }
+    };
+    let mut blocks = match Document::parse(&src) {
+        Ok(doc) => doc.blocks,
+        Err(e) => {
+            tracing::warn!("the note {} could not be embedded: {}", name, e);
+            return None;
+        }
+    };
+    embedding.push(name.to_string());
+    embed_within(&mut blocks, resolve, embedding);
+    embedding.pop();
+    Some(blocks)
+}
+
+fn embed_images(text: &mut MarkdownText) {
+    for inline in text.iter_mut() {
+        let image = match inline {
+            MarkdownInline::Plaintext(text) => match target(text) {
+                Some((name, alias)) if is_image(name) => {
+                    // `|300` and `|300x200` are sizes, which there is nowhere to put
+                    let alt = if alias.chars().all(|c| c.is_ascii_digit() || c == 'x') {
+                        ""
+                    } else {
+                        alias
+                    };
+                    MarkdownInline::Image(alt.to_string(), Url::from(name))
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+        *inline = image;
+    }
+}
+
+// the name of the note a line embeds, when there is nothing else on it
+fn note(text: &MarkdownText) -> Option<String> {
+    let mut parts = text.iter().filter(|part| match part {
+        MarkdownInline::Plaintext(text) => !text.trim().is_empty(),
+        _ => true,
+    });
+    let name = match (parts.next(), parts.next()) {
+        (Some(MarkdownInline::Plaintext(text)), None) => target(text.trim())?.0,
+        _ => return None,
+    };
+    Some(name.to_string()).filter(|name| !is_image(name))
+}
+
+// the name and the text after its `|`, of an embed as the parser keeps it
+fn target(embed: &str) -> Option<(&str, &str)> {
+    let inner = embed.strip_prefix("![[")?.strip_suffix("]]")?;
+    let (name, alias) = inner.split_once('|').unwrap_or((inner, ""));
+    Some((name.trim(), alias.trim())).filter(|(name, _)| !name.is_empty())
+}
+
+// `ideas`, `ideas.md` and `ideas#later` are all the one note
+fn same_note(a: &str, b: &str) -> bool {
+    let note = |name: &str| {
+        let name = name.split('#').next().unwrap_or(name);
+        name.strip_suffix(".md").unwrap_or(name).to_string()
+    };
+    note(a) == note(b)
+}
+
+fn is_image(name: &str) -> bool {
+    name.rsplit_once('.')
+        .is_some_and(|(_, ext)| IMAGES.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// `embed` for the file at `relative` in the `src` directory, with notes found
+// next to it the way images are, `![[ideas]]` is read from `ideas.md`. notes
+// outside of `src` are never read, those embeds stay as written. a `#heading`
+// after the name is passed over, the whole note is embedded
+#[cfg(feature = "std")]
+pub fn embed_files(blocks: &mut Vec<Markdown>, src: &Path, relative: &Path) {
+    let name = relative.file_stem().unwrap_or_default().to_string_lossy();
+    embed(blocks, &name, |name| {
+        let name = name.split('#').next().unwrap_or(name);
+        let file = if name.ends_with(".md") {
+            name.to_string()
+        } else {
+            format!("{}.md", name)
+        };
+        match crate::assets::resolve(relative, &file) {
+            Some(path) => crate::batch::read_text(&src.join(path)).ok(),
+            None => {
+                tracing::warn!(
+                    "{} is outside of {}, it is not embedded",
+                    name,
+                    src.display()
+                );
+                None
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translator::translate;
+    use alloc::collections::BTreeMap;
+
+    fn render(src: &str, notes: &BTreeMap<&str, &str>) -> String {
+        let mut blocks = Document::parse(src).unwrap().blocks;
+        embed(&mut blocks, "top", |name| {
+            notes.get(name).map(|note| note.to_string())
+        });
+        translate(&blocks)
+    }
+
+    #[test]
+    fn test_embed() {
+        let notes = BTreeMap::from([
+            ("a", "# A\nsee ![[b]]\n![[b]]\n"),
+            ("b", "from b ![[pic.PNG|300]]\n![[a]]\n"),
+        ]);
+        assert_eq!(
+            render("![[a]]\n![[cat.png|a cat]] ![[missing]]\n![[top.md]]\n", &notes),
+            "<h1>A</h1><p>see ![[b]]</p><p>from b <img src=\"pic.PNG\" alt=\"\" /></p><p>![[a]]</p><p><img src=\"cat.png\" alt=\"a cat\" /> ![[missing]]</p><p>![[top.md]]</p>"
+        );
+        assert_eq!(render("![[nothing]]\n", &notes), "<p>![[nothing]]</p>");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_embed_files() {
+        use crate::batch::fixtures::{scratch_dir, write};
+
+        let dir = scratch_dir("embeds");
+        let src = dir.join("content");
+        write(&src.join("notes/ideas.md"), "an idea\n");
+        write(&src.join("top.md"), "on top\n");
+        write(&dir.join("private/diary.md"), "a secret\n");
+        let mut blocks = Document::parse(
+            "![[ideas]]\n![[../top]]\n![[../../private/diary]]\n![[/notes/ideas#later]]\n",
+        )
+        .unwrap()
+        .blocks;
+        embed_files(&mut blocks, &src, Path::new("notes/page.md"));
+        assert_eq!(
+            translate(&blocks),
+            "<p>an idea</p><p>on top</p><p>![[../../private/diary]]</p><p>an idea</p>"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+// the named references people actually type, html has a couple thousand more
+const NAMED: [(&str, char); 42] = [
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("shy", '\u{ad}'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("hellip", '…'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("laquo", '«'),
+    ("raquo", '»'),
+    ("bull", '•'),
+    ("middot", '·'),
+    ("deg", '°'),
+    ("plusmn", '±'),
+    ("times", '×'),
+    ("divide", '÷'),
+    ("ne", '≠'),
+    ("le", '≤'),
+    ("ge", '≥'),
+    ("larr", '←'),
+    ("rarr", '→'),
+    ("uarr", '↑'),
+    ("darr", '↓'),
+    ("harr", '↔'),
+    ("euro", '€'),
+    ("pound", '£'),
+    ("yen", '¥'),
+    ("cent", '¢'),
+    ("sect", '§'),
+    ("para", '¶'),
+    ("frac12", '½'),
+    ("frac14", '¼'),
+    ("frac34", '¾'),
+];
+
+// the character a reference at the start of `text` stands for and how long
+// the reference is, for `&amp;`, `&#169;` and `&#x1F600;`
+fn reference(text: &str) -> Option<(char, usize)> {
+    let body = text.strip_prefix('&')?;
+    // bytes rather than a slice, which could end inside a multibyte character
+    let end = body.bytes().take(12).position(|b| b == b';')?;
+    let name = &body[..end];
+    let c = match name.strip_prefix('#') {
+        Some(hex) if hex.starts_with(['x', 'X']) => {
+            char::from_u32(u32::from_str_radix(&hex[1..], 16).ok()?)
+        }
+        Some(decimal) => char::from_u32(decimal.parse().ok()?),
+        None => NAMED
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, c)| *c),
+    }
+    .filter(|c| *c != '\0')?;
+    Some((c, end + 2))
+}
+
+// the text with its character references replaced by what they stand for,
+// for renderers that write text rather than html. anything that only looks
+// like a reference, `AT&T;`, is left as it is
+pub fn decode(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        match reference(rest) {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+// like `escape`, but references already in the text are kept, so a title
+// written as `Q&amp;A` does not come out as `Q&amp;amp;A`
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        match c {
+            '&' if reference(&text[i..]).is_some() => out.push('&'),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode("Q&amp;A &copy; 2021 &#169; &#x1F600; AT&T; &bogus; &#0;"),
+            "Q&A © 2021 © 😀 AT&T; &bogus; &#0;"
+        );
+        assert!(matches!(decode("no references"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_decode_multibyte() {
+        assert_eq!(decode("&amp;日本語"), "&日本語");
+        assert_eq!(decode("&amp;😀 &日本語;"), "&😀 &日本語;");
+        assert_eq!(escape("&amp;日本語"), "&amp;日本語");
+        assert_eq!(escape("&😀;"), "&amp;😀;");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("Q&amp;A & <b> &#x1F600;"),
+            "Q&amp;A &amp; &lt;b&gt; &#x1F600;"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::links::is_external;
+use crate::prelude::*;
+
+// the filters that come with prose, by the name configs give them
+pub const NAMES: [&str; 2] = ["external-links", "lazy-images"];
+
+pub fn named(name: &str) -> Option<fn(String) -> String> {
+    match name {
+        "external-links" => Some(external_links),
+        "lazy-images" => Some(lazy_images),
+        _ => None,
+    }
+}
+
+// links leaving the site open in a new tab, without handing it the page they came from
+pub fn external_links(html: String) -> String {
+    add_attributes(&html, "a", |attributes| {
+        match attribute(attributes, "href") {
+            Some(href) if is_external(href) && attribute(attributes, "target").is_none() => {
+                Some(" target=\"_blank\" rel=\"noopener noreferrer\"")
+            }
+            _ => None,
+        }
+    })
+}
+
+// images are only fetched once they are about to scroll into view
+pub fn lazy_images(html: String) -> String {
+    add_attributes(&html, "img", |attributes| {
+        match attribute(attributes, "loading") {
+            Some(_) => None,
+            None => Some(" loading=\"lazy\""),
+        }
+    })
+}
+
+// puts what `extra` comes up with at the end of every `name` tag
+fn add_attributes(html: &str, name: &str, extra: impl Fn(&str) -> Option<&'static str>) -> String {
+    let open = format!("<{}", name);
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        let end = match after.find('>') {
+            Some(end) if after.starts_with([' ', '>', '/']) => end,
+            _ => {
+                out.push_str(&rest[..start + open.len()]);
+                rest = after;
+                continue;
+            }
+        };
+        let attributes = after[..end].trim_end_matches('/').trim_end();
+        out.push_str(&rest[..start + open.len()]);
+        out.push_str(attributes);
+        if let Some(extra) = extra(attributes) {
+            out.push_str(extra);
+        }
+        out.push_str(&after[attributes.len()..=end]);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!(" {}=\"", name);
+    let start = attributes.find(&key)? + key.len();
+    let end = attributes[start..].find('"')?;
+    Some(&attributes[start..start + end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_links() {
+        assert_eq!(
+            external_links(String::from(
+                "<a href=\"https://x.com\">x</a> <a href=\"/y\">y</a> <abbr>z</abbr>"
+            )),
+            "<a href=\"https://x.com\" target=\"_blank\" rel=\"noopener noreferrer\">x</a> <a href=\"/y\">y</a> <abbr>z</abbr>"
+        );
+    }
+
+    #[test]
+    fn test_lazy_images() {
+        assert_eq!(
+            lazy_images(String::from(
+                "<img src=\"a.png\" alt=\"a\" /><img loading=\"eager\" src=\"b.png\" />"
+            )),
+            "<img src=\"a.png\" alt=\"a\" loading=\"lazy\" /><img loading=\"eager\" src=\"b.png\" />"
+        );
+        assert!(named("lazy-images").is_some());
+        assert!(named("nope").is_none());
+    }
+}
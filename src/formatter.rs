@@ -0,0 +1,258 @@
+use crate::prelude::*;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+
+// turns a syntax tree back into markdown, always picking the same spelling:
+// `-` bullets, sequential ordered list numbers, fenced code blocks,
+// no trailing whitespace and at most one blank line between blocks
+pub fn format(md: &[Markdown]) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    let mut blocks = md.iter().peekable();
+    while let Some(block) = blocks.next() {
+        if let Markdown::Line(text) = block {
+            if format_text(text).is_empty() {
+                blank = true;
+                continue;
+            }
+        }
+        if blank && !out.is_empty() {
+            out.push('\n');
+        }
+        blank = false;
+
+        out.push_str(&format_block(block));
+        if let Markdown::Codeblock(_, _) = block {
+            // the parser reads the newline after a closing fence as an empty line
+            if let Some(Markdown::Line(text)) = blocks.peek() {
+                if text.is_empty() {
+                    blocks.next();
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// one block spelled the way `format` would, covering the same text the parser
+// read it from. that means a code block stops at its closing fence
+pub fn format_block(block: &Markdown) -> String {
+    match block {
+        Markdown::Heading(level, text) => format!("{}\n", format_header(*level, text)),
+        Markdown::UnorderedList(lines) => format_unordered_list(lines, 0),
+        Markdown::OrderedList(lines) => format_ordered_list(lines, 0),
+        Markdown::Codeblock(lang, code) => format_codeblock(lang, code),
+        Markdown::Line(text) => format!("{}\n", format_text(text)),
+        Markdown::Table(header, aligns, rows) => format_table(header, aligns, rows),
+        Markdown::FootnoteDef(label, text) => format!("[^{}]: {}\n", label, format_text(text)),
+        // the text a registered parser handed back, which is usually all of the block
+        Markdown::Custom(_, text) => format!("{}\n", text.trim_end_matches('\n')),
+        Markdown::Alert(alert, lines) => {
+            let mut out = format!("> [!{}]\n", alert.name().to_uppercase());
+            for line in lines {
+                let text = format_text(line);
+                if text.is_empty() {
+                    out.push_str(">\n");
+                } else {
+                    out.push_str(&format!("> {}\n", text));
+                }
+            }
+            out
+        }
+    }
+}
+
+fn format_header(level: HeadingLevel, text: &MarkdownText) -> String {
+    format!("{} {}", "#".repeat(level.get()), format_text(text))
+}
+
+fn format_unordered_list(lines: &[ListItem], indent: usize) -> String {
+    lines
+        .iter()
+        .map(|line| format_list_item("- ", line, indent))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn format_ordered_list(lines: &[(usize, ListItem)], indent: usize) -> String {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, (_, line))| format_list_item(&format!("{}. ", i + 1), line, indent))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+// nested lists line up with the text of the item they belong to
+fn format_list_item(marker: &str, item: &ListItem, indent: usize) -> String {
+    let mut out = format!(
+        "{}{}{}\n",
+        " ".repeat(indent),
+        marker,
+        format_text(&item.text)
+    );
+    for child in &item.children {
+        match child {
+            Markdown::UnorderedList(lines) => {
+                out.push_str(&format_unordered_list(lines, indent + marker.len()))
+            }
+            Markdown::OrderedList(lines) => {
+                out.push_str(&format_ordered_list(lines, indent + marker.len()))
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn format_table(header: &[MarkdownText], aligns: &[Align], rows: &[Vec<MarkdownText>]) -> String {
+    let row = |cells: &[MarkdownText]| {
+        let cells = cells
+            .iter()
+            .map(|cell| format_text(cell).replace('|', "\\|"))
+            .collect::<Vec<String>>();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let delimiter = aligns
+        .iter()
+        .map(|align| match align {
+            Align::Default => "---",
+            Align::Left => ":---",
+            Align::Center => ":---:",
+            Align::Right => "---:",
+        })
+        .collect::<Vec<&str>>();
+    let mut out = row(header);
+    out.push_str(&format!("| {} |\n", delimiter.join(" | ")));
+    for cells in rows {
+        out.push_str(&row(cells));
+    }
+    out
+}
+
+fn format_codeblock(lang: &str, code: &str) -> String {
+    let lang = if lang == "__UNKNOWN__" { "" } else { lang };
+    let newline = if code.ends_with('\n') { "" } else { "\n" };
+    format!("```{}\n{}{}```", lang, code, newline)
+}
+
+pub(crate) fn format_text(text: &MarkdownText) -> String {
+    text.iter()
+        .map(format_inline)
+        .collect::<Vec<String>>()
+        .join("")
+        .trim_end()
+        .to_string()
+}
+
+fn format_inline(part: &MarkdownInline) -> String {
+    match part {
+        MarkdownInline::Bold(text) => format!("**{}**", text),
+        MarkdownInline::Italic(text) => format!("*{}*", text),
+        MarkdownInline::Inserted(text) => format!("++{}++", text),
+        MarkdownInline::InlineCode(code) => format!("`{}`", code),
+        MarkdownInline::Link(text, url) => format!("[{}]({})", text, url.as_written()),
+        MarkdownInline::Image(text, url) => format!("![{}]({})", text, url.as_written()),
+        MarkdownInline::Plaintext(text) => text.to_string(),
+        MarkdownInline::FootnoteRef(label) => format!("[^{}]", label),
+        MarkdownInline::Citation(keys) => format!(
+            "[{}]",
+            keys.iter()
+                .map(|key| format!("@{}", key))
+                .collect::<Vec<String>>()
+                .join("; ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+    use crate::Url;
+
+    fn reformat(md: &str) -> String {
+        let (rest, parsed) = parse_markdown(md).unwrap();
+        assert_eq!(rest, "");
+        format(&parsed)
+    }
+
+    #[test]
+    fn test_format_text() {
+        assert_eq!(
+            format_text(
+                &vec![
+                    MarkdownInline::Plaintext(String::from("see ")),
+                    MarkdownInline::Bold(String::from("bold")),
+                    MarkdownInline::Italic(String::from("italic")),
+                    MarkdownInline::InlineCode(String::from("code")),
+                    MarkdownInline::Link(String::from("tag"), Url::from("https://link.com")),
+                    MarkdownInline::Image(String::from("alt"), Url::from("a.png")),
+                    MarkdownInline::Plaintext(String::from(" the end   ")),
+                ]
+                .into()
+            ),
+            String::from("see **bold***italic*`code`[tag](https://link.com)![alt](a.png) the end")
+        );
+    }
+
+    #[test]
+    fn test_format_lists() {
+        assert_eq!(
+            reformat("- one\n- two\n\n3. three\n9. four\n"),
+            String::from("- one\n- two\n\n1. three\n2. four\n")
+        );
+        assert_eq!(
+            reformat("- one\n    5. a\n    6. b\n        - deep\n- two\n"),
+            String::from("- one\n  1. a\n  2. b\n     - deep\n- two\n")
+        );
+    }
+
+    #[test]
+    fn test_format_codeblock() {
+        assert_eq!(format_codeblock("__UNKNOWN__", "x\n"), "```\nx\n```");
+        assert_eq!(format_codeblock("rust", "x"), "```rust\nx\n```");
+        assert_eq!(
+            reformat("```bash\npip install foobar\n```\n\n\n# Next\n"),
+            String::from("```bash\npip install foobar\n```\n\n# Next\n")
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let md =
+            "\n\n# Foobar   \n\n\n\nSome *text*  here.\n## Usage\n```python\nimport foobar\n```\n";
+        let formatted = reformat(md);
+        assert_eq!(
+            formatted,
+            String::from(
+                "# Foobar\n\nSome *text*  here.\n## Usage\n```python\nimport foobar\n```\n"
+            )
+        );
+        // formatting is idempotent
+        assert_eq!(reformat(&formatted), formatted);
+
+        assert_eq!(reformat("> [!tip]\n>one\n>\n"), "> [!TIP]\n> one\n>\n");
+    }
+
+    #[test]
+    fn test_format_table() {
+        assert_eq!(
+            reformat("|a|*b*|\n|:-|:-:|\n|1 \\| 2|\n"),
+            String::from("| a | *b* |\n| :--- | :---: |\n| 1 \\| 2 |  |\n")
+        );
+    }
+
+    #[test]
+    fn test_format_footnotes() {
+        assert_eq!(
+            reformat("a note[^1]\n\n[^1]: *the*  note  \n"),
+            String::from("a note[^1]\n\n[^1]: *the*  note\n")
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use crate::prelude::*;
+use crate::unicode;
+use alloc::collections::BTreeMap;
+use core::fmt;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until},
+    combinator::{eof, map},
+    sequence::{preceded, terminated},
+    IResult,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Bool(bool),
+    Number(f64),
+    List(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    // a single value is treated as a list of one, so `tags: rust` works like `tags: [rust]`
+    pub fn as_list(&self) -> Vec<&Value> {
+        match self {
+            Value::List(values) => values.iter().collect(),
+            value => vec![value],
+        }
+    }
+}
+
+// how a value reads in running text, lists are joined with commas
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::List(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// the `key: value` block fenced by `---` lines at the very top of a document.
+// this understands the bits of yaml people actually write there: strings,
+// quoted strings, booleans, numbers and lists, both `[a, b]` and `- a` style
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrontMatter {
+    fields: BTreeMap<String, Value>,
+}
+
+impl FrontMatter {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.get(key)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Value::as_str)
+    }
+
+    pub fn insert(&mut self, key: &str, value: Value) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.fields.iter()
+    }
+
+    fn from_lines(body: &str) -> FrontMatter {
+        let mut front_matter = FrontMatter::default();
+        let mut list_key: Option<String> = None;
+        for line in body.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if let (Some(key), Some(item)) = (&list_key, trimmed.strip_prefix("- ")) {
+                let entry = front_matter
+                    .fields
+                    .entry(key.clone())
+                    .or_insert_with(|| Value::List(vec![]));
+                if let Value::List(items) = entry {
+                    items.push(parse_scalar(item));
+                } else {
+                    *entry = Value::List(vec![parse_scalar(item)]);
+                }
+                continue;
+            }
+            list_key = None;
+            if let Some((key, value)) = trimmed.split_once(':') {
+                let (key, value) = (key.trim().to_string(), value.trim());
+                if value.is_empty() {
+                    // either an empty value or the start of a `- item` list
+                    front_matter
+                        .fields
+                        .insert(key.clone(), Value::String(String::new()));
+                    list_key = Some(key);
+                } else {
+                    front_matter.fields.insert(key, parse_value(value));
+                }
+            }
+        }
+        front_matter
+    }
+}
+
+fn parse_value(value: &str) -> Value {
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) => Value::List(
+            items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(parse_scalar)
+                .collect(),
+        ),
+        None => parse_scalar(value),
+    }
+}
+
+fn parse_scalar(value: &str) -> Value {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return Value::String(value[1..value.len() - 1].to_string());
+        }
+    }
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match value.parse::<f64>() {
+            Ok(n) if n.is_finite() => Value::Number(n),
+            _ => Value::String(value.to_string()),
+        },
+    }
+}
+
+pub fn parse_front_matter(i: &str) -> IResult<&str, FrontMatter> {
+    map(
+        terminated(
+            preceded(
+                tag("---\n"),
+                alt((
+                    map(tag("---"), |_| ""),
+                    terminated(take_until("\n---"), tag("\n---")),
+                )),
+            ),
+            alt((tag("\n"), eof)),
+        ),
+        FrontMatter::from_lines,
+    )(i)
+}
+
+// pulls the front matter (if there is any) off the top of a document,
+// handing back the markdown that follows it. a byte order mark goes too
+pub fn split(md: &str) -> (FrontMatter, &str) {
+    let md = unicode::strip_bom(md);
+    match parse_front_matter(md) {
+        Ok((body, front_matter)) => (front_matter, body),
+        Err(_) => (FrontMatter::default(), md),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scalar() {
+        assert_eq!(parse_scalar("hello"), Value::String(String::from("hello")));
+        assert_eq!(
+            parse_scalar("\"quoted: yes\""),
+            Value::String(String::from("quoted: yes"))
+        );
+        assert_eq!(
+            parse_scalar("'single'"),
+            Value::String(String::from("single"))
+        );
+        assert_eq!(parse_scalar("true"), Value::Bool(true));
+        assert_eq!(parse_scalar("3.5"), Value::Number(3.5));
+        assert_eq!(
+            parse_scalar("2021-03-04"),
+            Value::String(String::from("2021-03-04"))
+        );
+        assert_eq!(parse_scalar("NaN"), Value::String(String::from("NaN")));
+    }
+
+    #[test]
+    fn test_parse_front_matter() {
+        let (rest, front_matter) = parse_front_matter(
+            "---\ntitle: Hello: World\ndraft: false\n# a comment\ntags: [rust, \"wasm\"]\nauthors:\n  - hg\n  - max\nempty:\n---\n# Body\n",
+        )
+        .unwrap();
+        assert_eq!(rest, "# Body\n");
+        assert_eq!(front_matter.get_str("title"), Some("Hello: World"));
+        assert_eq!(front_matter.get("draft"), Some(&Value::Bool(false)));
+        assert_eq!(
+            front_matter.get("tags"),
+            Some(&Value::List(vec![
+                Value::String(String::from("rust")),
+                Value::String(String::from("wasm"))
+            ]))
+        );
+        assert_eq!(
+            front_matter.get("authors"),
+            Some(&Value::List(vec![
+                Value::String(String::from("hg")),
+                Value::String(String::from("max"))
+            ]))
+        );
+        assert_eq!(front_matter.get_str("empty"), Some(""));
+    }
+
+    #[test]
+    fn test_split() {
+        let (front_matter, body) = split("---\n---\nhello\n");
+        assert!(front_matter.is_empty());
+        assert_eq!(body, "hello\n");
+
+        let (front_matter, body) = split("---\ntitle: x\n---");
+        assert_eq!(front_matter.get_str("title"), Some("x"));
+        assert_eq!(body, "");
+
+        // a horizontal-rule looking line further down is left alone
+        let (front_matter, body) = split("hello\n---\nworld\n");
+        assert!(front_matter.is_empty());
+        assert_eq!(body, "hello\n---\nworld\n");
+
+        // an unterminated block is not front matter
+        let (front_matter, body) = split("---\ntitle: x\n");
+        assert!(front_matter.is_empty());
+        assert_eq!(body, "---\ntitle: x\n");
+
+        let (front_matter, body) = split("\u{feff}---\ntitle: x\n---\n# Hi\n");
+        assert_eq!(front_matter.get_str("title"), Some("x"));
+        assert_eq!(body, "# Hi\n");
+    }
+}
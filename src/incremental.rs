@@ -0,0 +1,226 @@
+use crate::document;
+use crate::frontmatter::{self, FrontMatter};
+use crate::parser;
+use crate::prelude::*;
+use crate::Markdown;
+use crate::Span;
+use alloc::borrow::Cow;
+use core::ops::Range;
+
+// a parsed document that is kept up to date as its source is edited, for
+// editors that re-render on every keystroke. an edit only re-parses the
+// blocks around it, everything further down is moved along and kept
+#[derive(Clone, Debug, PartialEq)]
+pub struct IncrementalDocument {
+    src: String,
+    front_matter: FrontMatter,
+    // where the markdown starts, after any front matter
+    body_offset: usize,
+    blocks: Vec<(Markdown, Span)>,
+}
+
+impl IncrementalDocument {
+    pub fn parse(src: &str) -> IncrementalDocument {
+        let (front_matter, body) = frontmatter::split(src);
+        IncrementalDocument {
+            src: src.to_string(),
+            front_matter,
+            body_offset: src.len() - body.len(),
+            blocks: document::spanned_blocks(src),
+        }
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    pub fn front_matter(&self) -> &FrontMatter {
+        &self.front_matter
+    }
+
+    // like `document::spanned_blocks`, this stops wherever the parser gave up
+    pub fn blocks(&self) -> &[(Markdown, Span)] {
+        &self.blocks
+    }
+
+    // replaces the `range` bytes of the source with `text`, handing back
+    // which blocks are new. the blocks always match a fresh parse of the source
+    pub fn edit(&mut self, range: Span, text: &str) -> Range<usize> {
+        self.src.replace_range(range.start..range.end, text);
+        let delta = text.len() as isize - (range.end - range.start) as isize;
+        let shift = |offset: usize| (offset as isize + delta) as usize;
+
+        // anything touching the front matter could move where the body starts
+        let (front_matter, body) = frontmatter::split(&self.src);
+        let body_offset = self.src.len() - body.len();
+        if range.start <= self.body_offset || body_offset != shift(self.body_offset) {
+            *self = IncrementalDocument::parse(&self.src);
+            return 0..self.blocks.len();
+        }
+        self.front_matter = front_matter;
+        self.body_offset = body_offset;
+
+        // the block before the edit goes again too, it might swallow the edited
+        // lines (a line turning into a list item) or let go of them
+        let edited = self
+            .blocks
+            .iter()
+            .position(|(_, span)| span.end > range.start)
+            .unwrap_or(self.blocks.len());
+        let restart = edited.saturating_sub(1);
+        let restart_offset = self
+            .blocks
+            .get(restart)
+            .map_or(self.body_offset, |(_, span)| span.start);
+
+        let window = terminated(&self.src[restart_offset..]);
+        let edit_end = range.start + text.len();
+        let mut rest: &str = &window;
+        let mut reparsed = vec![];
+        // the old block the parse lined back up with, from there on nothing changed
+        let mut resync = None;
+        let mut old = edited;
+        while !rest.is_empty() {
+            let (next, block) = match parser::parse_block(rest) {
+                Ok(parsed) => parsed,
+                Err(_) => break,
+            };
+            let start = restart_offset + window.len() - rest.len();
+            let end = (restart_offset + window.len() - next.len()).min(self.src.len());
+            reparsed.push((block, Span { start, end }));
+            rest = next;
+            if end < edit_end {
+                continue;
+            }
+            while old < self.blocks.len() && shift(self.blocks[old].1.end) < end {
+                old += 1;
+            }
+            if old < self.blocks.len()
+                && self.blocks[old].1.end >= range.end
+                && shift(self.blocks[old].1.end) == end
+            {
+                resync = Some(old + 1);
+                break;
+            }
+        }
+
+        let count = reparsed.len();
+        match resync {
+            Some(resync) => {
+                for (_, span) in &mut self.blocks[resync..] {
+                    span.start = shift(span.start);
+                    span.end = shift(span.end);
+                }
+                self.blocks.splice(restart..resync, reparsed);
+            }
+            // the parse ran to the end, or gave up like a full parse would
+            None => {
+                self.blocks.truncate(restart);
+                self.blocks.extend(reparsed);
+            }
+        }
+        restart..restart + count
+    }
+}
+
+fn terminated(md: &str) -> Cow<'_, str> {
+    if md.ends_with('\n') {
+        Cow::Borrowed(md)
+    } else {
+        Cow::Owned(format!("{}\n", md))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // makes an edit and checks the outcome against parsing from scratch
+    fn edit(src: &str, from: &str, to: &str) -> (IncrementalDocument, Range<usize>) {
+        let mut doc = IncrementalDocument::parse(src);
+        let start = src.find(from).unwrap();
+        let changed = doc.edit(
+            Span {
+                start,
+                end: start + from.len(),
+            },
+            to,
+        );
+        assert_eq!(doc, IncrementalDocument::parse(&src.replacen(from, to, 1)));
+        (doc, changed)
+    }
+
+    #[test]
+    fn test_edit_line() {
+        let src = "# Title\none\ntwo\nthree\n- a\n- b\nfour\n";
+        let (_, changed) = edit(src, "two", "*2*");
+        assert_eq!(changed, 1..3);
+        edit(src, "four", "");
+        edit(src, "three\n", "");
+        edit(src, "two\nthree", "2 3");
+        edit(src, "four\n", "four\nfive");
+    }
+
+    #[test]
+    fn test_edit_joins_blocks() {
+        let src = "- a\n- b\nc\nd\n";
+        edit(src, "c", "- c");
+        edit(src, "- b", "b");
+        edit(src, "d", "```\nd\n```");
+        edit(
+            "| a | b |\nnot a delimiter\nrow\n",
+            "not a delimiter",
+            "|---|---|",
+        );
+    }
+
+    #[test]
+    fn test_edit_fences() {
+        let src = "one\n```\ncode\n```\ntwo\n";
+        edit(src, "two", "```\n");
+        edit(src, "code", "`");
+        edit(src, "one", "```rust");
+        edit("text\n```\nopen\n", "open", "open\n```");
+    }
+
+    #[test]
+    fn test_edit_front_matter() {
+        let src = "---\ntitle: a\n---\n# Hi\n";
+        let (doc, _) = edit(src, "a", "b");
+        assert_eq!(doc.front_matter().get_str("title"), Some("b"));
+        edit("---\ntitle: a\n# Hi\nthere\n", "there", "---");
+        edit("# Hi\n", "", "---\n---\n");
+    }
+
+    #[test]
+    fn test_edit_anywhere() {
+        let src =
+            "---\nt: x\n---\n# Hi\n- a\n  1. b\n\n| a |\n|---|\n| b |\n```\nc\n```\n[^n]: d\ne";
+        for (at, _) in src.char_indices() {
+            for text in ["", "\n", "- ", "`", "x"] {
+                let mut doc = IncrementalDocument::parse(src);
+                doc.edit(Span { start: at, end: at }, text);
+                let mut edited = src.to_string();
+                edited.insert_str(at, text);
+                assert_eq!(doc, IncrementalDocument::parse(&edited), "{:?}", edited);
+            }
+            let mut doc = IncrementalDocument::parse(src);
+            doc.edit(
+                Span {
+                    start: at,
+                    end: at + 1,
+                },
+                "",
+            );
+            let mut edited = src.to_string();
+            edited.remove(at);
+            assert_eq!(doc, IncrementalDocument::parse(&edited), "{:?}", edited);
+        }
+    }
+
+    #[test]
+    fn test_edit_after_failure() {
+        edit("fine\n2 * 3\nlost\n", "2 * 3", "2 times 3");
+        edit("fine\nok\n", "ok", "2 * 3");
+    }
+}
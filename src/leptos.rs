@@ -0,0 +1,199 @@
+use crate::options::HtmlOptions;
+use crate::raw_format;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use leptos::html;
+use leptos::prelude::*;
+
+// the same elements the translator writes out, as leptos views. unlike the
+// html string, text is escaped by leptos, and footnote definitions stay
+// where they were written instead of moving to the end
+pub fn view(md: &[Markdown], options: &HtmlOptions) -> AnyView {
+    md.iter()
+        .map(|block| block_view(block, options))
+        .collect::<Vec<AnyView>>()
+        .into_any()
+}
+
+fn block_view(block: &Markdown, options: &HtmlOptions) -> AnyView {
+    match block {
+        Markdown::Heading(level, text) => {
+            let text = text_view(text, options);
+            match level {
+                HeadingLevel::H1 => html::h1().child(text).into_any(),
+                HeadingLevel::H2 => html::h2().child(text).into_any(),
+                HeadingLevel::H3 => html::h3().child(text).into_any(),
+                HeadingLevel::H4 => html::h4().child(text).into_any(),
+                HeadingLevel::H5 => html::h5().child(text).into_any(),
+                HeadingLevel::H6 => html::h6().child(text).into_any(),
+            }
+        }
+        Markdown::UnorderedList(items) => html::ul()
+            .child(
+                items
+                    .iter()
+                    .map(|item| html::li().child(item_view(item, options)))
+                    .collect::<Vec<_>>(),
+            )
+            .into_any(),
+        Markdown::OrderedList(items) => html::ol()
+            .child(
+                items
+                    .iter()
+                    .map(|(_, item)| html::li().child(item_view(item, options)))
+                    .collect::<Vec<_>>(),
+            )
+            .into_any(),
+        Markdown::Codeblock(lang, code) if raw_format(lang) == Some("html") => {
+            html::div().inner_html(code.clone()).into_any()
+        }
+        Markdown::Codeblock(lang, _) if raw_format(lang).is_some() => ().into_any(),
+        Markdown::Codeblock(lang, code) => html::pre()
+            .child(
+                html::code()
+                    .class(format!("{}{}", options.code_class_prefix, lang))
+                    .child(code.clone()),
+            )
+            .into_any(),
+        // an empty line leaves nothing behind, not even the paragraph
+        Markdown::Line(text) if text.is_empty() => ().into_any(),
+        Markdown::Line(text) => html::p().child(text_view(text, options)).into_any(),
+        Markdown::FootnoteDef(label, text) => html::div()
+            .class("footnote")
+            .id(format!("fn-{}", label))
+            .child(text_view(text, options))
+            .into_any(),
+        Markdown::Table(header, aligns, rows) => {
+            let row = |cells: &[MarkdownText], head: bool| {
+                html::tr().child(
+                    cells
+                        .iter()
+                        .zip(aligns)
+                        .map(|(cell, align)| {
+                            let style = align_style(*align);
+                            if head {
+                                html::th()
+                                    .style(style)
+                                    .child(text_view(cell, options))
+                                    .into_any()
+                            } else {
+                                html::td()
+                                    .style(style)
+                                    .child(text_view(cell, options))
+                                    .into_any()
+                            }
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            };
+            html::table()
+                .child(html::thead().child(row(header, true)))
+                .child(html::tbody().child(rows.iter().map(|r| row(r, false)).collect::<Vec<_>>()))
+                .into_any()
+        }
+        Markdown::Custom(name, text) => html::div()
+            .class(name.clone())
+            .child(text.trim_end_matches('\n').to_string())
+            .into_any(),
+        Markdown::Alert(alert, lines) => html::div()
+            .class(options.alert_classes.container(*alert))
+            .child(
+                html::p()
+                    .class(options.alert_classes.title(*alert))
+                    .child(alert.title()),
+            )
+            .child(
+                lines
+                    .iter()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| html::p().child(text_view(line, options)))
+                    .collect::<Vec<_>>(),
+            )
+            .into_any(),
+    }
+}
+
+fn item_view(item: &ListItem, options: &HtmlOptions) -> AnyView {
+    (
+        text_view(&item.text, options),
+        view(&item.children, options),
+    )
+        .into_any()
+}
+
+fn text_view(text: &[MarkdownInline], options: &HtmlOptions) -> AnyView {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Bold(text) => html::b().child(options.decoded(text)).into_any(),
+            MarkdownInline::Italic(text) => html::i().child(options.decoded(text)).into_any(),
+            MarkdownInline::Inserted(text) => html::ins().child(options.decoded(text)).into_any(),
+            MarkdownInline::InlineCode(code) => html::code().child(code.clone()).into_any(),
+            MarkdownInline::Link(text, url) => html::a()
+                .href(options.href(&options.substitute_url(url)))
+                .child(options.decoded(text))
+                .into_any(),
+            MarkdownInline::Image(text, url) => html::img()
+                .src(options.src(&options.substitute_url(url)))
+                .alt(options.decoded(&options.alt(text, url)))
+                .into_any(),
+            MarkdownInline::Plaintext(text) => options.decoded(text).into_any(),
+            MarkdownInline::FootnoteRef(label) => html::sup()
+                .class("footnote-ref")
+                .child(
+                    html::a()
+                        .href(format!("#fn-{}", label))
+                        .child(label.clone()),
+                )
+                .into_any(),
+            MarkdownInline::Citation(keys) => html::cite()
+                .class("citation")
+                .child(format!("[@{}]", keys.join("; @")))
+                .into_any(),
+        })
+        .collect::<Vec<AnyView>>()
+        .into_any()
+}
+
+fn align_style(align: Align) -> &'static str {
+    match align {
+        Align::Default => "",
+        Align::Left => "text-align: left",
+        Align::Center => "text-align: center",
+        Align::Right => "text-align: right",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+    use crate::translator::translate;
+
+    #[test]
+    fn test_view() {
+        let (_, md) = parse_markdown(
+            "# Title\n- a\n- **b**\n  1. c\n| x | y |\n|:--|--:|\n| 1 | 2 |\n```{=html}\n<b>raw</b>\n```\n",
+        )
+        .unwrap();
+        // `<!>` marks where each view starts and ends for hydration
+        let html = view(&md, &HtmlOptions::default())
+            .to_html()
+            .replace("<!>", "");
+        assert_eq!(
+            html,
+            "<h1>Title</h1><ul><li>a</li><li><b>b</b><ol><li>c</li></ol></li></ul><table><thead><tr><th style=\"text-align: left;\">x</th><th style=\"text-align: right;\">y</th></tr></thead><tbody><tr><td style=\"text-align: left;\">1</td><td style=\"text-align: right;\">2</td></tr></tbody></table><div><b>raw</b>\n</div>"
+        );
+        // the translator's elements, but for cells aligned with a style and
+        // raw html needing an element to go in
+        assert_eq!(
+            html.replace(" style=\"text-align: left;\"", " align=\"left\"")
+                .replace(" style=\"text-align: right;\"", " align=\"right\"")
+                .replace("<div><b>raw</b>\n</div>", "<b>raw</b>\n"),
+            translate(&md)
+        );
+    }
+}
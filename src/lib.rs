@@ -1,30 +1,662 @@
+// without the `std` feature only the parts that work on strings in memory
+// are built, which needs nothing more than an allocator
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod assets;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod bibliography;
+#[cfg(feature = "std")]
+pub mod crossref;
+pub mod cst;
+pub mod csv;
+#[cfg(feature = "std")]
+pub mod date;
+pub mod diff;
+#[cfg(feature = "dioxus")]
+pub mod dioxus;
+pub mod document;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod email;
+pub mod embeds;
+pub mod entities;
+pub mod filters;
+pub mod formatter;
+pub mod frontmatter;
+pub mod incremental;
+#[cfg(feature = "leptos")]
+pub mod leptos;
+#[cfg(feature = "std")]
+pub mod linkcheck;
+pub mod links;
+pub mod lint;
+pub mod mediawiki;
+#[cfg(feature = "std")]
+pub mod metrics;
+pub mod normalize;
+pub mod notebook;
+pub mod options;
 pub mod parser;
+#[cfg(feature = "python")]
+mod python;
+pub mod readability;
+#[cfg(feature = "web")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod site;
+pub mod slug;
+pub mod telegram;
+pub mod textile;
+pub mod toc;
 pub mod translator;
+#[cfg(feature = "prose-tui")]
+pub mod tui;
+pub mod typography;
+pub mod unicode;
+pub mod url;
+pub mod vars;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// what the standard prelude would give us, for building without it
+mod prelude {
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+}
+
+use alloc::borrow::Cow;
+use core::convert::TryFrom;
+use core::fmt;
+use prelude::*;
+
+pub use document::{Document, ParseError};
+pub use options::ProseOptions;
+pub use url::Url;
+
+// the inline pieces of a heading, paragraph, list item or table cell. it
+// derefs to the `Vec` underneath, so `push`, `iter` and indexing work as before
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct MarkdownText(pub Vec<MarkdownInline>);
+
+impl MarkdownText {
+    pub fn new() -> MarkdownText {
+        MarkdownText(vec![])
+    }
+
+    // the text a reader sees, with all of the markup stripped away
+    pub fn plain_text(&self) -> String {
+        self.iter()
+            .map(|part| match part {
+                MarkdownInline::Link(text, _) | MarkdownInline::Image(text, _) => text.as_str(),
+                MarkdownInline::InlineCode(text)
+                | MarkdownInline::Bold(text)
+                | MarkdownInline::Italic(text)
+                | MarkdownInline::Inserted(text)
+                | MarkdownInline::Plaintext(text) => text.as_str(),
+                MarkdownInline::FootnoteRef(_) | MarkdownInline::Citation(_) => "",
+            })
+            .collect()
+    }
+
+    // nothing but whitespace would show up. images, footnote references and
+    // citations always show something, even without any text
+    pub fn is_empty_visually(&self) -> bool {
+        self.iter().all(|part| match part {
+            MarkdownInline::Image(_, _)
+            | MarkdownInline::FootnoteRef(_)
+            | MarkdownInline::Citation(_) => false,
+            MarkdownInline::Link(text, _)
+            | MarkdownInline::InlineCode(text)
+            | MarkdownInline::Bold(text)
+            | MarkdownInline::Italic(text)
+            | MarkdownInline::Inserted(text)
+            | MarkdownInline::Plaintext(text) => text.trim().is_empty(),
+        })
+    }
+
+    // adds plain text, joining it onto the text at the end if there is some
+    pub fn push_text(&mut self, text: &str) {
+        match self.0.last_mut() {
+            Some(MarkdownInline::Plaintext(last)) => last.push_str(text),
+            _ => self.0.push(MarkdownInline::Plaintext(text.to_string())),
+        }
+    }
+}
+
+impl core::ops::Deref for MarkdownText {
+    type Target = Vec<MarkdownInline>;
+
+    fn deref(&self) -> &Vec<MarkdownInline> {
+        &self.0
+    }
+}
+
+impl core::ops::DerefMut for MarkdownText {
+    fn deref_mut(&mut self) -> &mut Vec<MarkdownInline> {
+        &mut self.0
+    }
+}
+
+impl From<Vec<MarkdownInline>> for MarkdownText {
+    fn from(parts: Vec<MarkdownInline>) -> Self {
+        MarkdownText(parts)
+    }
+}
+
+impl From<MarkdownText> for Vec<MarkdownInline> {
+    fn from(text: MarkdownText) -> Self {
+        text.0
+    }
+}
 
-pub type MarkdownText = Vec<MarkdownInline>;
+impl core::iter::FromIterator<MarkdownInline> for MarkdownText {
+    fn from_iter<I: IntoIterator<Item = MarkdownInline>>(iter: I) -> Self {
+        MarkdownText(iter.into_iter().collect())
+    }
+}
 
+impl Extend<MarkdownInline> for MarkdownText {
+    fn extend<I: IntoIterator<Item = MarkdownInline>>(&mut self, iter: I) {
+        self.0.extend(iter)
+    }
+}
+
+impl IntoIterator for MarkdownText {
+    type Item = MarkdownInline;
+    type IntoIter = vec::IntoIter<MarkdownInline>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MarkdownText {
+    type Item = &'a MarkdownInline;
+    type IntoIter = core::slice::Iter<'a, MarkdownInline>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut MarkdownText {
+    type Item = &'a mut MarkdownInline;
+    type IntoIter = core::slice::IterMut<'a, MarkdownInline>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+// with the `serde` feature (part of `std`) the tree serializes as
+// `{"type": "Heading", "value": [1, [...]]}` for other languages to pick up
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum Markdown {
-    Heading(usize, MarkdownText),
-    OrderedList(Vec<MarkdownText>),
-    UnorderedList(Vec<MarkdownText>),
+    Heading(HeadingLevel, MarkdownText),
+    // each item keeps the number it was written with
+    OrderedList(Vec<(usize, ListItem)>),
+    UnorderedList(Vec<ListItem>),
     Line(MarkdownText),
     Codeblock(String, String),
+    FootnoteDef(String, MarkdownText),
+    // the header row, how each column is aligned, then the body rows
+    Table(Vec<MarkdownText>, Vec<Align>, Vec<Vec<MarkdownText>>),
+    // a block claimed by a parser registered in `ParserOptions`: its name and
+    // the text it handed back
+    Custom(String, String),
+    // a github style `> [!NOTE]` callout and the lines quoted under it
+    Alert(Alert, Vec<MarkdownText>),
+}
+
+// the kinds of callout github knows, `> [!WARNING]`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Alert {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl Alert {
+    pub const ALL: [Alert; 5] = [
+        Alert::Note,
+        Alert::Tip,
+        Alert::Important,
+        Alert::Warning,
+        Alert::Caution,
+    ];
+
+    // `note`, as it goes into class names
+    pub fn name(self) -> &'static str {
+        match self {
+            Alert::Note => "note",
+            Alert::Tip => "tip",
+            Alert::Important => "important",
+            Alert::Warning => "warning",
+            Alert::Caution => "caution",
+        }
+    }
+
+    // `Note`, the heading readers see
+    pub fn title(self) -> &'static str {
+        match self {
+            Alert::Note => "Note",
+            Alert::Tip => "Tip",
+            Alert::Important => "Important",
+            Alert::Warning => "Warning",
+            Alert::Caution => "Caution",
+        }
+    }
+}
+
+// `#` to `######`, serialized as the number
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(into = "usize"))]
+pub enum HeadingLevel {
+    H1 = 1,
+    H2,
+    H3,
+    H4,
+    H5,
+    H6,
+}
+
+impl HeadingLevel {
+    pub const ALL: [HeadingLevel; 6] = [
+        HeadingLevel::H1,
+        HeadingLevel::H2,
+        HeadingLevel::H3,
+        HeadingLevel::H4,
+        HeadingLevel::H5,
+        HeadingLevel::H6,
+    ];
+
+    pub fn get(self) -> usize {
+        self as usize
+    }
+
+    // `delta` levels deeper, or shallower when negative, staying within h1 to h6
+    pub fn shift(self, delta: isize) -> HeadingLevel {
+        HeadingLevel::ALL[(self.get() as isize - 1 + delta).clamp(0, 5) as usize]
+    }
+}
+
+// the level isn't one of 1 to 6
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidHeadingLevel(pub usize);
+
+impl fmt::Display for InvalidHeadingLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "there is no heading level {}, only 1 to 6", self.0)
+    }
+}
+
+impl core::error::Error for InvalidHeadingLevel {}
+
+impl TryFrom<usize> for HeadingLevel {
+    type Error = InvalidHeadingLevel;
+
+    fn try_from(level: usize) -> Result<HeadingLevel, InvalidHeadingLevel> {
+        match level {
+            1..=6 => Ok(HeadingLevel::ALL[level - 1]),
+            _ => Err(InvalidHeadingLevel(level)),
+        }
+    }
 }
 
+impl From<HeadingLevel> for usize {
+    fn from(level: HeadingLevel) -> usize {
+        level.get()
+    }
+}
+
+impl fmt::Display for HeadingLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+// a list entry along with the lists nested underneath it, which can be of either kind
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ListItem {
+    pub text: MarkdownText,
+    pub children: Vec<Markdown>,
+}
+
+impl From<MarkdownText> for ListItem {
+    fn from(text: MarkdownText) -> Self {
+        ListItem {
+            text,
+            children: vec![],
+        }
+    }
+}
+
+impl From<Vec<MarkdownInline>> for ListItem {
+    fn from(text: Vec<MarkdownInline>) -> Self {
+        ListItem::from(MarkdownText(text))
+    }
+}
+
+impl Markdown {
+    // every run of inline text in the block, nested list items included
+    pub fn texts(&self) -> Vec<&MarkdownText> {
+        match self {
+            Markdown::Heading(_, text) | Markdown::Line(text) | Markdown::FootnoteDef(_, text) => {
+                vec![text]
+            }
+            Markdown::UnorderedList(items) => items.iter().flat_map(ListItem::texts).collect(),
+            Markdown::OrderedList(items) => {
+                items.iter().flat_map(|(_, item)| item.texts()).collect()
+            }
+            Markdown::Table(header, _, rows) => {
+                header.iter().chain(rows.iter().flatten()).collect()
+            }
+            Markdown::Alert(_, lines) => lines.iter().collect(),
+            Markdown::Codeblock(_, _) | Markdown::Custom(_, _) => vec![],
+        }
+    }
+
+    pub fn texts_mut(&mut self) -> Vec<&mut MarkdownText> {
+        match self {
+            Markdown::Heading(_, text) | Markdown::Line(text) | Markdown::FootnoteDef(_, text) => {
+                vec![text]
+            }
+            Markdown::UnorderedList(items) => {
+                items.iter_mut().flat_map(ListItem::texts_mut).collect()
+            }
+            Markdown::OrderedList(items) => items
+                .iter_mut()
+                .flat_map(|(_, item)| item.texts_mut())
+                .collect(),
+            Markdown::Table(header, _, rows) => {
+                header.iter_mut().chain(rows.iter_mut().flatten()).collect()
+            }
+            Markdown::Alert(_, lines) => lines.iter_mut().collect(),
+            Markdown::Codeblock(_, _) | Markdown::Custom(_, _) => vec![],
+        }
+    }
+}
+
+impl ListItem {
+    pub fn texts(&self) -> Vec<&MarkdownText> {
+        core::iter::once(&self.text)
+            .chain(self.children.iter().flat_map(Markdown::texts))
+            .collect()
+    }
+
+    pub fn texts_mut(&mut self) -> Vec<&mut MarkdownText> {
+        core::iter::once(&mut self.text)
+            .chain(self.children.iter_mut().flat_map(Markdown::texts_mut))
+            .collect()
+    }
+}
+
+// how a table column is lined up, from the colons in its `|:---:|` row
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Align {
+    Default,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", content = "value"))]
 pub enum MarkdownInline {
-    Link(String, String),
-    Image(String, String),
+    Link(String, Url),
+    Image(String, Url),
     InlineCode(String),
     Bold(String),
     Italic(String),
+    // `++inserted++`
+    Inserted(String),
     Plaintext(String),
+    FootnoteRef(String),
+    Citation(Vec<String>),
+}
+
+// a range of bytes in the markdown source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // narrows this span down to the first place `needle` shows up inside of it
+    pub fn locate(self, src: &str, needle: &str) -> Span {
+        match src[self.start..self.end].find(needle) {
+            Some(at) => Span {
+                start: self.start + at,
+                end: self.start + at + needle.len(),
+            },
+            None => self,
+        }
+    }
+}
+
+// a human friendly place in the markdown source, both counting from 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn of(src: &str, offset: usize) -> Position {
+        let before = &src[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Position {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+// the format a pandoc style ```{=html} or ```{=latex} fence is written in. what's
+// in such a block goes out as it is from the backend for that format and every
+// other backend leaves it out
+pub fn raw_format(lang: &str) -> Option<&str> {
+    let format = lang.trim().strip_prefix("{=")?.strip_suffix('}')?;
+    if format.is_empty()
+        || !format
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    Some(format)
+}
+
+// makes text safe to drop into html, attribute values included
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 pub fn markdown(md: &str) -> String {
-    match parser::parse_markdown(md) {
-        Ok((_, m)) => translator::translate(m),
-        Err(_) => String::from("Sorry, this did not seem to work! Maybe your markdown was not well formed, have you hit [Enter] after your last line?"),
+    markdown_with_options(md, &ProseOptions::default()).unwrap_or_else(|_| String::from("Sorry, this did not seem to work! Maybe your markdown was not well formed, have you hit [Enter] after your last line?"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProseError {
+    // not even the first block parsed
+    Parse(ParseError),
+}
+
+impl fmt::Display for ProseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProseError::Parse(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for ProseError {}
+
+impl From<ParseError> for ProseError {
+    fn from(e: ParseError) -> ProseError {
+        ProseError::Parse(e)
+    }
+}
+
+// `markdown` with the parser and html options of your choosing. like it,
+// this renders as much of the document as parses, front matter feeding the
+// variables, `lang` and `dir`. `Document::parse` is for refusing the rest
+pub fn markdown_with_options(md: &str, options: &ProseOptions) -> Result<String, ProseError> {
+    let (blocks, html) = parse_with_options(md, options)?;
+    Ok(translator::translate_with_options(&blocks, &html))
+}
+
+// `markdown_with_options` handing its html to `chunk` a block at a time, see
+// `translator::translate_streaming`. the document is parsed before the first one
+pub fn markdown_streaming(
+    md: &str,
+    options: &ProseOptions,
+    chunk: impl FnMut(&str),
+) -> Result<(), ProseError> {
+    let (blocks, html) = parse_with_options(md, options)?;
+    translator::translate_streaming(&blocks, &html, chunk);
+    Ok(())
+}
+
+// the blocks `markdown_with_options` renders and the html options to render
+// them with, the front matter's variables, `lang` and `dir` filled in
+pub(crate) fn parse_with_options(
+    md: &str,
+    options: &ProseOptions,
+) -> Result<(Vec<Markdown>, options::HtmlOptions), ProseError> {
+    let (front_matter, body) = frontmatter::split(md);
+    let body_offset = md.len() - body.len();
+    let html = options.html.with_front_matter(&front_matter);
+    let body = match options.parser.filters.is_empty() {
+        true => Cow::Borrowed(body),
+        false => Cow::Owned(options.parser.filters.apply(body.to_string())),
+    };
+    match parser::parse_markdown_with(&body, &options.parser) {
+        Ok((_, blocks)) => Ok((blocks, html)),
+        Err(_) => Err(ProseError::Parse(ParseError {
+            position: Position::of(md, body_offset),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_format() {
+        assert_eq!(raw_format("{=html}"), Some("html"));
+        assert_eq!(raw_format("{=latex} "), Some("latex"));
+        assert_eq!(raw_format("{=}"), None);
+        assert_eq!(raw_format("{= html}"), None);
+        assert_eq!(raw_format("html"), None);
+    }
+
+    // the html for the snapshot's markdown is checked in, so anything that changes
+    // the output shows up in review. `PROSE_UPDATE_SNAPSHOTS=1 cargo test` writes it anew
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_stable_output() {
+        let md = include_str!("../tests/snapshots/stable.md");
+        let mut options = ProseOptions::default();
+        options.html.number_headings = true;
+        options.html.permalinks = Some(options::Permalinks::default());
+        options.html.link_appendix = true;
+        options.html.alt_from_filename = true;
+        let html = markdown_with_options(md, &options).unwrap();
+        for _ in 0..3 {
+            assert_eq!(markdown_with_options(md, &options).unwrap(), html);
+        }
+        let snapshot = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/stable.html");
+        if std::env::var_os("PROSE_UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(snapshot, &html).unwrap();
+        }
+        assert_eq!(html, std::fs::read_to_string(snapshot).unwrap());
+    }
+
+    #[test]
+    fn test_markdown_text() {
+        let mut text: MarkdownText = vec![
+            MarkdownInline::Bold(String::from("Hi")),
+            MarkdownInline::FootnoteRef(String::from("1")),
+        ]
+        .into();
+        text.push_text(" there");
+        text.push_text(", you");
+        assert_eq!(text.len(), 3);
+        assert_eq!(text.plain_text(), "Hi there, you");
+        assert_eq!(
+            text.into_iter().last(),
+            Some(MarkdownInline::Plaintext(String::from(" there, you")))
+        );
+
+        let blank = vec![
+            MarkdownInline::Plaintext(String::from("  ")),
+            MarkdownInline::Bold(String::new()),
+        ]
+        .into_iter()
+        .collect::<MarkdownText>();
+        assert!(blank.is_empty_visually());
+        assert!(MarkdownText::new().is_empty_visually());
+        let image = MarkdownText::from(vec![MarkdownInline::Image(
+            String::new(),
+            Url::from("cat.png"),
+        )]);
+        assert!(!image.is_empty_visually());
+    }
+
+    #[test]
+    fn test_heading_level() {
+        assert_eq!(HeadingLevel::try_from(3), Ok(HeadingLevel::H3));
+        assert_eq!(HeadingLevel::try_from(7), Err(InvalidHeadingLevel(7)));
+        assert_eq!(HeadingLevel::try_from(0), Err(InvalidHeadingLevel(0)));
+        assert_eq!(HeadingLevel::H2.shift(1), HeadingLevel::H3);
+        assert_eq!(HeadingLevel::H2.shift(-4), HeadingLevel::H1);
+        assert_eq!(HeadingLevel::H5.shift(4), HeadingLevel::H6);
+        assert_eq!(HeadingLevel::H4.to_string(), "4");
+    }
+
+    #[test]
+    fn test_markdown_with_options() {
+        let mut options = ProseOptions::default();
+        options.parser.filter(|src| src.replace("TODO\n", ""));
+        options.html.number_headings = true;
+        assert_eq!(
+            markdown_with_options("---\nname: prose\n---\nTODO\n# {{name}}\n", &options),
+            Ok(String::from(
+                "<h1><span class=\"heading-number\">1.</span> prose</h1>"
+            ))
+        );
+        assert_eq!(
+            markdown_with_options("---\nname: prose\n---\n* a\n", &options),
+            Err(ProseError::Parse(ParseError {
+                position: Position { line: 4, column: 1 }
+            }))
+        );
+        assert!(markdown("* a\n").starts_with("Sorry"));
     }
 }
@@ -0,0 +1,199 @@
+use crate::batch::is_markdown;
+use crate::document;
+use crate::links::{self, LinkRef};
+use crate::toc;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrokenLink {
+    pub link: LinkRef,
+    pub reason: String,
+}
+
+// checks that the files the links and images in `src` point at exist. `path` is where
+// `src` was read from, urls starting with `/` are looked up underneath `root`
+pub fn check_internal(root: &Path, path: &Path, src: &str) -> Vec<BrokenLink> {
+    links::links(src)
+        .into_iter()
+        .filter(|link| !link.is_external())
+        .filter_map(|link| {
+            let target = target(root, path, &link.url.decoded())?;
+            if target.exists() {
+                None
+            } else {
+                Some(BrokenLink {
+                    reason: format!("{} does not exist", target.display()),
+                    link,
+                })
+            }
+        })
+        .collect()
+}
+
+// `#fragment` links that do not match a heading in the document they point at,
+// be it `src` itself or another markdown file. links to files that are missing
+// altogether are left to `check_internal`
+pub fn check_anchors(root: &Path, path: &Path, src: &str) -> Vec<BrokenLink> {
+    let own = slugs(src);
+    links::links(src)
+        .into_iter()
+        .filter(|link| !link.is_external())
+        .filter_map(|link| {
+            // headings are slugged from the text as written, not percent-encoded
+            let url = link.url.decoded().into_owned();
+            let (file, fragment) = url.split_once('#')?;
+            // browsers always understand `#top`
+            if fragment.is_empty() || fragment == "top" {
+                return None;
+            }
+            let found = if file.is_empty() {
+                own.iter().any(|slug| slug == fragment)
+            } else {
+                let target = target(root, path, file).filter(|target| is_markdown(target))?;
+                let slugs = slugs(&fs::read_to_string(target).ok()?);
+                slugs.iter().any(|slug| slug == fragment)
+            };
+            if found {
+                None
+            } else {
+                Some(BrokenLink {
+                    reason: format!("no heading has the anchor #{}", fragment),
+                    link,
+                })
+            }
+        })
+        .collect()
+}
+
+// the anchors a document's headings get, as far as it parses
+fn slugs(src: &str) -> Vec<String> {
+    let blocks = document::spanned_blocks(src)
+        .into_iter()
+        .map(|(block, _)| block)
+        .collect::<Vec<_>>();
+    toc::toc(&blocks)
+        .into_iter()
+        .map(|entry| entry.slug)
+        .collect()
+}
+
+// the file a local url refers to, none for a bare `#fragment` within the same document
+fn target(root: &Path, path: &Path, url: &str) -> Option<PathBuf> {
+    let file = url.split(['?', '#']).next().unwrap_or_default();
+    if file.is_empty() {
+        return None;
+    }
+    Some(match file.strip_prefix('/') {
+        Some(file) => root.join(file),
+        None => path.parent().unwrap_or(Path::new("")).join(file),
+    })
+}
+
+#[cfg(feature = "http")]
+pub use self::http::UrlChecker;
+
+#[cfg(feature = "http")]
+mod http {
+    use super::BrokenLink;
+    use crate::links;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    // asks each external http(s) url whether it is still there, remembering the
+    // answers so a url linked from many documents is only requested once
+    pub struct UrlChecker {
+        agent: ureq::Agent,
+        seen: HashMap<String, Result<(), String>>,
+    }
+
+    impl UrlChecker {
+        pub fn new(timeout: Duration) -> UrlChecker {
+            UrlChecker {
+                agent: ureq::AgentBuilder::new().timeout(timeout).build(),
+                seen: HashMap::new(),
+            }
+        }
+
+        pub fn check(&mut self, url: &str) -> Result<(), String> {
+            if let Some(result) = self.seen.get(url) {
+                return result.clone();
+            }
+            let result = match self.agent.head(url).call() {
+                // plenty of servers do not bother with HEAD
+                Err(ureq::Error::Status(405, _)) => self.agent.get(url).call().map(|_| ()),
+                result => result.map(|_| ()),
+            }
+            .map_err(|e| match e {
+                ureq::Error::Status(code, _) => format!("status {}", code),
+                e => e.to_string(),
+            });
+            self.seen.insert(url.to_string(), result.clone());
+            result
+        }
+
+        // every http(s) link in `src` that did not answer with a success
+        pub fn check_external(&mut self, src: &str) -> Vec<BrokenLink> {
+            links::links(src)
+                .into_iter()
+                .filter(|link| matches!(link.url.scheme(), Some("http") | Some("https")))
+                .filter_map(|link| match self.check(&link.url) {
+                    Ok(()) => None,
+                    Err(reason) => Some(BrokenLink { link, reason }),
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_target() {
+        let (root, path) = (Path::new("site"), Path::new("site/posts/a.md"));
+        assert_eq!(
+            target(root, path, "b.md#intro"),
+            Some(PathBuf::from("site/posts/b.md"))
+        );
+        assert_eq!(
+            target(root, path, "/img/cat.png"),
+            Some(PathBuf::from("site/img/cat.png"))
+        );
+        assert_eq!(target(root, path, "#intro"), None);
+    }
+
+    #[test]
+    fn test_check_internal() {
+        let dir = std::env::temp_dir().join(format!("prose-linkcheck-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("there.md"), "hi\n").unwrap();
+
+        let src = "[ok](there.md) [gone](gone.md) [web](https://example.com) [top](#top)\n";
+        let broken = check_internal(&dir, &dir.join("index.md"), src);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].link.url, "gone.md");
+        assert_eq!(broken[0].link.span.start, 15);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_anchors() {
+        let dir = std::env::temp_dir().join(format!("prose-anchors-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("guide.md"), "# Getting Started\n## Setup\n").unwrap();
+
+        let src = "# Intro\n[a](#intro) [b](#outro) [c](#top) [d](guide.md#setup) [e](guide.md#teardown) [f](gone.md#x)\n";
+        let broken = check_anchors(&dir, &dir.join("index.md"), src)
+            .into_iter()
+            .map(|broken| broken.link.url)
+            .collect::<Vec<_>>();
+        assert_eq!(broken, vec!["#outro", "guide.md#teardown"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
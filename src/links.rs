@@ -0,0 +1,119 @@
+use crate::document;
+use crate::prelude::*;
+use crate::MarkdownInline;
+use crate::Span;
+use crate::Url;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    Link,
+    Image,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkRef {
+    pub kind: LinkKind,
+    pub text: String,
+    pub url: Url,
+    pub span: Span,
+}
+
+impl LinkRef {
+    pub fn is_external(&self) -> bool {
+        self.url.is_external()
+    }
+}
+
+// anything with a scheme (https:, mailto:) or a protocol relative `//host` leaves the site
+pub fn is_external(url: &str) -> bool {
+    if url.starts_with("//") {
+        return true;
+    }
+    match url.find(':') {
+        Some(colon) => {
+            let scheme = &url[..colon];
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
+
+// every link and image in `src`, in the order they appear
+pub fn links(src: &str) -> Vec<LinkRef> {
+    let mut found = vec![];
+    for (block, span) in document::spanned_blocks(src) {
+        let lines = block.texts();
+        // search onwards from the last match so repeated links get their own spans
+        let mut rest = span;
+        for inline in lines.into_iter().flatten() {
+            let (kind, text, url, needle) = match inline {
+                MarkdownInline::Link(text, url) => (
+                    LinkKind::Link,
+                    text,
+                    url,
+                    format!("[{}]({})", text, url.as_written()),
+                ),
+                MarkdownInline::Image(text, url) => (
+                    LinkKind::Image,
+                    text,
+                    url,
+                    format!("![{}]({})", text, url.as_written()),
+                ),
+                _ => continue,
+            };
+            let at = rest.locate(src, &needle);
+            rest.start = at.end;
+            found.push(LinkRef {
+                kind,
+                text: text.to_string(),
+                url: url.clone(),
+                span: at,
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_is_external() {
+        assert!(is_external("https://github.com"));
+        assert!(is_external("mailto:me@example.com"));
+        assert!(is_external("//cdn.example.com/a.png"));
+        assert!(!is_external("notes/other.md"));
+        assert!(!is_external("/about"));
+        assert!(!is_external("#usage"));
+    }
+
+    #[test]
+    fn test_links() {
+        let src = "# [Home](/)\nsee [pip](https://pip.pypa.io) or [pip](https://pip.pypa.io)\n```\n[not](a-link)\n```\n- ![cat](cat.png)\n";
+        let found = links(src);
+        assert_eq!(
+            found
+                .iter()
+                .map(|l| {
+                    let position = Position::of(src, l.span.start);
+                    (l.kind, l.url.as_str(), position.line, position.column)
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                (LinkKind::Link, "/", 1, 3),
+                (LinkKind::Link, "https://pip.pypa.io", 2, 5),
+                (LinkKind::Link, "https://pip.pypa.io", 2, 35),
+                (LinkKind::Image, "cat.png", 6, 3),
+            ]
+        );
+        assert_eq!(
+            &src[found[3].span.start..found[3].span.end],
+            "![cat](cat.png)"
+        );
+    }
+}
@@ -0,0 +1,383 @@
+use crate::document;
+use crate::prelude::*;
+use crate::HeadingLevel;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::Span;
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+
+// the rules that come with prose, by the name they go by in configs and on the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Builtin {
+    MissingAltText,
+    SkippedHeadingLevel,
+    DuplicateHeading,
+    EmptyLink,
+    BareUrl,
+    TrailingWhitespace,
+    LongLine,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 7] = [
+        Builtin::MissingAltText,
+        Builtin::SkippedHeadingLevel,
+        Builtin::DuplicateHeading,
+        Builtin::EmptyLink,
+        Builtin::BareUrl,
+        Builtin::TrailingWhitespace,
+        Builtin::LongLine,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::MissingAltText => "missing-alt-text",
+            Builtin::SkippedHeadingLevel => "skipped-heading-level",
+            Builtin::DuplicateHeading => "duplicate-heading",
+            Builtin::EmptyLink => "empty-link",
+            Builtin::BareUrl => "bare-url",
+            Builtin::TrailingWhitespace => "trailing-whitespace",
+            Builtin::LongLine => "long-line",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        Builtin::ALL
+            .iter()
+            .copied()
+            .find(|rule| rule.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintConfig {
+    pub rules: Vec<Builtin>,
+    pub max_line_length: usize,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        LintConfig {
+            rules: Builtin::ALL.to_vec(),
+            max_line_length: 80,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub span: Span,
+    pub message: String,
+}
+
+// what a rule gets to look at: the source, every block in it with its span
+// (as far as the document parses) and the settings the linter runs with
+pub struct Context<'a> {
+    pub src: &'a str,
+    pub blocks: &'a [(Markdown, Span)],
+    pub config: &'a LintConfig,
+}
+
+// a check over a whole document. applications add their own to a `Linter`
+// next to the built in ones
+pub trait Rule {
+    fn name(&self) -> &'static str;
+    fn check(&self, context: &Context, findings: &mut Vec<Finding>);
+}
+
+// a set of rules to run over documents
+#[derive(Default)]
+pub struct Linter {
+    pub config: LintConfig,
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Linter {
+    // a linter with no rules at all
+    pub fn new(config: LintConfig) -> Linter {
+        Linter {
+            config,
+            rules: vec![],
+        }
+    }
+
+    // a linter running the built in rules `config` turns on
+    pub fn builtin(config: LintConfig) -> Linter {
+        let mut linter = Linter::new(config);
+        for rule in linter.config.rules.clone() {
+            linter.register(rule);
+        }
+        linter
+    }
+
+    pub fn register(&mut self, rule: impl Rule + 'static) -> &mut Linter {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    // findings come back in source order, those at the same place in the
+    // order their rules were registered
+    pub fn lint(&self, src: &str) -> Vec<Finding> {
+        let context = Context {
+            src,
+            blocks: &document::spanned_blocks(src),
+            config: &self.config,
+        };
+        let mut findings = vec![];
+        for rule in &self.rules {
+            rule.check(&context, &mut findings);
+        }
+        findings.sort_by_key(|finding| finding.span.start);
+        findings
+    }
+}
+
+// runs every enabled built in rule over `src`
+pub fn lint(src: &str, config: &LintConfig) -> Vec<Finding> {
+    Linter::builtin(config.clone()).lint(src)
+}
+
+impl Rule for Builtin {
+    fn name(&self) -> &'static str {
+        Builtin::name(self)
+    }
+
+    fn check(&self, context: &Context, findings: &mut Vec<Finding>) {
+        let mut finding = |span, message| {
+            findings.push(Finding {
+                rule: self.name(),
+                span,
+                message,
+            })
+        };
+        let src = context.src;
+        match self {
+            Builtin::TrailingWhitespace | Builtin::LongLine => {
+                let max_line_length = context.config.max_line_length;
+                let mut offset = 0;
+                for line in src.split_inclusive('\n') {
+                    let content = line.trim_end_matches(['\n', '\r']);
+                    let trimmed = content.trim_end();
+                    let length = content.chars().count();
+                    if *self == Builtin::TrailingWhitespace && trimmed.len() < content.len() {
+                        finding(
+                            Span {
+                                start: offset + trimmed.len(),
+                                end: offset + content.len(),
+                            },
+                            String::from("line ends in whitespace"),
+                        );
+                    } else if *self == Builtin::LongLine && length > max_line_length {
+                        finding(
+                            Span {
+                                start: offset,
+                                end: offset + content.len(),
+                            },
+                            format!(
+                                "line is {} characters long, the limit is {}",
+                                length, max_line_length
+                            ),
+                        );
+                    }
+                    offset += line.len();
+                }
+            }
+            Builtin::SkippedHeadingLevel => {
+                let mut previous_level: Option<HeadingLevel> = None;
+                for (block, span) in context.blocks {
+                    if let Markdown::Heading(level, _) = block {
+                        match previous_level {
+                            Some(previous) if level.get() > previous.get() + 1 => finding(
+                                *span,
+                                format!("heading jumps from level {} to {}", previous, level),
+                            ),
+                            _ => {}
+                        }
+                        previous_level = Some(*level);
+                    }
+                }
+            }
+            Builtin::DuplicateHeading => {
+                let mut seen = BTreeSet::new();
+                for (block, span) in context.blocks {
+                    if let Markdown::Heading(_, text) = block {
+                        let title = text.plain_text().trim().to_string();
+                        if !seen.insert(title.to_lowercase()) {
+                            finding(*span, format!("there is already a heading {}", title));
+                        }
+                    }
+                }
+            }
+            Builtin::MissingAltText | Builtin::EmptyLink | Builtin::BareUrl => {
+                for (block, span) in context.blocks {
+                    for inline in block.texts().into_iter().flatten() {
+                        match (self, inline) {
+                            (Builtin::MissingAltText, MarkdownInline::Image(alt, url))
+                                if alt.trim().is_empty() =>
+                            {
+                                finding(
+                                    span.locate(src, &format!("![{}]({})", alt, url.as_written())),
+                                    format!("image {} has no alt text", url),
+                                )
+                            }
+                            (Builtin::EmptyLink, MarkdownInline::Link(text, url))
+                                if text.trim().is_empty() || url.trim() == "#" =>
+                            {
+                                finding(
+                                    span.locate(src, &format!("[{}]({})", text, url.as_written())),
+                                    format!(
+                                        "link [{}]({}) goes nowhere or says nothing",
+                                        text, url
+                                    ),
+                                )
+                            }
+                            (Builtin::BareUrl, MarkdownInline::Plaintext(text)) => {
+                                for url in bare_urls(text) {
+                                    finding(
+                                        span.locate(src, url),
+                                        format!("bare url {} should be written as a link", url),
+                                    )
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn bare_urls(text: &str) -> Vec<&str> {
+    text.split_whitespace()
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', ')']))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    fn rules(src: &str, config: &LintConfig) -> Vec<(&'static str, usize, usize)> {
+        lint(src, config)
+            .iter()
+            .map(|finding| {
+                let position = Position::of(src, finding.span.start);
+                (finding.rule, position.line, position.column)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_names() {
+        for rule in Builtin::ALL.iter() {
+            assert_eq!(Builtin::from_name(rule.name()), Some(*rule));
+        }
+        assert_eq!(Builtin::from_name("nope"), None);
+    }
+
+    #[test]
+    fn test_lint_headings() {
+        assert_eq!(
+            rules(
+                "# One\n## Two\n#### Four\n## Two again\n",
+                &LintConfig::default()
+            ),
+            vec![(Builtin::SkippedHeadingLevel.name(), 3, 1)]
+        );
+    }
+
+    #[test]
+    fn test_lint_inline() {
+        assert_eq!(
+            rules(
+                "see ![ ](cat.png) and https://example.com.\n- ![dog](dog.png)\n",
+                &LintConfig::default()
+            ),
+            vec![
+                (Builtin::MissingAltText.name(), 1, 5),
+                (Builtin::BareUrl.name(), 1, 23)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_duplicates_and_empty_links() {
+        assert_eq!(
+            rules(
+                "# Setup\nsee [ ](a.md) and [top](#)\n## setup\n",
+                &LintConfig::default()
+            ),
+            vec![
+                (Builtin::EmptyLink.name(), 2, 5),
+                (Builtin::EmptyLink.name(), 2, 19),
+                (Builtin::DuplicateHeading.name(), 3, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        struct NoTodo;
+        impl Rule for NoTodo {
+            fn name(&self) -> &'static str {
+                "no-todo"
+            }
+            fn check(&self, context: &Context, findings: &mut Vec<Finding>) {
+                for (_, span) in context.blocks {
+                    let found = span.locate(context.src, "TODO");
+                    if found != *span {
+                        findings.push(Finding {
+                            rule: self.name(),
+                            span: found,
+                            message: String::from("left a TODO"),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut linter = Linter::builtin(LintConfig {
+            rules: vec![Builtin::TrailingWhitespace],
+            max_line_length: 80,
+        });
+        linter.register(NoTodo);
+        let findings = linter.lint("# Plan\nwrite it TODO \n");
+        assert_eq!(
+            findings
+                .iter()
+                .map(|finding| (finding.rule, finding.span.start))
+                .collect::<Vec<_>>(),
+            vec![("no-todo", 16), ("trailing-whitespace", 20)]
+        );
+    }
+
+    #[test]
+    fn test_lint_lines() {
+        let config = LintConfig {
+            rules: vec![Builtin::TrailingWhitespace, Builtin::LongLine],
+            max_line_length: 10,
+        };
+        assert_eq!(
+            rules("short  \nthis line is too long\n```\nok\n```\n", &config),
+            vec![
+                (Builtin::TrailingWhitespace.name(), 1, 6),
+                (Builtin::LongLine.name(), 2, 1)
+            ]
+        );
+        assert_eq!(
+            rules(
+                "short  \n",
+                &LintConfig {
+                    rules: vec![],
+                    max_line_length: 10
+                }
+            ),
+            vec![]
+        );
+    }
+}
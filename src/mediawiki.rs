@@ -0,0 +1,454 @@
+use crate::prelude::*;
+use crate::textile;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use crate::Url;
+
+// reads mediawiki markup into the same blocks the markdown parser produces,
+// for moving a wiki over. templates and markup with no markdown counterpart
+// are kept as plain text, `<ref>` notes become footnotes at the end
+pub fn parse(src: &str) -> Vec<Markdown> {
+    let lines = src.lines().collect::<Vec<&str>>();
+    let mut notes = vec![];
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            blocks.push(Markdown::Line(MarkdownText::new()));
+            i += 1;
+        } else if let Some((level, text)) = heading(trimmed) {
+            blocks.push(Markdown::Heading(level, inline(text, &mut notes)));
+            i += 1;
+        } else if let Some((tag, lang)) = code_tag(trimmed) {
+            i = code(&lines, i, tag, lang, &mut blocks);
+        } else if trimmed.starts_with("{|") {
+            let start = i;
+            while i < lines.len() && !lines[i].trim().starts_with("|}") {
+                i += 1;
+            }
+            blocks.push(table(&lines[start + 1..i.min(lines.len())], &mut notes));
+            i += 1;
+        } else if list_item(line).is_some() {
+            let mut items = vec![];
+            while let Some((markers, text)) = lines.get(i).and_then(|line| list_item(line)) {
+                items.push((markers, inline(text, &mut notes)));
+                i += 1;
+            }
+            blocks.extend(textile::list(&items));
+        } else if line.starts_with(' ') {
+            // lines indented by a space are preformatted
+            let mut code = String::new();
+            while i < lines.len() && lines[i].starts_with(' ') {
+                code.push_str(&lines[i][1..]);
+                code.push('\n');
+                i += 1;
+            }
+            blocks.push(Markdown::Codeblock(String::new(), code));
+        } else if trimmed.starts_with("----") || is_references(trimmed) {
+            i += 1;
+        } else {
+            // `;` terms and `:` indented lines read as ordinary lines
+            let text = trimmed.trim_start_matches([';', ':']).trim_start();
+            blocks.push(Markdown::Line(inline(text, &mut notes)));
+            i += 1;
+        }
+    }
+    blocks.extend(
+        notes
+            .into_iter()
+            .map(|(label, text)| Markdown::FootnoteDef(label, text)),
+    );
+    blocks
+}
+
+// `== Heading ==`, as deep as the shallower side says
+fn heading(line: &str) -> Option<(HeadingLevel, &str)> {
+    let open = line.len() - line.trim_start_matches('=').len();
+    let close = line.len() - line.trim_end_matches('=').len();
+    let level = open.min(close);
+    if level == 0 || line.len() <= 2 * level {
+        return None;
+    }
+    Some((
+        HeadingLevel::ALL[level.min(6) - 1],
+        line[level..line.len() - level].trim(),
+    ))
+}
+
+fn is_references(line: &str) -> bool {
+    line.starts_with("<references") && line.ends_with("/>")
+}
+
+// `<pre>`, `<syntaxhighlight lang="rust">` or `<source lang="rust">`
+fn code_tag(line: &str) -> Option<(&str, &str)> {
+    let tag = ["pre", "syntaxhighlight", "source"]
+        .iter()
+        .copied()
+        .find(|tag| {
+            line.strip_prefix('<')
+                .and_then(|rest| rest.strip_prefix(*tag))
+                .is_some_and(|rest| rest.starts_with(['>', ' ']))
+        })?;
+    let open = &line[..line.find('>')?];
+    let lang = open.split("lang=").nth(1).map_or("", |lang| {
+        lang.trim_matches(['"', '\''])
+            .split(['"', '\''])
+            .next()
+            .unwrap_or("")
+    });
+    Some((tag, lang))
+}
+
+fn code(lines: &[&str], start: usize, tag: &str, lang: &str, blocks: &mut Vec<Markdown>) -> usize {
+    let close = format!("</{}>", tag);
+    let first = lines[start];
+    let mut body = first[first.find('>').map_or(first.len(), |end| end + 1)..].to_string();
+    let mut i = start + 1;
+    while !body.contains(&close) && i < lines.len() {
+        body.push('\n');
+        body.push_str(lines[i]);
+        i += 1;
+    }
+    let code = body
+        .split(&close)
+        .next()
+        .unwrap_or("")
+        .trim_start_matches('\n');
+    let code = if code.ends_with('\n') {
+        code.to_string()
+    } else {
+        format!("{}\n", code)
+    };
+    blocks.push(Markdown::Codeblock(lang.to_string(), code));
+    i
+}
+
+// `*` and `#` items, nested by repeating them. `:` and `;` are not lists
+fn list_item(line: &str) -> Option<(&str, &str)> {
+    let end = line.find(|c| c != '*' && c != '#')?;
+    if end == 0 {
+        return None;
+    }
+    Some((&line[..end], line[end..].trim_start()))
+}
+
+// the rows of a `{| ... |}` table, without those two lines. the first row
+// is the header, whether it uses `!` cells or not
+fn table(lines: &[&str], notes: &mut Vec<(String, MarkdownText)>) -> Markdown {
+    let mut rows: Vec<Vec<(Align, MarkdownText)>> = vec![];
+    let mut row = vec![];
+    for line in lines {
+        let line = line.trim();
+        if line.starts_with("|-") {
+            if !row.is_empty() {
+                rows.push(core::mem::take(&mut row));
+            }
+        } else if line.starts_with("|+") {
+            // captions have nowhere to go
+        } else if let Some(cells) = line.strip_prefix('!') {
+            for cell in cells.split("!!").flat_map(|cell| cell.split("||")) {
+                row.push(table_cell(cell, notes));
+            }
+        } else if let Some(cells) = line.strip_prefix('|') {
+            for cell in cells.split("||") {
+                row.push(table_cell(cell, notes));
+            }
+        }
+    }
+    if !row.is_empty() {
+        rows.push(row);
+    }
+    let mut rows = rows.into_iter();
+    let header = rows.next().unwrap_or_default();
+    let aligns = header
+        .iter()
+        .map(|(align, _)| *align)
+        .collect::<Vec<Align>>();
+    let rows = rows
+        .map(|row| {
+            let mut row = row.into_iter().map(|(_, text)| text).collect::<Vec<_>>();
+            row.resize(aligns.len(), MarkdownText::new());
+            row
+        })
+        .collect();
+    Markdown::Table(
+        header.into_iter().map(|(_, text)| text).collect(),
+        aligns,
+        rows,
+    )
+}
+
+// `style="text-align: right" | text`, the attributes only count for alignment
+fn table_cell(cell: &str, notes: &mut Vec<(String, MarkdownText)>) -> (Align, MarkdownText) {
+    let (attributes, text) = match cell.find('|') {
+        Some(bar) if !cell[..bar].contains("[[") => (&cell[..bar], &cell[bar + 1..]),
+        _ => ("", cell),
+    };
+    let attributes = attributes.replace(' ', "");
+    let align = if attributes.contains("right") {
+        Align::Right
+    } else if attributes.contains("center") {
+        Align::Center
+    } else if attributes.contains("left") {
+        Align::Left
+    } else {
+        Align::Default
+    };
+    (align, inline(text.trim(), notes))
+}
+
+// `'''bold'''`, `''italic''`, `[[Page|links]]`, `[https://example.com links]`,
+// `[[File:cat.png|thumb|a cat]]`, `<code>`, `<nowiki>` and `<ref>` notes
+fn inline(text: &str, notes: &mut Vec<(String, MarkdownText)>) -> MarkdownText {
+    let mut parts = MarkdownText::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some((part, after)) = phrase(rest, notes) {
+            if !plain.is_empty() {
+                parts.push(MarkdownInline::Plaintext(core::mem::take(&mut plain)));
+            }
+            parts.extend(part);
+            rest = after;
+            continue;
+        }
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if !plain.is_empty() {
+        parts.push(MarkdownInline::Plaintext(plain));
+    }
+    parts
+}
+
+fn phrase<'a>(
+    rest: &'a str,
+    notes: &mut Vec<(String, MarkdownText)>,
+) -> Option<(Option<MarkdownInline>, &'a str)> {
+    for (quotes, kind) in [
+        (
+            "'''''",
+            MarkdownInline::Bold as fn(String) -> MarkdownInline,
+        ),
+        ("'''", MarkdownInline::Bold),
+        ("''", MarkdownInline::Italic),
+    ] {
+        if let Some(inner) = rest.strip_prefix(quotes) {
+            let end = inner.find(quotes)?;
+            return Some((
+                Some(kind(inner[..end].to_string())),
+                &inner[end + quotes.len()..],
+            ));
+        }
+    }
+    if let Some(inner) = rest.strip_prefix("[[") {
+        let end = inner.find("]]")?;
+        return Some((Some(wiki_link(&inner[..end])), &inner[end + 2..]));
+    }
+    if let Some(inner) = rest.strip_prefix('[') {
+        if !["http://", "https://", "//", "mailto:"]
+            .iter()
+            .any(|scheme| inner.starts_with(scheme))
+        {
+            return None;
+        }
+        let end = inner.find(']')?;
+        let (url, label) = match inner[..end].split_once(' ') {
+            Some((url, label)) => (url, label.trim()),
+            None => (&inner[..end], &inner[..end]),
+        };
+        return Some((
+            Some(MarkdownInline::Link(label.to_string(), Url::from(url))),
+            &inner[end + 1..],
+        ));
+    }
+    for (tag, kind) in [
+        (
+            "code",
+            MarkdownInline::InlineCode as fn(String) -> MarkdownInline,
+        ),
+        ("tt", MarkdownInline::InlineCode),
+        ("ins", MarkdownInline::Inserted),
+        ("nowiki", MarkdownInline::Plaintext),
+    ] {
+        if let Some(inner) = rest.strip_prefix(&format!("<{}>", tag)) {
+            let close = format!("</{}>", tag);
+            let end = inner.find(&close)?;
+            return Some((
+                Some(kind(inner[..end].to_string())),
+                &inner[end + close.len()..],
+            ));
+        }
+    }
+    if rest.starts_with("<ref") && rest[4..].starts_with([' ', '>', '/']) {
+        return reference(rest, notes);
+    }
+    None
+}
+
+// `[[File:cat.png|thumb|a cat]]` is an image, anything else links to a page
+fn wiki_link(inner: &str) -> MarkdownInline {
+    let mut params = inner.split('|');
+    let target = params.next().unwrap_or("").trim();
+    let params = params.map(str::trim).collect::<Vec<&str>>();
+    let file = ["File:", "Image:", "file:", "image:"]
+        .iter()
+        .find_map(|prefix| target.strip_prefix(prefix));
+    match file {
+        Some(file) => {
+            let alt = params
+                .iter()
+                .rev()
+                .find(|param| !is_image_option(param))
+                .copied()
+                .unwrap_or("");
+            MarkdownInline::Image(alt.to_string(), Url::from(file.trim().replace(' ', "_")))
+        }
+        None => {
+            let label = params.last().copied().unwrap_or(target);
+            MarkdownInline::Link(label.to_string(), Url::from(target.replace(' ', "_")))
+        }
+    }
+}
+
+fn is_image_option(param: &str) -> bool {
+    matches!(
+        param,
+        "thumb"
+            | "thumbnail"
+            | "frame"
+            | "frameless"
+            | "border"
+            | "left"
+            | "right"
+            | "center"
+            | "none"
+            | "upright"
+    ) || param.ends_with("px")
+        || param.contains('=')
+}
+
+// `<ref>text</ref>` is numbered in order, `<ref name="a">` goes by its name
+// and a later `<ref name="a" />` points back at it
+fn reference<'a>(
+    rest: &'a str,
+    notes: &mut Vec<(String, MarkdownText)>,
+) -> Option<(Option<MarkdownInline>, &'a str)> {
+    let open_end = rest.find('>')?;
+    let open = &rest[..open_end];
+    let name = open.split("name=").nth(1).map(|name| {
+        name.trim_end_matches('/')
+            .trim()
+            .trim_matches(['"', '\''])
+            .to_string()
+    });
+    if open.ends_with('/') {
+        return Some((name.map(MarkdownInline::FootnoteRef), &rest[open_end + 1..]));
+    }
+    let inner = &rest[open_end + 1..];
+    let end = inner.find("</ref>")?;
+    let label = name.unwrap_or_else(|| (notes.len() + 1).to_string());
+    let text = inline(&inner[..end], notes);
+    if !notes.iter().any(|(existing, _)| *existing == label) {
+        notes.push((label.clone(), text));
+    }
+    Some((
+        Some(MarkdownInline::FootnoteRef(label)),
+        &inner[end + "</ref>".len()..],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ListItem;
+
+    fn text(s: &str) -> MarkdownInline {
+        MarkdownInline::Plaintext(String::from(s))
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            parse("== Intro ==\nsome text\n\n* one\n*# two\n pre formatted\n----\n<syntaxhighlight lang=\"rust\">\nlet x;\n</syntaxhighlight>\n"),
+            vec![
+                Markdown::Heading(HeadingLevel::H2, vec![text("Intro")].into()),
+                Markdown::Line(vec![text("some text")].into()),
+                Markdown::Line(vec![].into()),
+                Markdown::UnorderedList(vec![ListItem {
+                    text: vec![text("one")].into(),
+                    children: vec![Markdown::OrderedList(vec![(
+                        1,
+                        ListItem::from(vec![text("two")])
+                    )])]
+                }]),
+                Markdown::Codeblock(String::new(), String::from("pre formatted\n")),
+                Markdown::Codeblock(String::from("rust"), String::from("let x;\n")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            parse("'''bold''' ''it'' [[Main Page|home]] [[Help]] [https://x.com site] [[File:Cat.png|thumb|200px|a cat]] <code>x</code>"),
+            vec![Markdown::Line(vec![
+                MarkdownInline::Bold(String::from("bold")),
+                text(" "),
+                MarkdownInline::Italic(String::from("it")),
+                text(" "),
+                MarkdownInline::Link(String::from("home"), Url::from("Main_Page")),
+                text(" "),
+                MarkdownInline::Link(String::from("Help"), Url::from("Help")),
+                text(" "),
+                MarkdownInline::Link(String::from("site"), Url::from("https://x.com")),
+                text(" "),
+                MarkdownInline::Image(String::from("a cat"), Url::from("Cat.png")),
+                text(" "),
+                MarkdownInline::InlineCode(String::from("x")),
+            ].into())]
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        assert_eq!(
+            parse("a<ref>first</ref> b<ref name=\"src\">''named''</ref> c<ref name=\"src\" />\n<references />\n"),
+            vec![
+                Markdown::Line(vec![
+                    text("a"),
+                    MarkdownInline::FootnoteRef(String::from("1")),
+                    text(" b"),
+                    MarkdownInline::FootnoteRef(String::from("src")),
+                    text(" c"),
+                    MarkdownInline::FootnoteRef(String::from("src")),
+                ].into()),
+                Markdown::FootnoteDef(String::from("1"), vec![text("first")].into()),
+                Markdown::FootnoteDef(
+                    String::from("src"),
+                    vec![MarkdownInline::Italic(String::from("named"))].into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            parse("{| class=\"wikitable\"\n|+ caption\n! name !! style=\"text-align:right\" | count\n|-\n| a || 1\n|-\n| b\n| 2\n|}\n"),
+            vec![Markdown::Table(
+                vec![vec![text("name")].into(), vec![text("count")].into()],
+                vec![Align::Default, Align::Right],
+                vec![
+                    vec![vec![text("a")].into(), vec![text("1")].into()],
+                    vec![vec![text("b")].into(), vec![text("2")].into()],
+                ]
+            )]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use crate::translator;
+use crate::Markdown;
+use crate::ProseError;
+use crate::ProseOptions;
+use std::time::{Duration, Instant};
+
+// how long a document took to parse and translate and how much there was of it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Metrics {
+    // of markdown, front matter included
+    pub bytes: usize,
+    pub blocks: usize,
+    // text runs, links, code and the like in the blocks' text
+    pub inlines: usize,
+    pub parse: Duration,
+    pub translate: Duration,
+}
+
+impl Metrics {
+    pub fn total(&self) -> Duration {
+        self.parse + self.translate
+    }
+
+    // markdown through both phases, none too quick to measure counts as 0
+    pub fn bytes_per_second(&self) -> f64 {
+        match self.total().as_secs_f64() {
+            secs if secs > 0.0 => self.bytes as f64 / secs,
+            _ => 0.0,
+        }
+    }
+}
+
+// `markdown_with_options` that also says where the time went. the same
+// phases show up as `parse` and `translate` tracing spans for subscribers
+pub fn markdown_with_metrics(
+    md: &str,
+    options: &ProseOptions,
+) -> Result<(String, Metrics), ProseError> {
+    let start = Instant::now();
+    let (blocks, html) = crate::parse_with_options(md, options)?;
+    let parsed = Instant::now();
+    let out = translator::translate_with_options(&blocks, &html);
+    let metrics = Metrics {
+        bytes: md.len(),
+        blocks: blocks.len(),
+        inlines: inlines(&blocks),
+        parse: parsed - start,
+        translate: parsed.elapsed(),
+    };
+    Ok((out, metrics))
+}
+
+fn inlines(md: &[Markdown]) -> usize {
+    md.iter()
+        .map(|block| {
+            block
+                .texts()
+                .into_iter()
+                .map(|text| text.len())
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown_with_options;
+
+    #[test]
+    fn test_markdown_with_metrics() {
+        let md = "---\ntitle: x\n---\n# Hi *there*\n- [one](a.md)\n- two\n";
+        let options = ProseOptions::default();
+        let (html, metrics) = markdown_with_metrics(md, &options).unwrap();
+        assert_eq!(Ok(html), markdown_with_options(md, &options));
+        assert_eq!(metrics.bytes, md.len());
+        assert_eq!(metrics.blocks, 2);
+        assert_eq!(metrics.inlines, 4);
+        assert_eq!(metrics.total(), metrics.parse + metrics.translate);
+        assert!(markdown_with_metrics("2 * 3\n", &options).is_err());
+        assert_eq!(Metrics::default().bytes_per_second(), 0.0);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::formatter::format_text;
+use crate::prelude::*;
+use crate::HeadingLevel;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+
+// one canonical shape for a document, so two that only differ in how they
+// were spelled come out the same: `===` and `---` underlined headings become
+// headings, `~~~` and indented code become code blocks, text runs are merged
+// and trimmed, ordered lists count from 1, table rows are as wide as their
+// header and there is never more than one blank line in a row
+pub fn normalize(blocks: Vec<Markdown>) -> Vec<Markdown> {
+    let mut out: Vec<Markdown> = Vec::with_capacity(blocks.len());
+    let mut blocks = blocks.into_iter().peekable();
+    while let Some(mut block) = blocks.next() {
+        normalize_block(&mut block);
+        if let Markdown::Line(text) = &block {
+            let line = format_text(text);
+            if let Some(level) = blocks.peek().and_then(underline) {
+                if !text.is_empty() {
+                    blocks.next();
+                    out.push(Markdown::Heading(level, text.clone()));
+                    continue;
+                }
+            }
+            if let Some(lang) = line.strip_prefix("~~~") {
+                out.push(tilde_fence(lang.trim(), &mut blocks));
+                continue;
+            }
+            if indented(&line) {
+                let mut code = format!("{}\n", unindent(&line));
+                while let Some(Markdown::Line(next)) = blocks.peek() {
+                    let next = format_text(next);
+                    if !indented(&next) {
+                        break;
+                    }
+                    code.push_str(unindent(&next));
+                    code.push('\n');
+                    blocks.next();
+                }
+                out.push(Markdown::Codeblock(String::from("__UNKNOWN__"), code));
+                continue;
+            }
+        }
+        let blank = is_blank(&block);
+        match out.last() {
+            // the parser reads the newline after a closing fence as an empty line
+            Some(Markdown::Codeblock(_, _)) | None if blank => continue,
+            Some(last) if blank && is_blank(last) => continue,
+            _ => out.push(block),
+        }
+    }
+    while out.last().is_some_and(is_blank) {
+        out.pop();
+    }
+    out
+}
+
+// the parts of a block that can be tidied up without looking at its neighbours
+pub fn normalize_block(block: &mut Markdown) {
+    match block {
+        Markdown::OrderedList(items) => {
+            for (i, (number, item)) in items.iter_mut().enumerate() {
+                *number = i + 1;
+                normalize_item(item);
+            }
+        }
+        Markdown::UnorderedList(items) => items.iter_mut().for_each(normalize_item),
+        Markdown::Table(header, _, rows) => {
+            for row in rows.iter_mut() {
+                row.resize(header.len(), MarkdownText::new());
+            }
+        }
+        _ => {}
+    }
+    for text in block.texts_mut() {
+        normalize_text(text);
+    }
+}
+
+fn normalize_item(item: &mut ListItem) {
+    item.children.iter_mut().for_each(normalize_block);
+}
+
+// neighbouring plain text is one run, and text never ends in whitespace
+fn normalize_text(text: &mut MarkdownText) {
+    let mut merged = MarkdownText(Vec::with_capacity(text.len()));
+    for part in text.drain(..) {
+        match part {
+            MarkdownInline::Plaintext(plain) if plain.is_empty() => {}
+            MarkdownInline::Plaintext(plain) => merged.push_text(&plain),
+            part => merged.push(part),
+        }
+    }
+    if let Some(MarkdownInline::Plaintext(last)) = merged.last_mut() {
+        last.truncate(last.trim_end().len());
+        if last.is_empty() {
+            merged.pop();
+        }
+    }
+    *text = merged;
+}
+
+// the heading level a `===` or `---` line under some text gives it
+fn underline(block: &Markdown) -> Option<HeadingLevel> {
+    let line = match block {
+        Markdown::Line(text) => format_text(text),
+        _ => return None,
+    };
+    let line = line.trim();
+    if !line.is_empty() && line.chars().all(|c| c == '=') {
+        Some(HeadingLevel::H1)
+    } else if line.len() >= 2 && line.chars().all(|c| c == '-') {
+        Some(HeadingLevel::H2)
+    } else {
+        None
+    }
+}
+
+// the lines of a `~~~` block were read as text, each blank one as an empty line
+fn tilde_fence(lang: &str, blocks: &mut impl Iterator<Item = Markdown>) -> Markdown {
+    let mut code = String::new();
+    for block in blocks {
+        let line = match &block {
+            Markdown::Line(text) => format_text(text),
+            block => crate::formatter::format_block(block).trim_end().to_string(),
+        };
+        if line.trim() == "~~~" {
+            break;
+        }
+        code.push_str(&line);
+        code.push('\n');
+    }
+    let lang = if lang.is_empty() { "__UNKNOWN__" } else { lang };
+    Markdown::Codeblock(lang.to_string(), code)
+}
+
+fn indented(line: &str) -> bool {
+    line.starts_with("    ") || line.starts_with('\t')
+}
+
+fn unindent(line: &str) -> &str {
+    line.strip_prefix("    ")
+        .or_else(|| line.strip_prefix('\t'))
+        .unwrap_or(line)
+}
+
+fn is_blank(block: &Markdown) -> bool {
+    matches!(block, Markdown::Line(text) if text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    fn normalized(md: &str) -> Vec<Markdown> {
+        normalize(parse_markdown(md).unwrap().1)
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalized("\n\nTitle\n=====\n\n\n\nSub\n---\n3. one\n7. two\n"),
+            normalized("# Title\n\n## Sub\n1. one\n2. two\n")
+        );
+        assert_eq!(
+            normalized("~~~rust\nlet x;\n\nlet y;\n~~~\n    indented\n    code\n"),
+            vec![
+                Markdown::Codeblock(String::from("rust"), String::from("let x;\n\nlet y;\n")),
+                Markdown::Codeblock(
+                    String::from("__UNKNOWN__"),
+                    String::from("indented\ncode\n")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_block() {
+        let mut block = Markdown::Line(
+            vec![
+                MarkdownInline::Plaintext(String::from("a ")),
+                MarkdownInline::Plaintext(String::from("b  ")),
+            ]
+            .into(),
+        );
+        normalize_block(&mut block);
+        assert_eq!(
+            block,
+            Markdown::Line(vec![MarkdownInline::Plaintext(String::from("a b"))].into())
+        );
+    }
+}
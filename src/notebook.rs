@@ -0,0 +1,194 @@
+use crate::escape;
+use crate::parser;
+use crate::prelude::*;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use crate::Url;
+use core::fmt;
+use serde_json::Value;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotebookError {
+    pub message: String,
+}
+
+impl fmt::Display for NotebookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid notebook: {}", self.message)
+    }
+}
+
+impl core::error::Error for NotebookError {}
+
+fn invalid(message: &str) -> NotebookError {
+    NotebookError {
+        message: message.to_string(),
+    }
+}
+
+// the cells of a jupyter notebook as blocks: markdown cells are parsed, code
+// cells become code blocks in the kernel's language. with `outputs` what the
+// cells printed follows them, images included as data urls
+pub fn parse(src: &str, outputs: bool) -> Result<Vec<Markdown>, NotebookError> {
+    let notebook: Value = serde_json::from_str(src).map_err(|e| invalid(&e.to_string()))?;
+    let cells = notebook["cells"]
+        .as_array()
+        .ok_or_else(|| invalid("there is no list of cells"))?;
+    let metadata = &notebook["metadata"];
+    let lang = metadata["kernelspec"]["language"]
+        .as_str()
+        .or_else(|| metadata["language_info"]["name"].as_str())
+        .unwrap_or("");
+
+    let mut blocks = vec![];
+    for cell in cells {
+        let source = text(&cell["source"]);
+        match cell["cell_type"].as_str() {
+            Some("markdown") => {
+                let md = format!("{}\n", source.trim_end());
+                // like `markdown`, whatever parsed before a problem is kept
+                if let Ok((_, cell)) = parser::parse_markdown(&md) {
+                    blocks.extend(cell);
+                }
+            }
+            Some("code") => {
+                if !source.trim().is_empty() {
+                    blocks.push(code(lang, &source));
+                }
+                if outputs {
+                    if let Some(cell_outputs) = cell["outputs"].as_array() {
+                        blocks.extend(cell_outputs.iter().filter_map(output));
+                    }
+                }
+            }
+            // raw cells are meant for other converters
+            _ => continue,
+        }
+        // keeps the last paragraph of one cell apart from the next
+        blocks.push(Markdown::Line(MarkdownText::new()));
+    }
+    Ok(blocks)
+}
+
+// notebooks store text either whole or as a list of lines
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+// cells hold program text rather than markdown, so it is escaped
+fn code(lang: &str, source: &str) -> Markdown {
+    Markdown::Codeblock(
+        lang.to_string(),
+        format!("{}\n", escape(source.trim_end_matches('\n'))),
+    )
+}
+
+fn output(output: &Value) -> Option<Markdown> {
+    match output["output_type"].as_str()? {
+        "stream" => Some(code("output", &text(&output["text"]))),
+        "execute_result" | "display_data" => {
+            let data = &output["data"];
+            for mime in ["image/png", "image/jpeg", "image/gif"] {
+                if data[mime].is_string() || data[mime].is_array() {
+                    let base64 = text(&data[mime]).replace('\n', "");
+                    return Some(Markdown::Line(
+                        vec![MarkdownInline::Image(
+                            String::from("output"),
+                            Url::from(format!("data:{};base64,{}", mime, base64)),
+                        )]
+                        .into(),
+                    ));
+                }
+            }
+            let plain = text(&data["text/plain"]);
+            if plain.is_empty() {
+                None
+            } else {
+                Some(code("output", &plain))
+            }
+        }
+        "error" => Some(code(
+            "error",
+            &format!(
+                "{}: {}",
+                output["ename"].as_str().unwrap_or(""),
+                output["evalue"].as_str().unwrap_or("")
+            ),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeadingLevel;
+
+    const NOTEBOOK: &str = r##"{
+        "metadata": {"kernelspec": {"language": "python"}},
+        "cells": [
+            {"cell_type": "markdown", "source": ["# Title\n", "Some *text*"]},
+            {"cell_type": "code", "source": "print(1 < 2)", "outputs": [
+                {"output_type": "stream", "name": "stdout", "text": ["True\n"]},
+                {"output_type": "display_data", "data": {"image/png": "iVBO\nRw0=", "text/plain": "<Figure>"}}
+            ]},
+            {"cell_type": "raw", "source": "skipped"}
+        ]
+    }"##;
+
+    #[test]
+    fn test_parse() {
+        let blocks = parse(NOTEBOOK, false).unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                Markdown::Heading(
+                    HeadingLevel::H1,
+                    vec![MarkdownInline::Plaintext(String::from("Title"))].into()
+                ),
+                Markdown::Line(
+                    vec![
+                        MarkdownInline::Plaintext(String::from("Some ")),
+                        MarkdownInline::Italic(String::from("text"))
+                    ]
+                    .into()
+                ),
+                Markdown::Line(vec![].into()),
+                Markdown::Codeblock(String::from("python"), String::from("print(1 &lt; 2)\n")),
+                Markdown::Line(vec![].into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_outputs() {
+        let blocks = parse(NOTEBOOK, true).unwrap();
+        assert_eq!(
+            blocks[4..6],
+            [
+                Markdown::Codeblock(String::from("output"), String::from("True\n")),
+                Markdown::Line(
+                    vec![MarkdownInline::Image(
+                        String::from("output"),
+                        Url::from("data:image/png;base64,iVBORw0=")
+                    )]
+                    .into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            parse("{}", false).unwrap_err().to_string(),
+            "invalid notebook: there is no list of cells"
+        );
+        assert!(parse("not json", false).is_err());
+    }
+}
@@ -0,0 +1,650 @@
+use crate::bibliography::Bibliography;
+use crate::entities;
+use crate::frontmatter::FrontMatter;
+use crate::links::is_external;
+use crate::parser::ParserOptions;
+use crate::prelude::*;
+use crate::typography;
+use crate::vars::{self, Variables};
+use crate::Alert;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::Url;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::fmt;
+
+// the width and height of images by the url they are written with
+pub type ImageSizes = BTreeMap<String, (u32, u32)>;
+
+// knobs for the html the translator produces, `Default` matches plain `markdown()`
+#[derive(Clone, Debug, PartialEq)]
+pub struct HtmlOptions {
+    // prepended to a fenced block's language to make its css class, `lang-rust`
+    pub code_class_prefix: String,
+    // relative link and image urls are resolved against this, `https://example.com/docs/`.
+    // urls starting with `/` are resolved against its host
+    pub base_url: Option<String>,
+    // point links at `notes.md` to the `notes.html` a build writes
+    pub md_links_to_html: bool,
+    // values for `{{name}}` placeholders in the text, links and images
+    pub variables: Variables,
+    // put `1.`, `1.1`, `1.1.1` numbers in front of headings
+    pub number_headings: bool,
+    // keep the numbers ordered list items were written with instead of counting from 1
+    pub list_values: bool,
+    // render ```csv and ```tsv blocks as tables instead of code
+    pub csv_tables: bool,
+    // the works `[@key]` citations refer to
+    pub bibliography: Bibliography,
+    // the language of the text, `ar`, put on a `<div>` around everything along with `dir`
+    pub lang: Option<String>,
+    // which way the text runs, `rtl`, `ltr` or `auto`
+    pub dir: Option<String>,
+    // warn about images without alt text and label footnotes and citations for
+    // screen readers, pages written by `batch` also get a skip to content link
+    pub accessibility: bool,
+    // turn `(c)`, `->`, `1/2` and friends in the text into `©`, `→` and `½`
+    pub typographer: bool,
+    // number links like `text [1]` and list where they go under a "Links"
+    // heading at the end, for printing
+    pub link_appendix: bool,
+    // lines of text that follow one another are one paragraph with `<br />`s
+    // between them, like in chats and comments, instead of a paragraph each
+    pub hard_breaks: bool,
+    // a line with nothing on it but an image is written as the `<img>` alone
+    // rather than a `<p>` holding it
+    pub standalone_images: bool,
+    // a profile for paper: links are followed by where they go, `text (https://…)`,
+    // headings lose their permalinks and `page-break` and `avoid-break` classes
+    // are put where a print stylesheet wants to break pages or not
+    pub print: bool,
+    // images without alt text get one made from their file name, `cat-on-a-mat.png`
+    // is "Cat on a mat", and a warning lists them
+    pub alt_from_filename: bool,
+    // the classes `> [!NOTE]` alerts are written with
+    pub alert_classes: AlertClasses,
+    // put on the `<img>` of the images it has a size for, so the page doesn't
+    // jump about as they load. site builds fill it in from what their image
+    // hook hands back
+    pub image_sizes: ImageSizes,
+    // give headings ids and a link to themselves, `<a class="anchor" href="#intro">¶</a>`,
+    // for readers to copy
+    pub permalinks: Option<Permalinks>,
+    // callers' own html for the blocks and inlines they care about
+    pub overrides: Overrides,
+    // run over the html of the whole document once it's written, in order
+    pub filters: Filters,
+}
+
+// the classes an alert's `<div>` and the `<p>` with its title get, so css
+// written for github or mkdocs styles them as it is. `{}` is the alert's
+// name, `note` or `warning`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlertClasses {
+    pub container: String,
+    pub title: String,
+}
+
+impl AlertClasses {
+    pub const NAMES: [&'static str; 2] = ["github", "mkdocs"];
+
+    // `markdown-alert markdown-alert-note`, as github writes them
+    pub fn github() -> AlertClasses {
+        AlertClasses {
+            container: String::from("markdown-alert markdown-alert-{}"),
+            title: String::from("markdown-alert-title"),
+        }
+    }
+
+    // `admonition note`, for mkdocs and its material theme
+    pub fn mkdocs() -> AlertClasses {
+        AlertClasses {
+            container: String::from("admonition {}"),
+            title: String::from("admonition-title"),
+        }
+    }
+
+    // one of `NAMES`, for config files
+    pub fn named(name: &str) -> Option<AlertClasses> {
+        match name {
+            "github" => Some(AlertClasses::github()),
+            "mkdocs" => Some(AlertClasses::mkdocs()),
+            _ => None,
+        }
+    }
+
+    pub fn container(&self, alert: Alert) -> String {
+        self.container.replace("{}", alert.name())
+    }
+
+    pub fn title(&self, alert: Alert) -> String {
+        self.title.replace("{}", alert.name())
+    }
+}
+
+impl Default for AlertClasses {
+    fn default() -> Self {
+        AlertClasses::github()
+    }
+}
+
+// how heading permalinks look, the symbol is written as it is so it can be html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permalinks {
+    pub symbol: String,
+    pub position: PermalinkPosition,
+}
+
+impl Default for Permalinks {
+    fn default() -> Self {
+        Permalinks {
+            symbol: String::from("¶"),
+            position: PermalinkPosition::After,
+        }
+    }
+}
+
+// which side of the heading's text the permalink goes, docs.rs puts it
+// before and mkdocs after
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PermalinkPosition {
+    Before,
+    After,
+}
+
+impl PermalinkPosition {
+    pub const NAMES: [&'static str; 2] = ["before", "after"];
+
+    pub fn named(name: &str) -> Option<PermalinkPosition> {
+        match name {
+            "before" => Some(PermalinkPosition::Before),
+            "after" => Some(PermalinkPosition::After),
+            _ => None,
+        }
+    }
+}
+
+pub type Filter = Arc<dyn Fn(String) -> String + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct Filters(Vec<Filter>);
+
+impl Filters {
+    pub fn push(&mut self, filter: impl Fn(String) -> String + Send + Sync + 'static) {
+        self.0.push(Arc::new(filter));
+    }
+
+    pub fn apply(&self, text: String) -> String {
+        self.0.iter().fold(text, |text, filter| filter(text))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Filters({})", self.0.len())
+    }
+}
+
+impl PartialEq for Filters {
+    fn eq(&self, other: &Filters) -> bool {
+        same(&self.0, &other.0)
+    }
+}
+
+// everything that goes into turning markdown into html, for `markdown_with_options`
+#[derive(Clone, Debug, Default)]
+pub struct ProseOptions {
+    pub parser: ParserOptions,
+    pub html: HtmlOptions,
+}
+
+pub type BlockRenderer = Arc<dyn Fn(&Markdown, &HtmlOptions) -> Option<String> + Send + Sync>;
+pub type InlineRenderer =
+    Arc<dyn Fn(&MarkdownInline, &HtmlOptions) -> Option<String> + Send + Sync>;
+
+// renderers that get the first go at each node. the first one to hand back
+// some html wins, and a node none of them want is rendered as usual
+#[derive(Clone, Default)]
+pub struct Overrides {
+    blocks: Vec<BlockRenderer>,
+    inlines: Vec<InlineRenderer>,
+}
+
+impl Overrides {
+    pub fn block(&self, block: &Markdown, options: &HtmlOptions) -> Option<String> {
+        self.blocks.iter().find_map(|render| render(block, options))
+    }
+
+    pub fn inline(&self, inline: &MarkdownInline, options: &HtmlOptions) -> Option<String> {
+        self.inlines
+            .iter()
+            .find_map(|render| render(inline, options))
+    }
+}
+
+impl fmt::Debug for Overrides {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Overrides")
+            .field("blocks", &self.blocks.len())
+            .field("inlines", &self.inlines.len())
+            .finish()
+    }
+}
+
+impl PartialEq for Overrides {
+    fn eq(&self, other: &Overrides) -> bool {
+        same(&self.blocks, &other.blocks) && same(&self.inlines, &other.inlines)
+    }
+}
+
+// `cat_on-a-mat.png` reads "Cat on a mat"
+fn humanize(file_name: &str) -> String {
+    let stem = match file_name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => file_name,
+    };
+    let words = stem
+        .split(|c: char| c == '-' || c == '_' || c == '.' || c == '+' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+// closures can't be compared, the same ones shared between two sets of options can
+fn same<T: ?Sized>(a: &[Arc<T>], b: &[Arc<T>]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| Arc::ptr_eq(a, b))
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        HtmlOptions {
+            code_class_prefix: String::from("lang-"),
+            base_url: None,
+            md_links_to_html: false,
+            variables: Variables::new(),
+            number_headings: false,
+            list_values: false,
+            csv_tables: false,
+            bibliography: Bibliography::default(),
+            lang: None,
+            dir: None,
+            accessibility: false,
+            typographer: false,
+            link_appendix: false,
+            alt_from_filename: false,
+            hard_breaks: false,
+            standalone_images: false,
+            print: false,
+            alert_classes: AlertClasses::default(),
+            permalinks: None,
+            image_sizes: ImageSizes::new(),
+            overrides: Overrides::default(),
+            filters: Filters::default(),
+        }
+    }
+}
+
+impl HtmlOptions {
+    pub fn builder() -> HtmlOptionsBuilder {
+        HtmlOptionsBuilder::default()
+    }
+
+    // html of your own for blocks: `render` is handed every block and returns
+    // none for the ones it leaves to the translator
+    pub fn override_block(
+        &mut self,
+        render: impl Fn(&Markdown, &HtmlOptions) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut HtmlOptions {
+        self.overrides.blocks.push(Arc::new(render));
+        self
+    }
+
+    // adds a step to the end of the filters, `filters::external_links` or a
+    // closure of your own that rewrites the finished html
+    pub fn filter(
+        &mut self,
+        filter: impl Fn(String) -> String + Send + Sync + 'static,
+    ) -> &mut HtmlOptions {
+        self.filters.push(filter);
+        self
+    }
+
+    // an `override_block` for ```math code blocks, the only math the parser
+    // knows: `render` is handed the TeX inside the fence and returns its html,
+    // with an engine of your choosing, `katex::render` say, so pages don't need
+    // any javascript for their math. there are no math nodes and no `$…$` inside
+    // a line. fences the engine gives up on are left as code, with a warning
+    pub fn override_math_fences(
+        &mut self,
+        render: impl Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    ) -> &mut HtmlOptions {
+        self.override_block(move |block, _| match block {
+            Markdown::Codeblock(lang, tex) if lang == "math" => match render(tex) {
+                Ok(html) => Some(html),
+                Err(e) => {
+                    tracing::warn!("math that could not be rendered is left as code: {}", e);
+                    None
+                }
+            },
+            _ => None,
+        })
+    }
+
+    // like `override_block` for links, images, code and the rest of the text
+    pub fn override_inline(
+        &mut self,
+        render: impl Fn(&MarkdownInline, &HtmlOptions) -> Option<String> + Send + Sync + 'static,
+    ) -> &mut HtmlOptions {
+        self.overrides.inlines.push(Arc::new(render));
+        self
+    }
+
+    // the same options with a document's front matter added to the variables,
+    // its `lang` and `dir` fields win over the ones given
+    pub fn with_front_matter(&self, front_matter: &FrontMatter) -> HtmlOptions {
+        let field = |key| front_matter.get_str(key).map(String::from);
+        HtmlOptions {
+            variables: vars::with_front_matter(&self.variables, front_matter),
+            lang: field("lang").or_else(|| self.lang.clone()),
+            dir: field("dir").or_else(|| self.dir.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub fn substitute<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        vars::substitute(text, &self.variables)
+    }
+
+    // placeholders in a link are filled in as it was written, then it is
+    // encoded again so `{{ page }}` doesn't turn into `{{%20page%20}}`
+    pub fn substitute_url(&self, url: &Url) -> Url {
+        match self.substitute(url.as_written()) {
+            Cow::Borrowed(_) => url.clone(),
+            Cow::Owned(written) => Url::from(written),
+        }
+    }
+
+    // `typeset` for renderers that build text nodes rather than write html,
+    // which would show `&amp;` just as it is written
+    pub fn decoded(&self, text: &str) -> String {
+        entities::decode(&self.typeset(text)).into_owned()
+    }
+
+    // text as it is shown, placeholders filled in and typeset if asked for
+    pub fn typeset<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = self.substitute(text);
+        if !self.typographer {
+            return text;
+        }
+        match typography::typeset(&text) {
+            Cow::Borrowed(_) => text,
+            Cow::Owned(typeset) => Cow::Owned(typeset),
+        }
+    }
+
+    // an image's alt text, made up from the file name it points at when the
+    // author left it empty and `alt_from_filename` is on
+    pub fn alt<'a>(&self, text: &'a str, url: &Url) -> Cow<'a, str> {
+        if !self.alt_from_filename || !text.trim().is_empty() {
+            return Cow::Borrowed(text);
+        }
+        match url.file_name() {
+            Some(name) => Cow::Owned(humanize(&name)),
+            None => Cow::Borrowed(text),
+        }
+    }
+
+    // where a link should really go
+    pub fn href(&self, url: &str) -> String {
+        if !self.md_links_to_html || is_external(url) {
+            return self.resolve(url);
+        }
+        let end = url.find(['?', '#']).unwrap_or(url.len());
+        match url[..end].strip_suffix(".md") {
+            Some(path) => self.resolve(&format!("{}.html{}", path, &url[end..])),
+            None => self.resolve(url),
+        }
+    }
+
+    // where an image should be loaded from
+    pub fn src(&self, url: &str) -> String {
+        self.resolve(url)
+    }
+
+    fn resolve(&self, url: &str) -> String {
+        let base = match &self.base_url {
+            Some(base) if !is_external(url) && !url.starts_with('#') && !url.is_empty() => base,
+            _ => return url.to_string(),
+        };
+        match url.strip_prefix('/') {
+            Some(path) => format!("{}/{}", origin(base), path),
+            None => format!("{}/{}", base.trim_end_matches('/'), url),
+        }
+    }
+}
+
+// `HtmlOptions::builder().number_headings(true).base_url("https://example.com/").build()`,
+// anything not set keeps its default
+#[derive(Clone, Debug, Default)]
+pub struct HtmlOptionsBuilder {
+    options: HtmlOptions,
+}
+
+impl HtmlOptionsBuilder {
+    pub fn code_class_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.options.code_class_prefix = prefix.into();
+        self
+    }
+
+    pub fn base_url(mut self, url: impl Into<String>) -> Self {
+        self.options.base_url = Some(url.into());
+        self
+    }
+
+    pub fn variables(mut self, variables: Variables) -> Self {
+        self.options.variables = variables;
+        self
+    }
+
+    pub fn bibliography(mut self, bibliography: Bibliography) -> Self {
+        self.options.bibliography = bibliography;
+        self
+    }
+
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.options.lang = Some(lang.into());
+        self
+    }
+
+    pub fn dir(mut self, dir: impl Into<String>) -> Self {
+        self.options.dir = Some(dir.into());
+        self
+    }
+
+    pub fn md_links_to_html(mut self, on: bool) -> Self {
+        self.options.md_links_to_html = on;
+        self
+    }
+
+    pub fn number_headings(mut self, on: bool) -> Self {
+        self.options.number_headings = on;
+        self
+    }
+
+    pub fn list_values(mut self, on: bool) -> Self {
+        self.options.list_values = on;
+        self
+    }
+
+    pub fn csv_tables(mut self, on: bool) -> Self {
+        self.options.csv_tables = on;
+        self
+    }
+
+    pub fn accessibility(mut self, on: bool) -> Self {
+        self.options.accessibility = on;
+        self
+    }
+
+    pub fn typographer(mut self, on: bool) -> Self {
+        self.options.typographer = on;
+        self
+    }
+
+    pub fn alert_classes(mut self, classes: AlertClasses) -> Self {
+        self.options.alert_classes = classes;
+        self
+    }
+
+    pub fn permalinks(mut self, permalinks: Permalinks) -> Self {
+        self.options.permalinks = Some(permalinks);
+        self
+    }
+
+    pub fn hard_breaks(mut self, on: bool) -> Self {
+        self.options.hard_breaks = on;
+        self
+    }
+
+    pub fn standalone_images(mut self, on: bool) -> Self {
+        self.options.standalone_images = on;
+        self
+    }
+
+    pub fn print(mut self, on: bool) -> Self {
+        self.options.print = on;
+        self
+    }
+
+    pub fn alt_from_filename(mut self, on: bool) -> Self {
+        self.options.alt_from_filename = on;
+        self
+    }
+
+    pub fn link_appendix(mut self, on: bool) -> Self {
+        self.options.link_appendix = on;
+        self
+    }
+
+    pub fn image_sizes(mut self, sizes: ImageSizes) -> Self {
+        self.options.image_sizes = sizes;
+        self
+    }
+
+    pub fn override_block(
+        mut self,
+        render: impl Fn(&Markdown, &HtmlOptions) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.options.override_block(render);
+        self
+    }
+
+    pub fn override_inline(
+        mut self,
+        render: impl Fn(&MarkdownInline, &HtmlOptions) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.options.override_inline(render);
+        self
+    }
+
+    pub fn override_math_fences(
+        mut self,
+        render: impl Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.options.override_math_fences(render);
+        self
+    }
+
+    pub fn filter(mut self, filter: impl Fn(String) -> String + Send + Sync + 'static) -> Self {
+        self.options.filter(filter);
+        self
+    }
+
+    pub fn build(self) -> HtmlOptions {
+        self.options
+    }
+}
+
+// `https://example.com/docs/` -> `https://example.com`
+fn origin(base: &str) -> &str {
+    let host_start = base.find("//").map_or(0, |i| i + 2);
+    match base[host_start..].find('/') {
+        Some(slash) => &base[..host_start + slash],
+        None => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_href() {
+        let options = HtmlOptions {
+            md_links_to_html: true,
+            ..Default::default()
+        };
+        assert_eq!(options.href("notes.md"), "notes.html");
+        assert_eq!(options.href("../a.md?x=1#y"), "../a.html?x=1#y");
+        assert_eq!(options.href("readme.mdx"), "readme.mdx");
+        assert_eq!(HtmlOptions::default().href("notes.md"), "notes.md");
+    }
+
+    #[test]
+    fn test_alt() {
+        let options = HtmlOptions {
+            alt_from_filename: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.alt("", &Url::from("img/cat_on-a-mat.png")),
+            "Cat on a mat"
+        );
+        assert_eq!(
+            options.alt(" ", &Url::from("https://example.com/IMG_2041.JPG?w=2")),
+            "IMG 2041"
+        );
+        assert_eq!(options.alt("a cat", &Url::from("cat.png")), "a cat");
+        assert_eq!(
+            options.alt("", &Url::from("data:image/png;base64,AAAA")),
+            ""
+        );
+        assert_eq!(HtmlOptions::default().alt("", &Url::from("cat.png")), "");
+    }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(HtmlOptions::builder().build(), HtmlOptions::default());
+        let options = HtmlOptions::builder()
+            .number_headings(true)
+            .base_url("https://example.com/")
+            .lang("he")
+            .build();
+        assert_eq!(
+            options,
+            HtmlOptions {
+                number_headings: true,
+                base_url: Some(String::from("https://example.com/")),
+                lang: Some(String::from("he")),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(origin("https://example.com/docs/"), "https://example.com");
+        assert_eq!(origin("https://example.com"), "https://example.com");
+    }
+}
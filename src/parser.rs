@@ -1,27 +1,131 @@
+use crate::options::Filters;
+use crate::prelude::*;
+use crate::Alert;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
 use crate::Markdown;
 use crate::MarkdownInline;
 use crate::MarkdownText;
+use crate::Span;
+use crate::Url;
+use alloc::sync::Arc;
+use core::convert::TryFrom;
+use core::fmt;
 
 use nom::{
     branch::alt,
-    bytes::complete::{is_not, tag, take, take_while1},
+    bytes::complete::{is_not, tag, take_until, take_while1},
+    character::complete::space0,
     character::is_digit,
-    combinator::{map, not},
-    multi::{many0, many1},
+    combinator::{consumed, map, map_opt, map_res, opt, recognize, verify},
+    multi::{count, many0, many1, separated_list1},
     sequence::{delimited, pair, preceded, terminated, tuple},
-    IResult,
+    IResult, Offset,
 };
 
 pub fn parse_markdown(i: &str) -> IResult<&str, Vec<Markdown>> {
-    many1(alt((
+    many1(parse_block)(i)
+}
+
+// block syntax prose doesn't know about, `@slide` separators or `:::note`
+// directives. a parser gets the document from the start of a line and hands
+// back the text of its block, usually all of it through `recognize`, which
+// becomes a `Markdown::Custom` under the name it was registered with
+pub type BlockParser = Arc<dyn Fn(&str) -> IResult<&str, &str> + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct ParserOptions {
+    blocks: Vec<(String, BlockParser)>,
+    // run over the source before it's parsed, in order
+    pub filters: Filters,
+}
+
+impl ParserOptions {
+    // adds a step to the end of the filters, a closure that strips comments
+    // of your own or expands macros before prose reads the markdown
+    pub fn filter(
+        &mut self,
+        filter: impl Fn(String) -> String + Send + Sync + 'static,
+    ) -> &mut ParserOptions {
+        self.filters.push(filter);
+        self
+    }
+
+    // block parsers are tried in the order they were registered, before any
+    // of the built in blocks
+    pub fn register_block(
+        &mut self,
+        name: &str,
+        parser: impl Fn(&str) -> IResult<&str, &str> + Send + Sync + 'static,
+    ) -> &mut ParserOptions {
+        self.blocks.push((name.to_string(), Arc::new(parser)));
+        self
+    }
+}
+
+impl fmt::Debug for ParserOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParserOptions")
+            .field(
+                "blocks",
+                &self.blocks.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("filters", &self.filters)
+            .finish()
+    }
+}
+
+pub fn parse_markdown_with<'a>(
+    i: &'a str,
+    options: &ParserOptions,
+) -> IResult<&'a str, Vec<Markdown>> {
+    tracing::debug_span!("parse", bytes = i.len())
+        .in_scope(|| many1(|i| parse_block_with(i, options))(i))
+}
+
+fn parse_block_with<'a>(i: &'a str, options: &ParserOptions) -> IResult<&'a str, Markdown> {
+    for (name, parser) in &options.blocks {
+        match parser(i) {
+            // a parser that takes nothing would have `many1` spin forever
+            Ok((rest, text)) if rest.len() < i.len() => {
+                return Ok((rest, Markdown::Custom(name.clone(), text.to_string())))
+            }
+            _ => {}
+        }
+    }
+    parse_block(i)
+}
+
+// same as parse_markdown, but also hands back where in `i` each block came from
+pub fn parse_markdown_spanned(i: &str) -> IResult<&str, Vec<(Markdown, Span)>> {
+    many1(map(consumed(parse_block), |(raw, block)| {
+        let start = i.offset(raw);
+        (
+            block,
+            Span {
+                start,
+                end: start + raw.len(),
+            },
+        )
+    }))(i)
+}
+
+pub(crate) fn parse_block(i: &str) -> IResult<&str, Markdown> {
+    alt((
         map(parse_header, |e| Markdown::Heading(e.0, e.1)),
-        map(parse_unordered_list, |e| Markdown::UnorderedList(e)),
-        map(parse_ordered_list, |e| Markdown::OrderedList(e)),
+        map(parse_unordered_list, Markdown::UnorderedList),
+        map(parse_ordered_list, Markdown::OrderedList),
         map(parse_code_block, |e| {
             Markdown::Codeblock(e.0.to_string(), e.1.to_string())
         }),
-        map(parse_markdown_text, |e| Markdown::Line(e)),
-    )))(i)
+        map(parse_footnote_def, |(label, text)| {
+            Markdown::FootnoteDef(label.to_string(), text)
+        }),
+        parse_table,
+        parse_alert,
+        map(parse_markdown_text, Markdown::Line),
+    ))(i)
 }
 
 fn parse_boldtext(i: &str) -> IResult<&str, &str> {
@@ -32,6 +136,21 @@ fn parse_italics(i: &str) -> IResult<&str, &str> {
     delimited(tag("*"), is_not("*"), tag("*"))(i)
 }
 
+// `++inserted++` text, for documents showing tracked edits. the text can't
+// start or end with a space, so the `++`s in `C++ and C++` aren't read as one
+fn parse_inserted(i: &str) -> IResult<&str, &str> {
+    delimited(
+        tag("++"),
+        verify(take_until("++"), |s: &str| {
+            !s.is_empty()
+                && !s.contains('\n')
+                && !s.starts_with(char::is_whitespace)
+                && !s.ends_with(char::is_whitespace)
+        }),
+        tag("++"),
+    )(i)
+}
+
 fn parse_inline_code(i: &str) -> IResult<&str, &str> {
     delimited(tag("`"), is_not("`"), tag("`"))(i)
 }
@@ -43,6 +162,32 @@ fn parse_link(i: &str) -> IResult<&str, (&str, &str)> {
     )(i)
 }
 
+// `[^label]`, a reference to a footnote defined elsewhere in the document
+fn parse_footnote_ref(i: &str) -> IResult<&str, &str> {
+    delimited(tag("[^"), is_not("]\n"), tag("]"))(i)
+}
+
+// `[@doe2020]` or `[@doe2020; @roe2021]`, citations of works in a bibliography
+fn parse_citation(i: &str) -> IResult<&str, Vec<&str>> {
+    delimited(
+        tag("["),
+        separated_list1(
+            delimited(space0, tag(";"), space0),
+            preceded(
+                tag("@"),
+                take_while1(|c: char| c.is_alphanumeric() || "_:.#$%&-+?<>~/".contains(c)),
+            ),
+        ),
+        tag("]"),
+    )(i)
+}
+
+// an obsidian style `![[note]]` or `![[cat.png|a cat]]` embed, kept as the
+// text it is for `embeds::embed` to make something of
+fn parse_embed(i: &str) -> IResult<&str, &str> {
+    recognize(delimited(tag("![["), is_not("]\n"), tag("]]")))(i)
+}
+
 fn parse_image(i: &str) -> IResult<&str, (&str, &str)> {
     pair(
         delimited(tag("!["), is_not("]"), tag("]")),
@@ -52,17 +197,34 @@ fn parse_image(i: &str) -> IResult<&str, (&str, &str)> {
 
 // we want to match many things that are not any of our specail tags
 // but since we have no tools available to match and consume in the negative case (without regex)
-// we need to match against our tags, then consume one char
-// we repeat this until we run into one of our special characters
-// then we join our array of characters into a String
+// we jump from one special character to the next until we find one that
+// could start something else, a `!` only counts when it opens an image and
+// a `+` when it opens inserted text, so `C++` stays text
 fn parse_plaintext(i: &str) -> IResult<&str, String> {
-    map(
-        many1(preceded(
-            not(alt((tag("*"), tag("`"), tag("["), tag("!["), tag("\n")))),
-            take(1u8),
-        )),
-        |vec| vec.join(""),
-    )(i)
+    let mut end = 0;
+    while let Some(at) = i[end..].find(['*', '`', '[', '!', '+', '\n']) {
+        end += at;
+        let rest = &i[end..];
+        let special = match rest.as_bytes()[0] {
+            b'!' => rest.starts_with("!["),
+            b'+' => parse_inserted(rest).is_ok(),
+            _ => true,
+        };
+        if special {
+            return plaintext(i, end);
+        }
+        end += 1;
+    }
+    plaintext(i, i.len())
+}
+
+fn plaintext(i: &str, end: usize) -> IResult<&str, String> {
+    let kind = match end {
+        0 if i.is_empty() => nom::error::ErrorKind::Eof,
+        0 => nom::error::ErrorKind::Not,
+        end => return Ok((&i[end..], i[..end].to_string())),
+    };
+    Err(nom::Err::Error(nom::error::Error::new(i, kind)))
 }
 
 fn parse_markdown_inline(i: &str) -> IResult<&str, MarkdownInline> {
@@ -76,30 +238,55 @@ fn parse_markdown_inline(i: &str) -> IResult<&str, MarkdownInline> {
         map(parse_boldtext, |s: &str| {
             MarkdownInline::Bold(s.to_string())
         }),
+        map(parse_inserted, |s: &str| {
+            MarkdownInline::Inserted(s.to_string())
+        }),
+        map(parse_embed, |embed: &str| {
+            MarkdownInline::Plaintext(embed.to_string())
+        }),
         map(parse_image, |(tag, url): (&str, &str)| {
-            MarkdownInline::Image(tag.to_string(), url.to_string())
+            MarkdownInline::Image(tag.to_string(), Url::from(url))
         }),
+        // before citations, `[@octocat](https://github.com/octocat)` is a link
         map(parse_link, |(tag, url): (&str, &str)| {
-            MarkdownInline::Link(tag.to_string(), url.to_string())
+            MarkdownInline::Link(tag.to_string(), Url::from(url))
+        }),
+        map(parse_citation, |keys: Vec<&str>| {
+            MarkdownInline::Citation(keys.iter().map(|key| key.to_string()).collect())
         }),
-        map(parse_plaintext, |s| MarkdownInline::Plaintext(s)),
+        map(parse_footnote_ref, |label: &str| {
+            MarkdownInline::FootnoteRef(label.to_string())
+        }),
+        map(parse_plaintext, MarkdownInline::Plaintext),
     ))(i)
 }
 
 fn parse_markdown_text(i: &str) -> IResult<&str, MarkdownText> {
-    terminated(many0(parse_markdown_inline), tag("\n"))(i)
+    map(
+        terminated(many0(parse_markdown_inline), tag("\n")),
+        MarkdownText,
+    )(i)
+}
+
+// a line like `[^label]: the note itself`
+fn parse_footnote_def(i: &str) -> IResult<&str, (&str, MarkdownText)> {
+    pair(
+        delimited(tag("[^"), is_not("]\n"), tag("]: ")),
+        parse_markdown_text,
+    )(i)
 }
 
 // this guy matches the literal character #
-fn parse_header_tag(i: &str) -> IResult<&str, usize> {
-    map(
+// seven or more `#`s are no heading at all
+fn parse_header_tag(i: &str) -> IResult<&str, HeadingLevel> {
+    map_res(
         terminated(take_while1(|c| c == '#'), tag(" ")),
-        |s: &str| s.len(),
+        |s: &str| HeadingLevel::try_from(s.len()),
     )(i)
 }
 
 // this combines a tuple of the header tag and the rest of the line
-fn parse_header(i: &str) -> IResult<&str, (usize, MarkdownText)> {
+fn parse_header(i: &str) -> IResult<&str, (HeadingLevel, MarkdownText)> {
     tuple((parse_header_tag, parse_markdown_text))(i)
 }
 
@@ -111,8 +298,18 @@ fn parse_unordered_list_element(i: &str) -> IResult<&str, MarkdownText> {
     preceded(parse_unordered_list_tag, parse_markdown_text)(i)
 }
 
-fn parse_unordered_list(i: &str) -> IResult<&str, Vec<MarkdownText>> {
-    many1(parse_unordered_list_element)(i)
+fn parse_unordered_list(i: &str) -> IResult<&str, Vec<ListItem>> {
+    parse_unordered_list_at(i, 0)
+}
+
+fn parse_unordered_list_at(i: &str, indent: usize) -> IResult<&str, Vec<ListItem>> {
+    many1(map(
+        pair(
+            preceded(count(tag(" "), indent), parse_unordered_list_element),
+            many0(|i| parse_nested_list(i, indent)),
+        ),
+        |(text, children)| ListItem { text, children },
+    ))(i)
 }
 
 fn parse_ordered_list_tag(i: &str) -> IResult<&str, &str> {
@@ -122,12 +319,158 @@ fn parse_ordered_list_tag(i: &str) -> IResult<&str, &str> {
     )(i)
 }
 
-fn parse_ordered_list_element(i: &str) -> IResult<&str, MarkdownText> {
-    preceded(parse_ordered_list_tag, parse_markdown_text)(i)
+// the number the author wrote along with the item, lists don't have to count from 1
+fn parse_ordered_list_element(i: &str) -> IResult<&str, (usize, MarkdownText)> {
+    pair(
+        map_res(parse_ordered_list_tag, |n: &str| n.parse::<usize>()),
+        parse_markdown_text,
+    )(i)
+}
+
+fn parse_ordered_list(i: &str) -> IResult<&str, Vec<(usize, ListItem)>> {
+    parse_ordered_list_at(i, 0)
+}
+
+fn parse_ordered_list_at(i: &str, indent: usize) -> IResult<&str, Vec<(usize, ListItem)>> {
+    many1(map(
+        pair(
+            preceded(count(tag(" "), indent), parse_ordered_list_element),
+            many0(|i| parse_nested_list(i, indent)),
+        ),
+        |((number, text), children)| (number, ListItem { text, children }),
+    ))(i)
 }
 
-fn parse_ordered_list(i: &str) -> IResult<&str, Vec<MarkdownText>> {
-    many1(parse_ordered_list_element)(i)
+// a list whose markers sit `indent` spaces in
+fn parse_list(i: &str, indent: usize) -> IResult<&str, Markdown> {
+    alt((
+        map(
+            |i| parse_unordered_list_at(i, indent),
+            Markdown::UnorderedList,
+        ),
+        map(|i| parse_ordered_list_at(i, indent), Markdown::OrderedList),
+    ))(i)
+}
+
+// items can have lists of either kind nested under them,
+// as long as they are indented further than the item's own marker
+fn parse_nested_list(i: &str, indent: usize) -> IResult<&str, Markdown> {
+    let deeper = i.len() - i.trim_start_matches(' ').len();
+    if deeper <= indent {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+    parse_list(i, deeper)
+}
+
+// the cells of a `| a | b |` line, the closing pipe is optional
+fn parse_table_cells(i: &str) -> IResult<&str, Vec<&str>> {
+    map(delimited(tag("|"), is_not("\n"), tag("\n")), split_cells)(i)
+}
+
+fn split_cells(row: &str) -> Vec<&str> {
+    let row = row.trim_end();
+    let row = match row.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => row,
+    };
+    let mut cells = vec![];
+    let mut start = 0;
+    for (at, _) in row.match_indices('|') {
+        // `\|` is a pipe inside of a cell
+        if !row[..at].ends_with('\\') {
+            cells.push(row[start..at].trim());
+            start = at + 1;
+        }
+    }
+    cells.push(row[start..].trim());
+    cells
+}
+
+// cells get the same inline markup as any other line, a cell that
+// does not parse all the way is kept as plain text
+fn parse_table_cell(cell: &str) -> MarkdownText {
+    let cell = cell.replace("\\|", "|");
+    let parsed = match many0(parse_markdown_inline)(&cell) {
+        Ok(("", text)) => Some(text),
+        _ => None,
+    };
+    MarkdownText(parsed.unwrap_or_else(|| vec![MarkdownInline::Plaintext(cell.to_string())]))
+}
+
+fn parse_table_row(i: &str) -> IResult<&str, Vec<MarkdownText>> {
+    map(parse_table_cells, |cells| {
+        cells.into_iter().map(parse_table_cell).collect()
+    })(i)
+}
+
+// `|:---|:---:|---:|`, colons mark which way a column leans
+fn parse_table_delimiter(i: &str) -> IResult<&str, Vec<Align>> {
+    map_opt(parse_table_cells, |cells| {
+        cells
+            .iter()
+            .map(|cell| {
+                let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+                if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                    return None;
+                }
+                Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                    (true, true) => Align::Center,
+                    (true, false) => Align::Left,
+                    (false, true) => Align::Right,
+                    (false, false) => Align::Default,
+                })
+            })
+            .collect()
+    })(i)
+}
+
+// a header row, the delimiter row and any number of body rows.
+// body rows are padded or cut down to as many cells as the header has
+fn parse_table(i: &str) -> IResult<&str, Markdown> {
+    map(
+        verify(
+            tuple((
+                parse_table_row,
+                parse_table_delimiter,
+                many0(parse_table_row),
+            )),
+            |(header, aligns, _)| header.len() == aligns.len(),
+        ),
+        |(header, aligns, rows)| {
+            let rows = rows
+                .into_iter()
+                .map(|mut row| {
+                    row.resize(header.len(), MarkdownText::new());
+                    row
+                })
+                .collect();
+            Markdown::Table(header, aligns, rows)
+        },
+    )(i)
+}
+
+// `> [!NOTE]` on a line of its own and the `> ` quoted lines under it. the
+// kind is read whatever its case, like github does
+fn parse_alert(i: &str) -> IResult<&str, Markdown> {
+    map(
+        pair(
+            delimited(
+                tag("> [!"),
+                map_opt(is_not("]\n"), |name: &str| {
+                    Alert::ALL
+                        .iter()
+                        .copied()
+                        .find(|alert| alert.name().eq_ignore_ascii_case(name))
+                }),
+                tuple((tag("]"), space0, tag("\n"))),
+            ),
+            many0(preceded(pair(tag(">"), opt(tag(" "))), parse_markdown_text)),
+        ),
+        |(alert, lines)| Markdown::Alert(alert, lines),
+    )(i)
 }
 
 fn parse_code_block(i: &str) -> IResult<&str, (String, &str)> {
@@ -148,8 +491,37 @@ fn parse_code_block_lang(i: &str) -> IResult<&str, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nom::combinator::recognize;
     use nom::{error::Error, error::ErrorKind, Err as NomErr};
 
+    #[test]
+    fn test_parse_markdown_with() {
+        let mut options = ParserOptions::default();
+        options.register_block("slide", |i| {
+            recognize(tuple((tag("@slide"), is_not("\n"), tag("\n"))))(i)
+        });
+        assert_eq!(
+            parse_markdown_with("# One\n@slide two\ntext\n", &options),
+            Ok((
+                "",
+                vec![
+                    Markdown::Heading(
+                        HeadingLevel::H1,
+                        vec![MarkdownInline::Plaintext(String::from("One"))].into()
+                    ),
+                    Markdown::Custom(String::from("slide"), String::from("@slide two\n")),
+                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from("text"))].into()),
+                ]
+            ))
+        );
+        // parsers that match nothing are passed over
+        options.register_block("nothing", |i| Ok((i, "")));
+        assert_eq!(
+            parse_markdown_with("text\n", &options),
+            parse_markdown("text\n")
+        );
+    }
+
     #[test]
     fn test_parse_italics() {
         assert_eq!(
@@ -208,6 +580,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_inserted() {
+        assert_eq!(
+            parse_inserted("++new text++ after"),
+            Ok((" after", "new text"))
+        );
+        assert!(parse_inserted("++++").is_err());
+        assert!(parse_inserted("++no end\n++").is_err());
+        assert_eq!(
+            parse_markdown_text("C++ and ++a + b++\n"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext(String::from("C++ and ")),
+                    MarkdownInline::Inserted(String::from("a + b")),
+                ]
+                .into()
+            ))
+        );
+    }
+
     #[test]
     fn test_parse_boldtext() {
         assert_eq!(parse_boldtext("**here is bold**"), Ok(("", "here is bold")));
@@ -327,6 +720,121 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_footnotes() {
+        assert_eq!(parse_footnote_ref("[^1] more"), Ok((" more", "1")));
+        assert_eq!(
+            parse_markdown_text("see[^note].\n"),
+            Ok((
+                "",
+                vec![
+                    MarkdownInline::Plaintext(String::from("see")),
+                    MarkdownInline::FootnoteRef(String::from("note")),
+                    MarkdownInline::Plaintext(String::from(".")),
+                ]
+                .into()
+            ))
+        );
+        assert_eq!(
+            parse_block("[^note]: a *note*\n"),
+            Ok((
+                "",
+                Markdown::FootnoteDef(
+                    String::from("note"),
+                    vec![
+                        MarkdownInline::Plaintext(String::from("a ")),
+                        MarkdownInline::Italic(String::from("note")),
+                    ]
+                    .into()
+                )
+            ))
+        );
+        // without the colon it is just a line starting with a reference
+        assert_eq!(
+            parse_block("[^note] a\n"),
+            Ok((
+                "",
+                Markdown::Line(
+                    vec![
+                        MarkdownInline::FootnoteRef(String::from("note")),
+                        MarkdownInline::Plaintext(String::from(" a")),
+                    ]
+                    .into()
+                )
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_citation() {
+        assert_eq!(parse_citation("[@doe2020]"), Ok(("", vec!["doe2020"])));
+        assert_eq!(
+            parse_citation("[@doe:2020; @roe-2021]."),
+            Ok((".", vec!["doe:2020", "roe-2021"]))
+        );
+        assert_eq!(
+            parse_citation("[@doe, p. 3]"),
+            Err(NomErr::Error(Error {
+                input: ", p. 3]",
+                code: ErrorKind::Tag
+            }))
+        );
+        // a handle that is linked somewhere is no citation
+        assert_eq!(
+            parse_markdown_inline("[@octocat](https://github.com/octocat) [@doe2020]"),
+            Ok((
+                " [@doe2020]",
+                MarkdownInline::Link(
+                    String::from("@octocat"),
+                    Url::from("https://github.com/octocat")
+                )
+            ))
+        );
+        assert_eq!(
+            parse_markdown_inline("[@doe2020]"),
+            Ok(("", MarkdownInline::Citation(vec![String::from("doe2020")])))
+        );
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let plain = |s: &str| MarkdownText(vec![MarkdownInline::Plaintext(s.to_string())]);
+        assert_eq!(
+            parse_block("| name | `code` |\n|:--|--:|\n| **a** | [b](b.md) |\n| a \\| b\n"),
+            Ok((
+                "",
+                Markdown::Table(
+                    vec![
+                        plain("name"),
+                        vec![MarkdownInline::InlineCode(String::from("code"))].into()
+                    ],
+                    vec![Align::Left, Align::Right],
+                    vec![
+                        vec![
+                            vec![MarkdownInline::Bold(String::from("a"))].into(),
+                            vec![MarkdownInline::Link(String::from("b"), Url::from("b.md"))].into(),
+                        ],
+                        vec![plain("a | b"), vec![].into()],
+                    ]
+                )
+            ))
+        );
+        // a cell with a stray `*` stays as it was written
+        assert_eq!(parse_table_cell("2 * 3"), plain("2 * 3"));
+        assert_eq!(
+            parse_table_delimiter("| --- | :-: | x |\n"),
+            Err(NomErr::Error(Error {
+                input: "| --- | :-: | x |\n",
+                code: ErrorKind::MapOpt
+            }))
+        );
+        // without a delimiter row it is just a line
+        assert_eq!(
+            parse_block("| a | b |\n"),
+            Ok(("", Markdown::Line(plain("| a | b |"))))
+        );
+    }
+
     #[test]
     fn test_parse_image() {
         assert_eq!(
@@ -342,6 +850,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_embed() {
+        assert_eq!(parse_embed("![[note]] after"), Ok((" after", "![[note]]")));
+        assert_eq!(
+            parse_markdown_text("see ![[cat.png|a cat]]\n"),
+            Ok((
+                "",
+                MarkdownText(vec![
+                    MarkdownInline::Plaintext(String::from("see ")),
+                    MarkdownInline::Plaintext(String::from("![[cat.png|a cat]]")),
+                ])
+            ))
+        );
+        assert!(parse_embed("![[]]").is_err());
+    }
+
     #[test]
     fn test_parse_plaintext() {
         assert_eq!(
@@ -360,6 +884,10 @@ mod tests {
             parse_plaintext("oh my gosh!*"),
             Ok(("*", String::from("oh my gosh!")))
         );
+        assert_eq!(
+            parse_plaintext("wow! héllo!! wörld`"),
+            Ok(("`", String::from("wow! héllo!! wörld")))
+        );
         assert_eq!(
             parse_plaintext("*bold babey bold*"),
             Err(NomErr::Error(Error {
@@ -466,17 +994,14 @@ mod tests {
             parse_markdown_inline("[title](https://www.example.com)"),
             Ok((
                 "",
-                (MarkdownInline::Link(
-                    String::from("title"),
-                    String::from("https://www.example.com")
-                ))
+                (MarkdownInline::Link(String::from("title"), Url::from("https://www.example.com")))
             ))
         );
         assert_eq!(
             parse_markdown_inline("![alt text](image.jpg)"),
             Ok((
                 "",
-                (MarkdownInline::Image(String::from("alt text"), String::from("image.jpg")))
+                (MarkdownInline::Image(String::from("alt text"), Url::from("image.jpg")))
             ))
         );
         assert_eq!(
@@ -521,7 +1046,7 @@ mod tests {
 
     #[test]
     fn test_parse_markdown_text() {
-        assert_eq!(parse_markdown_text("\n"), Ok(("", vec![])));
+        assert_eq!(parse_markdown_text("\n"), Ok(("", vec![].into())));
         assert_eq!(
             parse_markdown_text("here is some plaintext\n"),
             Ok((
@@ -529,6 +1054,7 @@ mod tests {
                 vec![MarkdownInline::Plaintext(String::from(
                     "here is some plaintext"
                 ))]
+                .into()
             ))
         );
         assert_eq!(
@@ -539,6 +1065,7 @@ mod tests {
                     MarkdownInline::Plaintext(String::from("here is some plaintext ")),
                     MarkdownInline::Italic(String::from("but what if we italicize?")),
                 ]
+                .into()
             ))
         );
         assert_eq!(
@@ -550,7 +1077,7 @@ mod tests {
                 MarkdownInline::Bold(String::from("matter")),
                 MarkdownInline::Plaintext(String::from(" in my ")),
                 MarkdownInline::InlineCode(String::from("code")),
-            ]))
+            ].into()))
         );
         assert_eq!(
             parse_markdown_text("here is some plaintext *but what if we italicize?*\n"),
@@ -560,6 +1087,7 @@ mod tests {
                     MarkdownInline::Plaintext(String::from("here is some plaintext ")),
                     MarkdownInline::Italic(String::from("but what if we italicize?")),
                 ]
+                .into()
             ))
         );
         assert_eq!(
@@ -573,10 +1101,11 @@ mod tests {
 
     #[test]
     fn test_parse_header_tag() {
-        assert_eq!(parse_header_tag("# "), Ok(("", 1)));
-        assert_eq!(parse_header_tag("### "), Ok(("", 3)));
-        assert_eq!(parse_header_tag("# h1"), Ok(("h1", 1)));
-        assert_eq!(parse_header_tag("# h1"), Ok(("h1", 1)));
+        assert_eq!(parse_header_tag("# "), Ok(("", HeadingLevel::H1)));
+        assert_eq!(parse_header_tag("### "), Ok(("", HeadingLevel::H3)));
+        assert_eq!(parse_header_tag("# h1"), Ok(("h1", HeadingLevel::H1)));
+        assert!(parse_header_tag("####### h7").is_err());
+        assert_eq!(parse_header_tag("# h1"), Ok(("h1", HeadingLevel::H1)));
         assert_eq!(
             parse_header_tag(" "),
             Err(NomErr::Error(Error {
@@ -597,17 +1126,32 @@ mod tests {
     fn test_parse_header() {
         assert_eq!(
             parse_header("# h1\n"),
-            Ok(("", (1, vec![MarkdownInline::Plaintext(String::from("h1"))])))
+            Ok((
+                "",
+                (
+                    HeadingLevel::H1,
+                    vec![MarkdownInline::Plaintext(String::from("h1"))].into()
+                )
+            ))
         );
         assert_eq!(
             parse_header("## h2\n"),
-            Ok(("", (2, vec![MarkdownInline::Plaintext(String::from("h2"))])))
+            Ok((
+                "",
+                (
+                    HeadingLevel::H2,
+                    vec![MarkdownInline::Plaintext(String::from("h2"))].into()
+                )
+            ))
         );
         assert_eq!(
             parse_header("###  h3\n"),
             Ok((
                 "",
-                (3, vec![MarkdownInline::Plaintext(String::from(" h3"))])
+                (
+                    HeadingLevel::H3,
+                    vec![MarkdownInline::Plaintext(String::from(" h3"))].into()
+                )
             ))
         );
         assert_eq!(
@@ -638,7 +1182,10 @@ mod tests {
                 code: ErrorKind::Tag
             }))
         );
-        assert_eq!(parse_header("# \n"), Ok(("", (1, vec![]))));
+        assert_eq!(
+            parse_header("# \n"),
+            Ok(("", (HeadingLevel::H1, vec![].into())))
+        );
         assert_eq!(
             parse_header("# test"),
             Err(NomErr::Error(Error {
@@ -694,6 +1241,7 @@ mod tests {
                 vec![MarkdownInline::Plaintext(String::from(
                     "this is an element"
                 ))]
+                .into()
             ))
         );
         assert_eq!(
@@ -707,6 +1255,7 @@ mod tests {
                 vec![MarkdownInline::Plaintext(String::from(
                     "this is an element"
                 ))]
+                .into()
             ))
         );
         assert_eq!(
@@ -716,7 +1265,10 @@ mod tests {
                 code: ErrorKind::Tag
             }))
         );
-        assert_eq!(parse_unordered_list_element("- \n"), Ok(("", vec![])));
+        assert_eq!(
+            parse_unordered_list_element("- \n"),
+            Ok(("", vec![].into()))
+        );
         assert_eq!(
             parse_unordered_list_element("- "),
             Err(NomErr::Error(Error {
@@ -753,9 +1305,9 @@ mod tests {
             parse_unordered_list("- this is an element\n"),
             Ok((
                 "",
-                vec![vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]]
+                vec![ListItem::from(vec![MarkdownInline::Plaintext(
+                    String::from("this is an element")
+                )])]
             ))
         );
         assert_eq!(
@@ -767,15 +1319,58 @@ mod tests {
             Ok((
                 "",
                 vec![
-                    vec![MarkdownInline::Plaintext(String::from(
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from(
                         "this is an element"
-                    ))],
-                    vec![MarkdownInline::Plaintext(String::from("here is another"))]
+                    ))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from(
+                        "here is another"
+                    ))])
                 ]
             ))
         );
     }
 
+    #[test]
+    fn test_parse_nested_lists() {
+        let item = |s: &str| ListItem::from(vec![MarkdownInline::Plaintext(s.to_string())]);
+        assert_eq!(
+            parse_block("1. one\n   - a\n     1. deep\n   - b\n   3. c\n2. two\n- next\n"),
+            Ok((
+                "- next\n",
+                Markdown::OrderedList(vec![
+                    (
+                        1,
+                        ListItem {
+                            children: vec![
+                                Markdown::UnorderedList(vec![
+                                    ListItem {
+                                        children: vec![Markdown::OrderedList(vec![(
+                                            1,
+                                            item("deep")
+                                        )])],
+                                        ..item("a")
+                                    },
+                                    item("b"),
+                                ]),
+                                Markdown::OrderedList(vec![(3, item("c"))]),
+                            ],
+                            ..item("one")
+                        }
+                    ),
+                    (2, item("two")),
+                ])
+            ))
+        );
+        // an indented list with nothing to nest under is not a list
+        assert_eq!(
+            parse_block("  - a\n"),
+            Ok((
+                "",
+                Markdown::Line(vec![MarkdownInline::Plaintext(String::from("  - a"))].into())
+            ))
+        );
+    }
+
     #[test]
     fn test_parse_ordered_list_tag() {
         assert_eq!(parse_ordered_list_tag("1. "), Ok(("", "1")));
@@ -820,9 +1415,13 @@ mod tests {
             parse_ordered_list_element("1. this is an element\n"),
             Ok((
                 "",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]
+                (
+                    1,
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))]
+                    .into()
+                )
             ))
         );
         assert_eq!(
@@ -833,9 +1432,13 @@ mod tests {
             ),
             Ok((
                 "1. here is another\n",
-                vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]
+                (
+                    1,
+                    vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))]
+                    .into()
+                )
             ))
         );
         assert_eq!(
@@ -852,7 +1455,14 @@ mod tests {
                 code: ErrorKind::TakeWhile1
             }))
         );
-        assert_eq!(parse_ordered_list_element("1. \n"), Ok(("", vec![])));
+        assert_eq!(
+            parse_ordered_list_element("1. \n"),
+            Ok(("", (1, vec![].into())))
+        );
+        assert_eq!(
+            parse_ordered_list_element("42. \n"),
+            Ok(("", (42, vec![].into())))
+        );
         assert_eq!(
             parse_ordered_list_element("1. test"),
             Err(NomErr::Error(Error {
@@ -882,9 +1492,12 @@ mod tests {
             parse_ordered_list("1. this is an element\n"),
             Ok((
                 "",
-                vec![vec![MarkdownInline::Plaintext(String::from(
-                    "this is an element"
-                ))]]
+                vec![(
+                    1,
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from(
+                        "this is an element"
+                    ))])
+                )]
             ))
         );
         assert_eq!(
@@ -903,10 +1516,18 @@ mod tests {
             Ok((
                 "",
                 vec![
-                    vec!(MarkdownInline::Plaintext(String::from(
-                        "this is an element"
-                    ))),
-                    vec![MarkdownInline::Plaintext(String::from("here is another"))]
+                    (
+                        1,
+                        ListItem::from(vec!(MarkdownInline::Plaintext(String::from(
+                            "this is an element"
+                        ))))
+                    ),
+                    (
+                        2,
+                        ListItem::from(vec![MarkdownInline::Plaintext(String::from(
+                            "here is another"
+                        ))])
+                    )
                 ]
             ))
         );
@@ -1002,27 +1623,36 @@ foobar.singularize('phenomena') # returns 'phenomenon'
             Ok((
                 "",
                 vec![
-                    Markdown::Heading(1, vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
-                    Markdown::Line(vec![]),
-                    Markdown::Line(vec![MarkdownInline::Plaintext(String::from(
-                        "Foobar is a Python library for dealing with word pluralization."
-                    ))]),
-                    Markdown::Line(vec![]),
+                    Markdown::Heading(
+                        HeadingLevel::H1,
+                        vec![MarkdownInline::Plaintext(String::from("Foobar"))].into()
+                    ),
+                    Markdown::Line(vec![].into()),
+                    Markdown::Line(
+                        vec![MarkdownInline::Plaintext(String::from(
+                            "Foobar is a Python library for dealing with word pluralization."
+                        ))]
+                        .into()
+                    ),
+                    Markdown::Line(vec![].into()),
                     Markdown::Codeblock(String::from("bash"), String::from("pip install foobar\n")),
-                    Markdown::Line(vec![]),
+                    Markdown::Line(vec![].into()),
                     Markdown::Heading(
-                        2,
-                        vec![MarkdownInline::Plaintext(String::from("Installation"))]
+                        HeadingLevel::H2,
+                        vec![MarkdownInline::Plaintext(String::from("Installation"))].into()
+                    ),
+                    Markdown::Line(vec![].into()),
+                    Markdown::Line(
+                        vec![
+                            MarkdownInline::Plaintext(String::from("Use the package manager ")),
+                            MarkdownInline::Link(
+                                String::from("pip"),
+                                Url::from("https://pip.pypa.io/en/stable/")
+                            ),
+                            MarkdownInline::Plaintext(String::from(" to install foobar.")),
+                        ]
+                        .into()
                     ),
-                    Markdown::Line(vec![]),
-                    Markdown::Line(vec![
-                        MarkdownInline::Plaintext(String::from("Use the package manager ")),
-                        MarkdownInline::Link(
-                            String::from("pip"),
-                            String::from("https://pip.pypa.io/en/stable/")
-                        ),
-                        MarkdownInline::Plaintext(String::from(" to install foobar.")),
-                    ]),
                     Markdown::Codeblock(
                         String::from("python"),
                         String::from(
@@ -1038,4 +1668,35 @@ foobar.singularize('phenomena') # returns 'phenomenon'
             ))
         )
     }
+
+    #[test]
+    fn test_parse_markdown_spanned() {
+        assert_eq!(
+            parse_markdown_spanned("# Foobar\n\n- one\n- two\n```\ncode\n```"),
+            Ok((
+                "",
+                vec![
+                    (
+                        Markdown::Heading(
+                            HeadingLevel::H1,
+                            vec![MarkdownInline::Plaintext(String::from("Foobar"))].into()
+                        ),
+                        Span { start: 0, end: 9 }
+                    ),
+                    (Markdown::Line(vec![].into()), Span { start: 9, end: 10 }),
+                    (
+                        Markdown::UnorderedList(vec![
+                            ListItem::from(vec![MarkdownInline::Plaintext(String::from("one"))]),
+                            ListItem::from(vec![MarkdownInline::Plaintext(String::from("two"))]),
+                        ]),
+                        Span { start: 10, end: 22 }
+                    ),
+                    (
+                        Markdown::Codeblock(String::from("__UNKNOWN__"), String::from("code\n")),
+                        Span { start: 22, end: 34 }
+                    ),
+                ]
+            ))
+        );
+    }
 }
@@ -0,0 +1,163 @@
+use crate::filters;
+use crate::frontmatter;
+use crate::markdown_with_options;
+use crate::options::{AlertClasses, HtmlOptions, PermalinkPosition, Permalinks, ProseOptions};
+use crate::parser;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_json::Value;
+
+// `render("# Hi", number_headings=True)`, like `markdown` it renders as much as parsed
+#[pyfunction]
+#[pyo3(signature = (md, **options))]
+fn render(md: &str, options: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let options = ProseOptions {
+        html: match options {
+            Some(options) => html_options(options)?,
+            None => HtmlOptions::default(),
+        },
+        ..ProseOptions::default()
+    };
+    Ok(markdown_with_options(md, &options).unwrap_or_default())
+}
+
+// the blocks as dicts, `{"type": "Heading", "value": [1, [...]]}`
+#[pyfunction]
+fn parse(py: Python<'_>, md: &str) -> PyResult<PyObject> {
+    let (_, md) = frontmatter::split(md);
+    let blocks = parser::parse_markdown(md)
+        .map(|(_, blocks)| blocks)
+        .unwrap_or_default();
+    let value = serde_json::to_value(&blocks).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    to_python(py, &value)
+}
+
+#[pymodule]
+fn markdown_to_html(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(render, m)?)?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    Ok(())
+}
+
+// the keyword arguments are named like the fields of `HtmlOptions`
+fn html_options(kwargs: &Bound<'_, PyDict>) -> PyResult<HtmlOptions> {
+    let mut options = HtmlOptions::default();
+    // the symbol and position only count once permalinks are asked for, whatever
+    // order the arguments came in
+    let mut permalinks = false;
+    let mut permalink = Permalinks::default();
+    for (key, value) in kwargs.iter() {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "code_class_prefix" => options.code_class_prefix = value.extract()?,
+            "base_url" => options.base_url = value.extract()?,
+            "md_links_to_html" => options.md_links_to_html = value.extract()?,
+            "variables" => options.variables = value.extract()?,
+            "number_headings" => options.number_headings = value.extract()?,
+            "list_values" => options.list_values = value.extract()?,
+            "csv_tables" => options.csv_tables = value.extract()?,
+            "lang" => options.lang = value.extract()?,
+            "dir" => options.dir = value.extract()?,
+            "accessibility" => options.accessibility = value.extract()?,
+            "typographer" => options.typographer = value.extract()?,
+            "link_appendix" => options.link_appendix = value.extract()?,
+            "alt_from_filename" => options.alt_from_filename = value.extract()?,
+            "hard_breaks" => options.hard_breaks = value.extract()?,
+            "standalone_images" => options.standalone_images = value.extract()?,
+            "print" => options.print = value.extract()?,
+            "permalinks" => permalinks = value.extract()?,
+            "permalink_symbol" => permalink.symbol = value.extract()?,
+            "permalink_position" => {
+                let name = value.extract::<String>()?;
+                permalink.position = PermalinkPosition::named(&name).ok_or_else(|| {
+                    PyValueError::new_err(format!("unknown permalink position '{}'", name))
+                })?;
+            }
+            "alert_classes" => {
+                let name = value.extract::<String>()?;
+                options.alert_classes = AlertClasses::named(&name).ok_or_else(|| {
+                    PyValueError::new_err(format!("unknown alert classes '{}'", name))
+                })?;
+            }
+            "filters" => {
+                for name in value.extract::<Vec<String>>()? {
+                    let filter = filters::named(&name).ok_or_else(|| {
+                        PyValueError::new_err(format!("unknown filter '{}'", name))
+                    })?;
+                    options.filter(filter);
+                }
+            }
+            _ => {
+                return Err(PyTypeError::new_err(format!(
+                    "render() got an unexpected keyword argument '{}'",
+                    key
+                )))
+            }
+        }
+    }
+    if permalinks {
+        options.permalinks = Some(permalink);
+    }
+    Ok(options)
+}
+
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Value::Number(n) => match n.as_u64() {
+            Some(n) => n.into_pyobject(py)?.into_any().unbind(),
+            None => n.as_f64().into_pyobject(py)?.into_any().unbind(),
+        },
+        Value::String(s) => s.into_pyobject(py)?.into_any().unbind(),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(to_python(py, item)?)?;
+            }
+            list.into_any().unbind()
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (key, field) in fields {
+                dict.set_item(key, to_python(py, field)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_options() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("number_headings", true).unwrap();
+            kwargs.set_item("code_class_prefix", "language-").unwrap();
+            assert_eq!(
+                render("# Hi\n```rust\nx\n```\n", Some(&kwargs)).unwrap(),
+                "<h1><span class=\"heading-number\">1.</span> Hi</h1><pre><code class=\"language-rust\">x\n</code></pre>"
+            );
+            kwargs.set_item("numbered", true).unwrap();
+            assert!(render("# Hi\n", Some(&kwargs)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_parse() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let blocks = parse(py, "# Hi\n").unwrap();
+            let blocks = blocks.bind(py);
+            assert_eq!(
+                blocks.repr().unwrap().to_string(),
+                "[{'type': 'Heading', 'value': [1, [{'type': 'Plaintext', 'value': 'Hi'}]]}]"
+            );
+        });
+    }
+}
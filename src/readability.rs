@@ -0,0 +1,98 @@
+// how hard a piece of text is to read, from counting its sentences, words and
+// syllables. the scores are the usual Flesch ones, made for English
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Readability {
+    pub sentences: usize,
+    pub words: usize,
+    pub syllables: usize,
+    // words per sentence
+    pub average_sentence_length: f64,
+    // 0 to 100, higher is easier. most plain writing lands around 60 to 70
+    pub reading_ease: f64,
+    // the school grade a reader needs to follow along
+    pub grade_level: f64,
+}
+
+// sentences end at `.`, `!` or `?`, and at the end of every line, since
+// headings and list items rarely finish with a full stop
+pub fn readability(text: &str) -> Readability {
+    let (mut sentences, mut words, mut syllables) = (0, 0, 0);
+    for line in text.lines() {
+        let mut open = false;
+        for word in line.split_whitespace() {
+            let letters = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if !letters.is_empty() {
+                words += 1;
+                syllables += count_syllables(letters);
+                open = true;
+            }
+            if open && word.ends_with(['.', '!', '?']) {
+                sentences += 1;
+                open = false;
+            }
+        }
+        if open {
+            sentences += 1;
+        }
+    }
+    if words == 0 {
+        return Readability::default();
+    }
+
+    let per_sentence = words as f64 / sentences as f64;
+    let per_word = syllables as f64 / words as f64;
+    Readability {
+        sentences,
+        words,
+        syllables,
+        average_sentence_length: per_sentence,
+        reading_ease: 206.835 - 1.015 * per_sentence - 84.6 * per_word,
+        grade_level: 0.39 * per_sentence + 11.8 * per_word - 15.59,
+    }
+}
+
+// a guess from the groups of vowels in a word, close enough for English:
+// a final silent `e` doesn't count and every word has at least one
+fn count_syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut previous = false;
+    for c in word.chars() {
+        let is_vowel = vowel(c);
+        if is_vowel && !previous {
+            count += 1;
+        }
+        previous = is_vowel;
+    }
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_syllables() {
+        assert_eq!(count_syllables("cat"), 1);
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("readability"), 5);
+        assert_eq!(count_syllables("Rhythm"), 1);
+    }
+
+    #[test]
+    fn test_readability() {
+        let score = readability("Intro\nThe cat sat. The dog ran away!\n");
+        assert_eq!(score.sentences, 3);
+        assert_eq!(score.words, 8);
+        assert_eq!(score.syllables, 10);
+        assert!(score.reading_ease > 90.0);
+        assert!(score.grade_level < 2.0);
+        assert_eq!(readability(""), Readability::default());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::markdown_with_options;
+use crate::options::{HtmlOptions, ProseOptions};
+use gloo_timers::callback::Timeout;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct MarkdownViewProps {
+    pub markdown: String,
+    #[prop_or_default]
+    pub options: HtmlOptions,
+}
+
+// the rendered document, for any yew app to drop in. it only renders again
+// when the markdown or the options change
+#[function_component(MarkdownView)]
+pub fn markdown_view(props: &MarkdownViewProps) -> Html {
+    let html = use_memo(
+        (props.markdown.clone(), props.options.clone()),
+        |(md, options)| render(md, options),
+    );
+    html! {
+        <div class="prose-preview">
+            { Html::from_html_unchecked(AttrValue::from((*html).clone())) }
+        </div>
+    }
+}
+
+// front matter feeds the variables, like `markdown`
+fn render(md: &str, options: &HtmlOptions) -> String {
+    let options = ProseOptions {
+        html: options.clone(),
+        ..ProseOptions::default()
+    };
+    markdown_with_options(md, &options).unwrap_or_default()
+}
+
+// the preview catches up with the editor at most this often, so typing
+// into a long document does not parse it again on every key
+pub const RENDER_INTERVAL_MS: u32 = 150;
+
+pub enum Msg {
+    Edit(String),
+    Render,
+}
+
+// the demo editor, a textarea on the left and the rendered document on the right
+pub struct Model {
+    value: String,
+    // what the preview shows, a little behind `value` while typing
+    rendered: String,
+    pending: Option<Timeout>,
+}
+
+impl Component for Model {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Model {
+            value: String::new(),
+            rendered: String::new(),
+            pending: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            // the textarea already shows the edit, the preview waits for the
+            // timer, which picks up every edit made in the meantime
+            Msg::Edit(value) => {
+                self.value = value;
+                if self.pending.is_none() {
+                    let link = ctx.link().clone();
+                    self.pending = Some(Timeout::new(RENDER_INTERVAL_MS, move || {
+                        link.send_message(Msg::Render)
+                    }));
+                }
+                false
+            }
+            Msg::Render => {
+                self.pending = None;
+                self.rendered = self.value.clone();
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            Msg::Edit(e.target_unchecked_into::<HtmlTextAreaElement>().value())
+        });
+        html! {
+            <div class="prose">
+                <textarea class="prose-editor" value={self.value.clone()} {oninput} />
+                <MarkdownView markdown={self.rendered.clone()} />
+            </div>
+        }
+    }
+}
+
+// mounts the editor on the page's body, `runApp()` from javascript
+#[wasm_bindgen(js_name = runApp)]
+pub fn run_app() {
+    yew::Renderer::<Model>::new().render();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let options = HtmlOptions {
+            number_headings: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render("---\nname: prose\n---\n# {{name}}\n", &options),
+            "<h1><span class=\"heading-number\">1.</span> prose</h1>"
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use crate::assets;
+use crate::batch::{self, write_file, BuildError, BuildOptions, BuildReport};
+use crate::crossref::LinkMap;
+use crate::date::{self, is_iso_date};
+use crate::document::Document;
+use crate::embeds;
+use crate::entities;
+use crate::escape;
+use crate::linkcheck;
+use crate::slug::slugify;
+use crate::toc;
+use crate::translator;
+
+use serde_json::json;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const PAGE_TEMPLATE: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{{ title }} | {{ site_title }}</title>
+</head>
+<body>
+<a class=\"skip-link\" href=\"#content\">Skip to content</a>
+<nav><a href=\"{{ root }}index.html\">{{ site_title }}</a></nav>
+<main id=\"content\">
+{{ content }}
+</main>
+<footer>{{ date }} {{ tags }}</footer>
+</body>
+</html>
+";
+
+const LIST_TEMPLATE: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{{ title }} | {{ site_title }}</title>
+</head>
+<body>
+<a class=\"skip-link\" href=\"#content\">Skip to content</a>
+<nav><a href=\"{{ root }}index.html\">{{ site_title }}</a></nav>
+<main id=\"content\">
+<h1>{{ title }}</h1>
+{{ content }}
+</main>
+</body>
+</html>
+";
+
+// a bare bones template: `{{ name }}` is swapped for the value of `name`,
+// names nobody filled in come out empty
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    source: String,
+}
+
+impl Template {
+    pub fn new(source: &str) -> Template {
+        Template {
+            source: source.to_string(),
+        }
+    }
+
+    pub fn render(&self, vars: &[(&str, &str)]) -> String {
+        let mut out = String::new();
+        let mut rest = self.source.as_str();
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => break,
+            };
+            out.push_str(&rest[..start]);
+            let name = rest[start + 2..end].trim();
+            if let Some((_, value)) = vars.iter().find(|(var, _)| *var == name) {
+                out.push_str(value);
+            }
+            rest = &rest[end + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+// `page.html` wraps every document, `list.html` wraps the index pages
+#[derive(Clone, Debug, PartialEq)]
+pub struct Templates {
+    pub page: Template,
+    pub list: Template,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates {
+            page: Template::new(PAGE_TEMPLATE),
+            list: Template::new(LIST_TEMPLATE),
+        }
+    }
+}
+
+impl Templates {
+    // any template missing from `dir` falls back to the built in one
+    pub fn load(dir: &Path) -> io::Result<Templates> {
+        let read = |name: &str, fallback: &str| match fs::read_to_string(dir.join(name)) {
+            Ok(source) => Ok(Template::new(&source)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Template::new(fallback)),
+            Err(e) => Err(e),
+        };
+        Ok(Templates {
+            page: read("page.html", PAGE_TEMPLATE)?,
+            list: read("list.html", LIST_TEMPLATE)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SiteOptions {
+    pub build: BuildOptions,
+    pub templates: Templates,
+    pub title: String,
+    // where the site is hosted, e.g. `https://example.com/blog/`.
+    // sitemaps need absolute urls, so there is no sitemap without it
+    pub url: Option<String>,
+}
+
+// one rendered document, `path` is relative to the content directory
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    pub path: PathBuf,
+    // where the page is written, relative to the output directory
+    pub output: PathBuf,
+    pub title: String,
+    pub date: Option<String>,
+    pub tags: Vec<String>,
+    pub content: String,
+    // the source file's modification date, `YYYY-MM-DD`
+    pub modified: Option<String>,
+    pub headings: Vec<String>,
+    // the rendered excerpt shown on index pages
+    pub excerpt: String,
+    // the plain text of the document, for searching
+    pub text: String,
+}
+
+impl Page {
+    // renders `doc`, read from `relative` in the `src` directory as `text`,
+    // copying the images it uses into `out` along the way
+    pub fn new(
+        src: &Path,
+        out: &Path,
+        relative: &Path,
+        (text, mut doc): (String, Document),
+        links: &LinkMap,
+        options: &SiteOptions,
+    ) -> Result<Page, BuildError> {
+        let path = src.join(relative);
+        embeds::embed_files(&mut doc.blocks, src, relative);
+        let copied = assets::copy_images(
+            &mut doc.blocks,
+            src,
+            out,
+            relative,
+            options.build.hash_assets,
+            &options.build.image_hook,
+        )?;
+        links.rewrite(&mut doc.blocks, relative);
+        for broken in linkcheck::check_anchors(src, &path, &text) {
+            tracing::warn!(path = %relative.display(), "{}: {}", broken.link.url, broken.reason);
+        }
+        let front_matter = &doc.front_matter;
+        let mut html = options.build.html.with_front_matter(front_matter);
+        html.image_sizes.extend(copied.sizes);
+        Ok(Page {
+            path: relative.to_path_buf(),
+            output: batch::output_file(relative, front_matter),
+            title: match doc.title() {
+                Some(title) => title,
+                None => relative
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            },
+            date: doc.date().map(str::to_string),
+            tags: front_matter
+                .get("tags")
+                .map(|tags| {
+                    tags.as_list()
+                        .into_iter()
+                        .filter_map(|tag| tag.as_str())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            headings: toc::toc(&doc.blocks)
+                .into_iter()
+                .map(|entry| entry.text)
+                .collect(),
+            text: doc.text(),
+            excerpt: translator::translate_with_options(&doc.excerpt(), &html),
+            content: translator::translate_with_options(&doc.blocks, &html),
+            modified: fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(date::iso_date),
+        })
+    }
+
+    // where the page ends up, relative to the output directory, always with `/`
+    pub fn url(&self) -> String {
+        url(&self.output)
+    }
+
+    // `2021-03-04` files under 2021, dates that do not start with a year are ignored
+    pub fn year(&self) -> Option<&str> {
+        self.date
+            .as_deref()
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    // the front matter date when it is a proper `YYYY-MM-DD`, else the file's own
+    pub fn last_modified(&self) -> Option<&str> {
+        self.date
+            .as_deref()
+            .filter(|date| is_iso_date(date))
+            .or(self.modified.as_deref())
+    }
+}
+
+// reads every document in `src` and writes the whole site to `out`:
+// a page per document, `index.html` listing everything newest first,
+// `tags/<tag>.html` per tag and `archive/<year>.html` per year
+pub fn generate(src: &Path, out: &Path, options: &SiteOptions) -> BuildReport {
+    let (relatives, failed) = batch::sources(src, &options.build);
+    let mut report = BuildReport {
+        failed,
+        ..Default::default()
+    };
+
+    let mut docs = vec![];
+    for relative in relatives {
+        match batch::load(src, &relative, &options.build) {
+            Ok(Some(loaded)) => docs.push((relative, loaded)),
+            Ok(None) => report.skipped.push(relative),
+            Err(e) => report.failed.push((relative, e)),
+        }
+    }
+    let links = LinkMap::from_documents(
+        docs.iter()
+            .map(|(relative, (_, doc))| (relative.as_path(), doc)),
+    );
+    let mut pages = vec![];
+    for (relative, loaded) in docs {
+        let _span = tracing::debug_span!("render", path = %relative.display()).entered();
+        tracing::debug!(path = %relative.display(), "rendering");
+        match Page::new(src, out, &relative, loaded, &links, options) {
+            Ok(page) => pages.push(page),
+            Err(e) => report.failed.push((relative, e)),
+        }
+    }
+    // newest first, undated pages last, ties broken by title
+    pages.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.title.cmp(&b.title)));
+
+    for page in &pages {
+        let html = render_page(page, options);
+        match write_file(&out.join(page.url()), &html) {
+            Ok(()) => report.converted.push(page.path.clone()),
+            Err(e) => report.failed.push((page.path.clone(), e)),
+        }
+    }
+
+    let mut lists = vec![(
+        PathBuf::from("index.html"),
+        options.title.clone(),
+        pages.iter().collect(),
+    )];
+    for (tag, tagged) in group(&pages, |page| {
+        page.tags.iter().map(String::as_str).collect()
+    }) {
+        lists.push((
+            PathBuf::from(format!("tags/{}.html", slugify(tag))),
+            format!("Tagged {}", tag),
+            tagged,
+        ));
+    }
+    for (year, dated) in group(&pages, |page| page.year().into_iter().collect()) {
+        lists.push((
+            PathBuf::from(format!("archive/{}.html", year)),
+            year.to_string(),
+            dated,
+        ));
+    }
+    let mut entries = pages
+        .iter()
+        .map(|page| (page.url(), page.last_modified()))
+        .collect::<Vec<_>>();
+    // index pages have no source, so failures are reported against the output path
+    for (path, title, listed) in lists {
+        let html = render_list(&path, &title, &listed, options);
+        // a list changes whenever its newest page does
+        entries.push((
+            url(&path),
+            listed.iter().filter_map(|page| page.last_modified()).max(),
+        ));
+        if let Err(e) = write_file(&out.join(&path), &html) {
+            report.failed.push((path, e));
+        }
+    }
+
+    let path = PathBuf::from("search-index.json");
+    if let Err(e) = write_file(&out.join(&path), &search_index(&pages)) {
+        report.failed.push((path, e));
+    }
+
+    match &options.url {
+        Some(base) => {
+            let path = PathBuf::from("sitemap.xml");
+            if let Err(e) = write_file(&out.join(&path), &sitemap(base, &entries)) {
+                report.failed.push((path, e));
+            }
+        }
+        None => tracing::warn!("no site url given, skipping sitemap.xml"),
+    }
+    report
+}
+
+// `entries` are urls relative to `base` along with when they last changed
+pub fn sitemap(base: &str, entries: &[(String, Option<&str>)]) -> String {
+    let base = base.trim_end_matches('/');
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (url, lastmod) in entries {
+        xml.push_str(&format!("<url><loc>{}/{}</loc>", escape(base), escape(url)));
+        if let Some(lastmod) = lastmod {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", escape(lastmod)));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+// what client side search libraries like lunr or fuse want to be fed,
+// urls are relative to the top of the site and text is plain, `&amp;` is `&`
+pub fn search_index(pages: &[Page]) -> String {
+    let entries = pages
+        .iter()
+        .map(|page| {
+            json!({
+                "title": entities::decode(&page.title),
+                "url": page.url(),
+                "headings": page
+                    .headings
+                    .iter()
+                    .map(|heading| entities::decode(heading))
+                    .collect::<Vec<_>>(),
+                "body": page.text,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string(&entries).unwrap()
+}
+
+fn group<'a>(
+    pages: &'a [Page],
+    keys: impl Fn(&'a Page) -> Vec<&'a str>,
+) -> BTreeMap<&'a str, Vec<&'a Page>> {
+    let mut groups: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in pages {
+        for key in keys(page) {
+            groups.entry(key).or_default().push(page);
+        }
+    }
+    groups
+}
+
+fn render_page(page: &Page, options: &SiteOptions) -> String {
+    let root = root(&page.url());
+    let tags = if page.tags.is_empty() {
+        String::new()
+    } else {
+        let items = page
+            .tags
+            .iter()
+            .map(|tag| {
+                format!(
+                    "<li><a href=\"{}tags/{}.html\">{}</a></li>",
+                    root,
+                    slugify(tag),
+                    escape(tag)
+                )
+            })
+            .collect::<String>();
+        format!("<ul class=\"tags\">{}</ul>", items)
+    };
+    options.templates.page.render(&[
+        ("title", &entities::escape(&page.title)),
+        ("site_title", &entities::escape(&options.title)),
+        ("date", &escape(page.date.as_deref().unwrap_or_default())),
+        ("tags", &tags),
+        ("content", &page.content),
+        ("root", &root),
+    ])
+}
+
+fn render_list(path: &Path, title: &str, pages: &[&Page], options: &SiteOptions) -> String {
+    let root = root(&url(path));
+    let items = pages
+        .iter()
+        .map(|page| {
+            let date = match &page.date {
+                Some(date) => format!(" <time>{}</time>", escape(date)),
+                None => String::new(),
+            };
+            let excerpt = if page.excerpt.is_empty() {
+                String::new()
+            } else {
+                format!("<div class=\"excerpt\">{}</div>", page.excerpt)
+            };
+            format!(
+                "<li><a href=\"{}{}\">{}</a>{}{}</li>",
+                root,
+                page.url(),
+                entities::escape(&page.title),
+                date,
+                excerpt
+            )
+        })
+        .collect::<String>();
+    options.templates.list.render(&[
+        ("title", &escape(title)),
+        ("site_title", &entities::escape(&options.title)),
+        ("content", &format!("<ul class=\"pages\">{}</ul>", items)),
+        ("root", &root),
+    ])
+}
+
+fn url(path: &Path) -> String {
+    path.iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// the way back up to the top of the site from a page, so links work
+// wherever the site is hosted, even straight off the disk
+fn root(url: &str) -> String {
+    "../".repeat(url.matches('/').count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::fixtures::{scratch_dir, write};
+
+    #[test]
+    fn test_template_render() {
+        let template = Template::new("<h1>{{ title }}</h1>{{content}}{{ missing }}{{ open");
+        assert_eq!(
+            template.render(&[("title", "Hi"), ("content", "<p>x</p>")]),
+            "<h1>Hi</h1><p>x</p>{{ open"
+        );
+    }
+
+    #[test]
+    fn test_sitemap() {
+        assert_eq!(
+            sitemap(
+                "https://example.com/",
+                &[
+                    (String::from("index.html"), Some("2021-03-04")),
+                    (String::from("a&b.html"), None)
+                ]
+            ),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n<url><loc>https://example.com/index.html</loc><lastmod>2021-03-04</lastmod></url>\n<url><loc>https://example.com/a&amp;b.html</loc></url>\n</urlset>\n"
+        );
+    }
+
+    #[test]
+    fn test_root() {
+        assert_eq!(root("index.html"), "");
+        assert_eq!(root("posts/2021/first.html"), "../../");
+    }
+
+    #[test]
+    fn test_generate() {
+        let dir = scratch_dir("generate");
+        let (src, out) = (dir.join("content"), dir.join("public"));
+        write(
+            &src.join("posts/old.md"),
+            "---\ntitle: Old <Post>\ndate: 2020-05-01\ntags: [rust]\n---\nold\n",
+        );
+        write(
+            &src.join("posts/new.md"),
+            "---\ntitle: New\ndate: 2021-03-04\ntags: [rust, Web Dev]\n---\nnew\n<!--more-->\nhidden\n",
+        );
+        write(&src.join("about.md"), "# About\n");
+        write(&src.join("broken.md"), "2 * 3\n");
+        write(&src.join("draft.md"), "---\ndraft: true\n---\nsecret\n");
+
+        let options = SiteOptions {
+            title: String::from("My Site"),
+            url: Some(String::from("https://example.com")),
+            ..Default::default()
+        };
+        let report = generate(&src, &out, &options);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, PathBuf::from("broken.md"));
+        assert_eq!(report.converted.len(), 3);
+        assert_eq!(report.skipped, vec![PathBuf::from("draft.md")]);
+
+        let index = fs::read_to_string(out.join("index.html")).unwrap();
+        let (new, old, about) = (
+            index.find("posts/new.html").unwrap(),
+            index.find("posts/old.html").unwrap(),
+            index.find("about.html").unwrap(),
+        );
+        assert!(new < old && old < about);
+        assert!(index.contains("Old &lt;Post&gt;"));
+        assert!(index.contains("<div class=\"excerpt\"><p>new</p></div>"));
+        assert!(!index.contains("hidden"));
+        assert!(!index.contains("draft.html"));
+
+        let page = fs::read_to_string(out.join("posts/new.html")).unwrap();
+        assert!(page.contains("<title>New | My Site</title>"));
+        assert!(page.contains("<a href=\"../index.html\">My Site</a>"));
+        assert!(page.contains("<a href=\"../tags/web-dev.html\">Web Dev</a>"));
+        assert!(page.contains("<p>new</p>"));
+
+        let rust = fs::read_to_string(out.join("tags/rust.html")).unwrap();
+        assert!(rust.contains("<h1>Tagged rust</h1>"));
+        assert!(rust.contains("../posts/old.html"));
+        assert!(!fs::read_to_string(out.join("tags/web-dev.html"))
+            .unwrap()
+            .contains("old.html"));
+        assert!(fs::read_to_string(out.join("archive/2020.html"))
+            .unwrap()
+            .contains("posts/old.html"));
+
+        let sitemap = fs::read_to_string(out.join("sitemap.xml")).unwrap();
+        assert!(sitemap.contains(
+            "<url><loc>https://example.com/posts/new.html</loc><lastmod>2021-03-04</lastmod></url>"
+        ));
+        assert!(sitemap.contains(
+            "<url><loc>https://example.com/tags/rust.html</loc><lastmod>2021-03-04</lastmod></url>"
+        ));
+        assert!(!sitemap.contains("broken"));
+
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(out.join("search-index.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            index[2],
+            json!({"title": "About", "url": "about.html", "headings": ["About"], "body": "About"})
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_templates_load() {
+        let dir = scratch_dir("templates");
+        write(&dir.join("page.html"), "<div>{{ content }}</div>");
+        let templates = Templates::load(&dir).unwrap();
+        assert_eq!(templates.page.render(&[("content", "x")]), "<div>x</div>");
+        assert_eq!(templates.list, Templates::default().list);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+use crate::entities;
+use crate::prelude::*;
+use alloc::collections::BTreeMap;
+
+// github style anchors: lowercase, spaces become dashes, punctuation goes away.
+// `&amp;` and friends count as the character they stand for
+pub fn slugify(text: &str) -> String {
+    entities::decode(text)
+        .trim()
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+// hands out unique slugs for one document, repeats get a counter appended
+#[derive(Clone, Debug, Default)]
+pub struct Slugger {
+    seen: BTreeMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        let mut slug = base.clone();
+        while let Some(count) = self.seen.get_mut(&slug) {
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("  How Do I Run This?  "), "how-do-i-run-this");
+        assert_eq!(slugify("snake_case & kebab-case"), "snake_case--kebab-case");
+        assert_eq!(slugify("Ünïcödé"), "ünïcödé");
+        assert_eq!(slugify("Q&amp;A"), slugify("Q&A"));
+    }
+
+    #[test]
+    fn test_slugger() {
+        let mut slugger = Slugger::default();
+        assert_eq!(slugger.slug("Usage"), "usage");
+        assert_eq!(slugger.slug("Usage"), "usage-1");
+        assert_eq!(slugger.slug("Usage"), "usage-2");
+        assert_eq!(slugger.slug("Usage 1"), "usage-1-1");
+    }
+}
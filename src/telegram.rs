@@ -0,0 +1,251 @@
+use crate::options::HtmlOptions;
+use crate::prelude::*;
+use crate::raw_format;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+
+// telegram's MarkdownV2, for bots sending documents as messages. telegram has
+// no headings, tables or footnotes: headings are bold, tables are lined up in a
+// code block and footnotes are `[1]` markers with their text where it was written
+pub fn translate(md: &[Markdown], options: &HtmlOptions) -> String {
+    let mut out = String::new();
+    for block in md {
+        translate_block(&mut out, block, "", options);
+    }
+    out.trim_end_matches('\n').to_string()
+}
+
+fn translate_block(out: &mut String, block: &Markdown, indent: &str, options: &HtmlOptions) {
+    match block {
+        Markdown::Heading(_, text) => {
+            out.push_str(indent);
+            out.push('*');
+            out.push_str(&escape(&options.decoded(&text.plain_text())));
+            out.push_str("*\n");
+        }
+        Markdown::UnorderedList(items) => {
+            for item in items {
+                translate_item(out, item, indent, "• ", options);
+            }
+        }
+        Markdown::OrderedList(items) => {
+            for (number, item) in items {
+                translate_item(out, item, indent, &format!("{}\\. ", number), options);
+            }
+        }
+        Markdown::Codeblock(lang, code) if raw_format(lang) == Some("telegram") => {
+            out.push_str(code)
+        }
+        Markdown::Codeblock(lang, _) if raw_format(lang).is_some() => {}
+        Markdown::Codeblock(lang, code) => pre(out, lang, code),
+        Markdown::Line(text) => {
+            if !text.is_empty() {
+                out.push_str(indent);
+                translate_text(out, text, options);
+            }
+            out.push('\n');
+        }
+        Markdown::FootnoteDef(label, text) => {
+            out.push_str(indent);
+            out.push_str(&escape(&format!("[{}] ", label)));
+            translate_text(out, text, options);
+            out.push('\n');
+        }
+        Markdown::Table(header, _, rows) => pre(out, "", &table(header, rows, options)),
+        Markdown::Custom(_, text) => {
+            for line in text.lines() {
+                out.push_str(indent);
+                out.push_str(&escape(line));
+                out.push('\n');
+            }
+        }
+        Markdown::Alert(alert, lines) => {
+            out.push_str(&format!(">*{}*\n", alert.title()));
+            for line in lines {
+                out.push('>');
+                translate_text(out, line, options);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn translate_item(
+    out: &mut String,
+    item: &ListItem,
+    indent: &str,
+    marker: &str,
+    options: &HtmlOptions,
+) {
+    out.push_str(indent);
+    out.push_str(marker);
+    translate_text(out, &item.text, options);
+    out.push('\n');
+    let nested = format!("{}  ", indent);
+    for child in &item.children {
+        translate_block(out, child, &nested, options);
+    }
+}
+
+fn translate_text(out: &mut String, text: &[MarkdownInline], options: &HtmlOptions) {
+    for inline in text {
+        match inline {
+            MarkdownInline::Bold(text) => styled(out, "*", &options.decoded(text)),
+            MarkdownInline::Italic(text) => styled(out, "_", &options.decoded(text)),
+            // telegram's underline is the closest it has to `<ins>`
+            MarkdownInline::Inserted(text) => styled(out, "__", &options.decoded(text)),
+            MarkdownInline::InlineCode(code) => {
+                out.push('`');
+                out.push_str(&escape_code(code));
+                out.push('`');
+            }
+            MarkdownInline::Link(text, url) => link(
+                out,
+                &options.decoded(text),
+                &options.href(&options.substitute_url(url)),
+            ),
+            // telegram shows the picture from a link preview, not inside the text
+            MarkdownInline::Image(text, url) => link(
+                out,
+                &options.alt(text, url),
+                &options.src(&options.substitute_url(url)),
+            ),
+            MarkdownInline::Plaintext(text) => out.push_str(&escape(&options.decoded(text))),
+            MarkdownInline::FootnoteRef(label) => out.push_str(&escape(&format!("[{}]", label))),
+            MarkdownInline::Citation(keys) => out.push_str(&escape(&format!(
+                "[{}]",
+                keys.iter()
+                    .map(|key| format!("@{}", key))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ))),
+        }
+    }
+}
+
+fn styled(out: &mut String, marker: &str, text: &str) {
+    out.push_str(marker);
+    out.push_str(&escape(text));
+    out.push_str(marker);
+}
+
+fn link(out: &mut String, text: &str, url: &str) {
+    let text = if text.trim().is_empty() { url } else { text };
+    out.push('[');
+    out.push_str(&escape(text));
+    out.push_str("](");
+    out.push_str(&url.replace('\\', "\\\\").replace(')', "\\)"));
+    out.push(')');
+}
+
+fn pre(out: &mut String, lang: &str, code: &str) {
+    out.push_str("```");
+    if lang != "__UNKNOWN__" {
+        out.push_str(lang);
+    }
+    out.push('\n');
+    out.push_str(&escape_code(code));
+    if !code.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str("```\n");
+}
+
+// the cells lined up in columns, as plain text for a code block
+fn table(header: &[MarkdownText], rows: &[Vec<MarkdownText>], options: &HtmlOptions) -> String {
+    let rows = core::iter::once(header)
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            row.iter()
+                .map(|cell| options.decoded(&cell.plain_text()))
+                .collect::<Vec<String>>()
+        })
+        .collect::<Vec<_>>();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let mut out = String::new();
+    for row in &rows {
+        let cells = widths
+            .iter()
+            .enumerate()
+            .map(|(i, width)| {
+                let cell = row.get(i).map_or("", String::as_str);
+                format!("{}{}", cell, " ".repeat(width - cell.chars().count()))
+            })
+            .collect::<Vec<_>>();
+        out.push_str(cells.join(" | ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+// every character MarkdownV2 gives a meaning to has to be escaped in text,
+// even where it couldn't start anything, or telegram refuses the message
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "_*[]()~`>#+-=|{}.!\\".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// in code only the backtick and backslash are special
+fn escape_code(code: &str) -> String {
+    code.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    fn render(md: &str) -> String {
+        let (_, md) = parse_markdown(md).unwrap();
+        translate(&md, &HtmlOptions::default())
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("1 + 1 = 2. (Really!)"),
+            "1 \\+ 1 \\= 2\\. \\(Really\\!\\)"
+        );
+        assert_eq!(escape_code("a `b` \\c"), "a \\`b\\` \\\\c");
+        let mut out = String::new();
+        link(&mut out, "", "https://e.com/a_(b)");
+        assert_eq!(out, "[https://e\\.com/a\\_\\(b\\)](https://e.com/a_(b\\))");
+    }
+
+    #[test]
+    fn test_translate() {
+        assert_eq!(
+            render("# Hello, world!\nSee **this** and *that* at [docs](https://e.com/a_b).\n\n- `x_y`\n- two\n  1. nested\n"),
+            "*Hello, world\\!*\nSee *this* and _that_ at [docs](https://e.com/a_b)\\.\n\n• `x_y`\n• two\n  1\\. nested"
+        );
+        assert_eq!(
+            render("> [!NOTE]\n> Q&amp;A\n```rust\nlet x = '\\';\n```\n```\nplain\n```\n"),
+            ">*Note*\n>Q&A\n```rust\nlet x = '\\\\';\n```\n\n```\nplain\n```"
+        );
+        assert_eq!(
+            render("| a | bb |\n|---|---|\n| 1 | 2 |\n"),
+            "```\na | bb\n1 | 2\n```"
+        );
+        assert_eq!(
+            render("text[^1]\n[^1]: note.\n"),
+            "text\\[1\\]\n\\[1\\] note\\."
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use crate::prelude::*;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use crate::Url;
+
+// reads textile, the way redmine and older wikis write it, into the same
+// blocks the markdown parser produces. markup with no markdown counterpart
+// (block quotes, deleted text, css attributes) is kept as plain text
+pub fn parse(src: &str) -> Vec<Markdown> {
+    let lines = src.lines().collect::<Vec<&str>>();
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim().is_empty() {
+            blocks.push(Markdown::Line(MarkdownText::new()));
+            i += 1;
+        } else if line.trim_start().starts_with("<pre>") {
+            i = pre(&lines, i, &mut blocks);
+        } else if let Some((tag, extended, text)) = signature(line) {
+            i += 1;
+            match tag {
+                "bc" | "pre" => {
+                    let mut code = vec![text];
+                    while i < lines.len() && !ends_code(lines[i], extended) {
+                        code.push(lines[i]);
+                        i += 1;
+                    }
+                    // an extended block keeps its blank lines, but not the ones it ends on
+                    while code.len() > 1 && code.last().is_some_and(|l| l.trim().is_empty()) {
+                        code.pop();
+                        i -= 1;
+                    }
+                    blocks.push(Markdown::Codeblock(String::new(), code.join("\n") + "\n"));
+                }
+                _ => blocks.push(block(tag, text)),
+            }
+        } else if list_item(line).is_some() {
+            let start = i;
+            while i < lines.len() && list_item(lines[i]).is_some() {
+                i += 1;
+            }
+            let items = lines[start..i]
+                .iter()
+                .filter_map(|line| list_item(line))
+                .map(|(markers, text)| (markers, inline(text)))
+                .collect::<Vec<_>>();
+            blocks.extend(list(&items));
+        } else if is_row(line) {
+            let start = i;
+            while i < lines.len() && is_row(lines[i]) {
+                i += 1;
+            }
+            blocks.push(table(&lines[start..i]));
+        } else {
+            blocks.push(Markdown::Line(inline(line)));
+            i += 1;
+        }
+    }
+    blocks
+}
+
+// a block signature like `h2. `, `p(intro). ` or `bc.. `, split into the tag,
+// whether it runs past blank lines, and the text after it
+fn signature(line: &str) -> Option<(&str, bool, &str)> {
+    let tag_end = line
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(line.len());
+    let tag = &line[..tag_end];
+    let known = matches!(
+        tag,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "bq" | "bc" | "pre"
+    ) || (tag.len() > 2
+        && tag.starts_with("fn")
+        && tag[2..].chars().all(|c| c.is_ascii_digit()));
+    if !known {
+        return None;
+    }
+    let rest = skip_attributes(&line[tag_end..]);
+    if let Some(text) = rest.strip_prefix(".. ") {
+        Some((tag, true, text))
+    } else {
+        rest.strip_prefix(". ").map(|text| (tag, false, text))
+    }
+}
+
+// `(class#id)`, `{color: red}`, `[en]` and the `<>=()` alignment marks
+fn skip_attributes(mut rest: &str) -> &str {
+    loop {
+        let close = match rest.chars().next() {
+            Some('(') => ')',
+            Some('{') => '}',
+            Some('[') => ']',
+            Some('<') | Some('>') | Some('=') | Some(')') => {
+                rest = &rest[1..];
+                continue;
+            }
+            _ => return rest,
+        };
+        match rest.find(close) {
+            Some(end) => rest = &rest[end + 1..],
+            None => return rest,
+        }
+    }
+}
+
+// a plain code block ends at a blank line, an extended one at the next block
+fn ends_code(line: &str, extended: bool) -> bool {
+    if extended {
+        signature(line).is_some()
+    } else {
+        line.trim().is_empty()
+    }
+}
+
+fn block(tag: &str, text: &str) -> Markdown {
+    match tag {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => Markdown::Heading(
+            HeadingLevel::ALL[usize::from(tag.as_bytes()[1] - b'1')],
+            inline(text),
+        ),
+        _ if tag.starts_with("fn") => Markdown::FootnoteDef(tag[2..].to_string(), inline(text)),
+        _ => Markdown::Line(inline(text)),
+    }
+}
+
+// redmine's `<pre>` blocks, with the language on an inner `<code class="ruby">`
+fn pre(lines: &[&str], start: usize, blocks: &mut Vec<Markdown>) -> usize {
+    let mut body = lines[start].trim_start()["<pre>".len()..].to_string();
+    let mut i = start + 1;
+    while !body.contains("</pre>") && i < lines.len() {
+        body.push('\n');
+        body.push_str(lines[i]);
+        i += 1;
+    }
+    let mut code = body.split("</pre>").next().unwrap_or("");
+    let mut lang = "";
+    if let Some(open) = code.strip_prefix("<code") {
+        if let Some(end) = open.find('>') {
+            lang = open[..end]
+                .split("class=\"")
+                .nth(1)
+                .and_then(|class| class.split('"').next())
+                .unwrap_or("");
+            code = open[end + 1..].trim_end_matches("</code>");
+        }
+    }
+    let code = code.trim_start_matches('\n');
+    let code = if code.ends_with('\n') {
+        code.to_string()
+    } else {
+        format!("{}\n", code)
+    };
+    blocks.push(Markdown::Codeblock(lang.to_string(), code));
+    i
+}
+
+// `** nested` or `# numbered`, the markers and the text after them
+fn list_item(line: &str) -> Option<(&str, &str)> {
+    let end = line.find(|c| c != '*' && c != '#')?;
+    if end == 0 {
+        return None;
+    }
+    line[end..]
+        .strip_prefix(' ')
+        .map(|text| (&line[..end], text))
+}
+
+// items by their `*` and `#` markers, which mediawiki writes the same way.
+// deeper items belong to the item above them, each run of one kind is a list
+pub(crate) fn list(items: &[(&str, MarkdownText)]) -> Vec<Markdown> {
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < items.len() {
+        let depth = items[i].0.len();
+        let ordered = items[i].0.ends_with('#');
+        let mut entries = vec![];
+        while i < items.len() && items[i].0.len() == depth && items[i].0.ends_with('#') == ordered {
+            let text = items[i].1.clone();
+            i += 1;
+            let start = i;
+            while i < items.len() && items[i].0.len() > depth {
+                i += 1;
+            }
+            entries.push(ListItem {
+                text,
+                children: list(&items[start..i]),
+            });
+        }
+        blocks.push(if ordered {
+            Markdown::OrderedList(
+                entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(n, item)| (n + 1, item))
+                    .collect(),
+            )
+        } else {
+            Markdown::UnorderedList(entries)
+        });
+    }
+    blocks
+}
+
+fn is_row(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 1 && line.starts_with('|') && line.ends_with('|')
+}
+
+// the first row is the header, `_.` marks or not. its `<.`, `=.` and `>.`
+// marks align the columns
+fn table(lines: &[&str]) -> Markdown {
+    let mut rows = lines.iter().map(|line| {
+        let line = line.trim();
+        line[1..line.len() - 1]
+            .split('|')
+            .map(cell)
+            .collect::<Vec<(Align, MarkdownText)>>()
+    });
+    let header = rows.next().unwrap_or_default();
+    let aligns = header
+        .iter()
+        .map(|(align, _)| *align)
+        .collect::<Vec<Align>>();
+    let rows = rows
+        .map(|row| {
+            let mut row = row.into_iter().map(|(_, text)| text).collect::<Vec<_>>();
+            row.resize(aligns.len(), MarkdownText::new());
+            row
+        })
+        .collect();
+    Markdown::Table(
+        header.into_iter().map(|(_, text)| text).collect(),
+        aligns,
+        rows,
+    )
+}
+
+fn cell(cell: &str) -> (Align, MarkdownText) {
+    let cell = cell.trim();
+    let marks = cell
+        .find(|c| !matches!(c, '_' | '<' | '>' | '=' | '^' | '~'))
+        .unwrap_or(cell.len());
+    match cell[marks..].strip_prefix(". ") {
+        Some(text) if marks > 0 => {
+            let marks = &cell[..marks];
+            let align = if marks.contains("<>") {
+                Align::Default
+            } else if marks.contains('<') {
+                Align::Left
+            } else if marks.contains('>') {
+                Align::Right
+            } else if marks.contains('=') {
+                Align::Center
+            } else {
+                Align::Default
+            };
+            (align, inline(text.trim()))
+        }
+        _ => (Align::Default, inline(cell)),
+    }
+}
+
+// `*strong*`, `_emphasis_`, `@code@`, `"text":url`, `!image.png(alt)!` and
+// `[1]` footnote references
+fn inline(text: &str) -> MarkdownText {
+    let mut parts = MarkdownText::new();
+    let mut plain = String::new();
+    let mut rest = text;
+    let mut boundary = true;
+    while let Some(c) = rest.chars().next() {
+        if let Some((part, after)) = phrase(rest, boundary) {
+            if !plain.is_empty() {
+                parts.push(MarkdownInline::Plaintext(core::mem::take(&mut plain)));
+            }
+            parts.push(part);
+            rest = after;
+            boundary = false;
+            continue;
+        }
+        plain.push(c);
+        boundary = !c.is_alphanumeric();
+        rest = &rest[c.len_utf8()..];
+    }
+    if !plain.is_empty() {
+        parts.push(MarkdownInline::Plaintext(plain));
+    }
+    parts
+}
+
+fn phrase(rest: &str, boundary: bool) -> Option<(MarkdownInline, &str)> {
+    if rest.starts_with('[') {
+        let end = rest.find(']')?;
+        let label = &rest[1..end];
+        if !label.is_empty() && label.chars().all(|c| c.is_ascii_digit()) {
+            return Some((
+                MarkdownInline::FootnoteRef(label.to_string()),
+                &rest[end + 1..],
+            ));
+        }
+    }
+    if !boundary {
+        return None;
+    }
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let end = quoted.find("\":")?;
+        let after = &quoted[end + 2..];
+        let url_end = after.find(char::is_whitespace).unwrap_or(after.len());
+        let url = after[..url_end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
+        if url.is_empty() {
+            return None;
+        }
+        return Some((
+            MarkdownInline::Link(quoted[..end].to_string(), Url::from(url)),
+            &after[url.len()..],
+        ));
+    }
+    if let Some(image) = rest.strip_prefix('!') {
+        let end = image.find('!')?;
+        let body = &image[..end];
+        if body.is_empty() || body.contains(char::is_whitespace) && !body.contains('(') {
+            return None;
+        }
+        let (src, alt) = match body.find('(') {
+            Some(open) => (&body[..open], body[open + 1..].trim_end_matches(')')),
+            None => (body, ""),
+        };
+        return Some((
+            MarkdownInline::Image(alt.to_string(), Url::from(src)),
+            &image[end + 1..],
+        ));
+    }
+    for (delimiter, kind) in [
+        ("**", MarkdownInline::Bold as fn(String) -> MarkdownInline),
+        ("__", MarkdownInline::Italic),
+        ("*", MarkdownInline::Bold),
+        ("_", MarkdownInline::Italic),
+        ("+", MarkdownInline::Inserted),
+        ("@", MarkdownInline::InlineCode),
+    ] {
+        if let Some(inner) = rest.strip_prefix(delimiter) {
+            if let Some(end) = closing(inner, delimiter) {
+                return Some((
+                    kind(inner[..end].to_string()),
+                    &inner[end + delimiter.len()..],
+                ));
+            }
+            return None;
+        }
+    }
+    None
+}
+
+// where the phrase ends: text on both sides of it and no letter right after
+fn closing(inner: &str, delimiter: &str) -> Option<usize> {
+    if inner.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut from = 0;
+    while let Some(at) = inner[from..].find(delimiter) {
+        let end = from + at;
+        let after = inner[end + delimiter.len()..].chars().next();
+        if end > 0
+            && !inner[..end].ends_with(char::is_whitespace)
+            && !after.is_some_and(char::is_alphanumeric)
+        {
+            return Some(end);
+        }
+        from = end + delimiter.len();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> MarkdownInline {
+        MarkdownInline::Plaintext(String::from(s))
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            parse("h2(intro). Hello\n\np. some text\nfn1. a note\n"),
+            vec![
+                Markdown::Heading(HeadingLevel::H2, vec![text("Hello")].into()),
+                Markdown::Line(vec![].into()),
+                Markdown::Line(vec![text("some text")].into()),
+                Markdown::FootnoteDef(String::from("1"), vec![text("a note")].into()),
+            ]
+        );
+        assert_eq!(
+            parse("bc. let x;\nlet y;\n\nafter\n"),
+            vec![
+                Markdown::Codeblock(String::new(), String::from("let x;\nlet y;\n")),
+                Markdown::Line(vec![].into()),
+                Markdown::Line(vec![text("after")].into()),
+            ]
+        );
+        assert_eq!(
+            parse("bc.. one\n\ntwo\n\np. after\n")[0],
+            Markdown::Codeblock(String::new(), String::from("one\n\ntwo\n")),
+        );
+        assert_eq!(
+            parse("<pre><code class=\"ruby\">\nputs 1\n</code></pre>\n"),
+            vec![Markdown::Codeblock(
+                String::from("ruby"),
+                String::from("puts 1\n")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(
+            inline("a *b* _c_ @d@ \"site\":https://x.com. !cat.png(a cat)! x[1] snake_case_name"),
+            vec![
+                text("a "),
+                MarkdownInline::Bold(String::from("b")),
+                text(" "),
+                MarkdownInline::Italic(String::from("c")),
+                text(" "),
+                MarkdownInline::InlineCode(String::from("d")),
+                text(" "),
+                MarkdownInline::Link(String::from("site"), Url::from("https://x.com")),
+                text(". "),
+                MarkdownInline::Image(String::from("a cat"), Url::from("cat.png")),
+                text(" x"),
+                MarkdownInline::FootnoteRef(String::from("1")),
+                text(" snake_case_name"),
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            parse("* one\n** two\n## three\n* four\n# five\n"),
+            vec![
+                Markdown::UnorderedList(vec![
+                    ListItem {
+                        text: vec![text("one")].into(),
+                        children: vec![
+                            Markdown::UnorderedList(vec![ListItem::from(vec![text("two")])]),
+                            Markdown::OrderedList(vec![(1, ListItem::from(vec![text("three")]))]),
+                        ]
+                    },
+                    ListItem::from(vec![text("four")]),
+                ]),
+                Markdown::OrderedList(vec![(1, ListItem::from(vec![text("five")]))]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            parse("|_. name |_>. count |\n| a | 1 |\n| b |\n"),
+            vec![Markdown::Table(
+                vec![vec![text("name")].into(), vec![text("count")].into()],
+                vec![Align::Default, Align::Right],
+                vec![
+                    vec![vec![text("a")].into(), vec![text("1")].into()],
+                    vec![vec![text("b")].into(), vec![].into()],
+                ]
+            )]
+        );
+    }
+}
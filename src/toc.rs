@@ -0,0 +1,186 @@
+use crate::prelude::*;
+use crate::slug::Slugger;
+use crate::Markdown;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TocEntry {
+    pub level: usize,
+    pub text: String,
+    pub slug: String,
+}
+
+pub fn toc(md: &[Markdown]) -> Vec<TocEntry> {
+    let mut slugger = Slugger::default();
+    md.iter()
+        .filter_map(|block| match block {
+            Markdown::Heading(level, text) => {
+                let text = text.plain_text();
+                Some(TocEntry {
+                    level: level.get(),
+                    slug: slugger.slug(&text),
+                    text,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// hierarchical numbers for the entries, `1.`, `1.1`, `1.1.1`, counting from
+// the shallowest heading in the document. a skipped level counts as 0, `1.0.1`
+pub fn numbers(entries: &[TocEntry]) -> Vec<String> {
+    let top = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+    let mut counts: Vec<usize> = vec![];
+    entries
+        .iter()
+        .map(|entry| {
+            let depth = entry.level - top + 1;
+            counts.resize(depth, 0);
+            counts[depth - 1] += 1;
+            match counts.as_slice() {
+                [only] => format!("{}.", only),
+                counts => counts
+                    .iter()
+                    .map(|count| count.to_string())
+                    .collect::<Vec<String>>()
+                    .join("."),
+            }
+        })
+        .collect()
+}
+
+// renders the outline as a nested markdown list of links,
+// the shallowest heading in the document sits at the left margin
+pub fn to_markdown(entries: &[TocEntry]) -> String {
+    let top = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+    entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{}- [{}](#{})\n",
+                "  ".repeat(entry.level - top),
+                entry.text,
+                entry.slug
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+// the outline as a collapsible widget, `<details>` holding lists nested as
+// deep as the headings are. a skipped level gets an item of its own to hold
+// the list, so the html stays valid
+pub fn to_html(entries: &[TocEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let top = entries.iter().map(|entry| entry.level).min().unwrap_or(1);
+    let mut out = String::from("<details class=\"toc\"><summary>Contents</summary>");
+    // how many lists are open, every one of them inside an open item but the first
+    let mut open = 0;
+    for entry in entries {
+        let depth = entry.level - top + 1;
+        if depth > open {
+            while open < depth {
+                out.push_str("<ul>");
+                open += 1;
+                if open < depth {
+                    out.push_str("<li>");
+                }
+            }
+        } else {
+            out.push_str("</li>");
+            while open > depth {
+                out.push_str("</ul></li>");
+                open -= 1;
+            }
+        }
+        out.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>",
+            entry.slug, entry.text
+        ));
+    }
+    out.push_str("</li>");
+    while open > 0 {
+        out.push_str("</ul>");
+        open -= 1;
+        if open > 0 {
+            out.push_str("</li>");
+        }
+    }
+    out.push_str("</details>");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    #[test]
+    fn test_toc() {
+        let (_, md) =
+            parse_markdown("intro\n## Install\n### With *pip*\n## Usage\n### With *pip*\n")
+                .unwrap();
+        assert_eq!(
+            toc(&md),
+            vec![
+                TocEntry {
+                    level: 2,
+                    text: String::from("Install"),
+                    slug: String::from("install")
+                },
+                TocEntry {
+                    level: 3,
+                    text: String::from("With pip"),
+                    slug: String::from("with-pip")
+                },
+                TocEntry {
+                    level: 2,
+                    text: String::from("Usage"),
+                    slug: String::from("usage")
+                },
+                TocEntry {
+                    level: 3,
+                    text: String::from("With pip"),
+                    slug: String::from("with-pip-1")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let (_, md) = parse_markdown("## Install\n### With pip\n## Usage\n").unwrap();
+        assert_eq!(
+            to_markdown(&toc(&md)),
+            String::from("- [Install](#install)\n  - [With pip](#with-pip)\n- [Usage](#usage)\n")
+        );
+        assert_eq!(to_markdown(&[]), String::from(""));
+    }
+
+    #[test]
+    fn test_to_html() {
+        let (_, md) = parse_markdown("## Install\n### With pip\n## Usage\n").unwrap();
+        assert_eq!(
+            to_html(&toc(&md)),
+            "<details class=\"toc\"><summary>Contents</summary><ul><li><a href=\"#install\">Install</a><ul><li><a href=\"#with-pip\">With pip</a></li></ul></li><li><a href=\"#usage\">Usage</a></li></ul></details>"
+        );
+        let (_, md) = parse_markdown("# Top\n### Deep\n# Next\n").unwrap();
+        assert_eq!(
+            to_html(&toc(&md)),
+            "<details class=\"toc\"><summary>Contents</summary><ul><li><a href=\"#top\">Top</a><ul><li><ul><li><a href=\"#deep\">Deep</a></li></ul></li></ul></li><li><a href=\"#next\">Next</a></li></ul></details>"
+        );
+        assert_eq!(to_html(&[]), "");
+    }
+
+    #[test]
+    fn test_numbers() {
+        let (_, md) =
+            parse_markdown("## Intro\n### Why\n### How\n## Usage\n#### Deep\n### Next\n").unwrap();
+        assert_eq!(
+            numbers(&toc(&md)),
+            vec!["1.", "1.1", "1.2", "2.", "2.0.1", "2.1"]
+        );
+    }
+}
@@ -1,101 +1,1084 @@
+use crate::csv;
+use crate::escape;
+use crate::options::{HtmlOptions, PermalinkPosition};
+use crate::prelude::*;
+use crate::raw_format;
+use crate::slug::slugify;
+use crate::toc;
+use crate::Alert;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
 use crate::Markdown;
 use crate::MarkdownInline;
 use crate::MarkdownText;
+use crate::Span;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use core::fmt::{self, Write};
 
-pub fn translate(md: Vec<Markdown>) -> String {
-    md.iter()
-        .map(|bit| match bit {
-            Markdown::Heading(size, line) => translate_header(*size, line.to_vec()),
-            Markdown::UnorderedList(lines) => translate_unordered_list(lines.to_vec()),
-            Markdown::OrderedList(lines) => translate_ordered_list(lines.to_vec()),
-            Markdown::Codeblock(lang, code) => {
-                translate_codeblock(lang.to_string(), code.to_string())
-            }
-            Markdown::Line(line) => translate_line(line.to_vec()),
+pub fn translate(md: &[Markdown]) -> String {
+    translate_with_options(md, &HtmlOptions::default())
+}
+
+// everything is written into one buffer as it goes rather than built up
+// from little strings and joined, which on big documents is most of the work
+pub fn translate_with_options(md: &[Markdown], options: &HtmlOptions) -> String {
+    let _span = tracing::debug_span!("translate", blocks = md.len()).entered();
+    let mut out = String::with_capacity(capacity(md));
+    let mut notes = Notes::new(md, options);
+    // writing to a string never fails
+    translate_document(&mut out, md, None, None, options, &mut notes).expect("write to a string");
+    options.filters.apply(out)
+}
+
+// the html of `translate_with_options` handed to `chunk` a piece at a time,
+// each top-level block as soon as it's written, so servers can stream long
+// documents. filters need the html of the whole document, so with any set
+// it all comes in one piece at the end
+pub fn translate_streaming(md: &[Markdown], options: &HtmlOptions, mut chunk: impl FnMut(&str)) {
+    if !options.filters.is_empty() {
+        chunk(&translate_with_options(md, options));
+        return;
+    }
+    let mut out = String::new();
+    let mut notes = Notes::new(md, options);
+    translate_document(&mut out, md, None, Some(&mut chunk), options, &mut notes)
+        .expect("write to a string");
+    if !out.is_empty() {
+        chunk(&out);
+    }
+}
+
+// like `translate_with_options`, with every top-level element carrying a
+// `data-sourcepos="line:column"` attribute saying where its block starts in
+// `src`, so previews can scroll along with the editor. `spans` line up with
+// `md`, as handed out by `document::spanned_blocks`
+pub fn translate_with_source_positions(
+    md: &[Markdown],
+    spans: &[Span],
+    src: &str,
+    options: &HtmlOptions,
+) -> String {
+    let _span = tracing::debug_span!("translate", blocks = md.len()).entered();
+    let mut out = String::with_capacity(capacity(md) + md.len() * 24);
+    let mut notes = Notes::new(md, options);
+    translate_document(&mut out, md, Some((spans, src)), None, options, &mut notes)
+        .expect("write to a string");
+    options.filters.apply(out)
+}
+
+// the same html as `translate_with_options`, with runs of top-level blocks
+// rendered on rayon's thread pool. footnotes, citations and heading numbers
+// are counted through the whole document, so documents using them are
+// rendered in one go as usual
+#[cfg(feature = "parallel")]
+pub fn translate_parallel(md: &[Markdown], options: &HtmlOptions) -> String {
+    use rayon::prelude::*;
+
+    if !independent(md, options) {
+        return translate_with_options(md, options);
+    }
+    let html = md
+        .par_chunks(PARALLEL_CHUNK)
+        .map(|chunk| {
+            let mut out = String::with_capacity(capacity(chunk));
+            let mut notes = Notes::default();
+            for bit in chunk {
+                translate_block(&mut out, bit, options, &mut notes).expect("write to a string");
+            }
+            out
         })
         .collect::<Vec<String>>()
-        .join("")
+        .concat();
+    options.filters.apply(
+        match direction(options.lang.as_deref(), options.dir.as_deref()) {
+            direction if direction.is_empty() => html,
+            direction => format!("<div{}>{}</div>", direction, html),
+        },
+    )
+}
+
+// enough blocks per task that handing them out costs less than rendering them
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK: usize = 256;
+
+// whether blocks render the same no matter what came before them
+#[cfg(feature = "parallel")]
+fn independent(md: &[Markdown], options: &HtmlOptions) -> bool {
+    !options.number_headings
+        && !options.link_appendix
+        && !options.alt_from_filename
+        && !options.hard_breaks
+        && options.permalinks.is_none()
+        && md.iter().all(|block| {
+            !matches!(block, Markdown::FootnoteDef(_, _))
+                && (options.bibliography.is_empty()
+                    || !block
+                        .texts()
+                        .into_iter()
+                        .flatten()
+                        .any(|inline| matches!(inline, MarkdownInline::Citation(_))))
+        })
+}
+
+fn translate_document(
+    out: &mut String,
+    md: &[Markdown],
+    sources: Option<(&[Span], &str)>,
+    mut chunk: Option<&mut dyn FnMut(&str)>,
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    let direction = direction(options.lang.as_deref(), options.dir.as_deref());
+    if !direction.is_empty() {
+        write!(out, "<div{}>", direction)?;
+    }
+    let mut lines = Lines::default();
+    // whether the last block was a line of text, which the next can join
+    let mut paragraph = false;
+    for (i, bit) in md.iter().enumerate() {
+        let start = out.len();
+        translate_block(out, bit, options, notes)?;
+        let line = matches!(bit, Markdown::Line(_)) && out[start..].starts_with("<p");
+        if options.hard_breaks && line && paragraph && join_paragraph(out, start) {
+            continue;
+        }
+        paragraph = line;
+        // blocks that weren't in the source, like embedded notes, have no span
+        let span = sources.and_then(|(spans, src)| Some((spans.get(i)?, src)));
+        // raw html is the author's own, and anything not starting with a tag
+        // has nowhere to put an attribute
+        let raw = matches!(bit, Markdown::Codeblock(lang, _) if raw_format(lang).is_some());
+        if let (Some((span, src)), false, true) = (span, raw, out[start..].starts_with('<')) {
+            let (line, column) = lines.position(src, span.start);
+            // straight after the tag name of the block's first element
+            let at = out[start + 1..]
+                .find([' ', '>'])
+                .map_or(out.len(), |end| start + 1 + end);
+            out.insert_str(at, &format!(" data-sourcepos=\"{}:{}\"", line, column));
+        }
+        // a paragraph the next line could still join is held back
+        if let (Some(chunk), false) = (chunk.as_mut(), options.hard_breaks && paragraph) {
+            if !out.is_empty() {
+                chunk(out);
+                out.clear();
+            }
+        }
+    }
+    // footnotes can cite works too, so they go first, into a buffer of their own
+    let mut footnotes = String::new();
+    translate_footnotes(&mut footnotes, options, notes)?;
+    translate_references(out, options, notes)?;
+    out.push_str(&footnotes);
+    // last, as footnotes can have links of their own
+    translate_link_appendix(out, notes)?;
+    if !notes.made_up_alt.is_empty() {
+        tracing::warn!(
+            "images without alt text, it was made from their file names: {}",
+            notes.made_up_alt.join(", ")
+        );
+    }
+    if !direction.is_empty() {
+        out.push_str("</div>");
+    }
+    Ok(())
+}
+
+// moves the `<p>` written from `start` on into the one before it, after a
+// `<br />`. lines with attributes of their own stay paragraphs of their own
+fn join_paragraph(out: &mut String, start: usize) -> bool {
+    let (before, new) = out.split_at(start);
+    if !before.ends_with("</p>") || !new.starts_with("<p>") || !new.ends_with("</p>") {
+        return false;
+    }
+    let inner = new["<p>".len()..new.len() - "</p>".len()].to_string();
+    out.truncate(start - "</p>".len());
+    out.push_str("<br />");
+    out.push_str(&inner);
+    out.push_str("</p>");
+    true
+}
+
+// ` lang="he" dir="rtl"`, leaving out anything that is not a language tag or a direction
+fn direction(lang: Option<&str>, dir: Option<&str>) -> String {
+    let mut attributes = String::new();
+    if let Some(lang) = lang.filter(|lang| {
+        !lang.is_empty() && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }) {
+        attributes.push_str(&format!(" lang=\"{}\"", lang));
+    }
+    if let Some(dir) = dir.filter(|dir| matches!(*dir, "ltr" | "rtl" | "auto")) {
+        attributes.push_str(&format!(" dir=\"{}\"", dir));
+    }
+    attributes
+}
+
+// a paragraph ending in `{dir=rtl}`, `{lang=he}` or both sets its own direction,
+// and `{data-step="2"}` hands scripts whatever they need.
+// gives back the text without the braces and the attributes they stand for
+fn paragraph_attributes(text: &[MarkdownInline]) -> Option<(Vec<MarkdownInline>, String)> {
+    let (last, rest) = text.split_last()?;
+    let last = match last {
+        MarkdownInline::Plaintext(last) => last.trim_end(),
+        _ => return None,
+    };
+    let open = last.rfind('{')?;
+    let inner = last[open + 1..].strip_suffix('}')?;
+    let (mut lang, mut dir, mut data) = (None, None, String::new());
+    for (name, value) in settings(inner)? {
+        match name {
+            "lang" => lang = Some(value),
+            "dir" => dir = Some(value),
+            name if name.starts_with("data-") => {
+                if let Some(name) = data_attribute(name) {
+                    data.push_str(&format!(" {}=\"{}\"", name, escape(value)));
+                }
+            }
+            _ => return None,
+        }
+    }
+    let attributes = direction(lang, dir) + &data;
+    if attributes.is_empty() {
+        return None;
+    }
+    let mut text = rest.to_vec();
+    let before = last[..open].trim_end();
+    if !before.is_empty() {
+        text.push(MarkdownInline::Plaintext(before.to_string()));
+    }
+    Some((text, attributes))
+}
+
+// `dir=rtl data-note="two words"` as names and values, none unless it is all `name=value`
+fn settings(mut inner: &str) -> Option<Vec<(&str, &str)>> {
+    let mut settings = vec![];
+    loop {
+        inner = inner.trim_start();
+        if inner.is_empty() {
+            return Some(settings);
+        }
+        let (name, rest) = inner.split_once('=')?;
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return None;
+        }
+        let (value, rest) = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len())),
+        };
+        settings.push((name, value));
+        inner = rest;
+    }
+}
+
+// the name as browsers would keep it in `dataset`, lowercase letters, digits,
+// `-`, `_` and `.` after the `data-`. anything else could break out of the tag
+fn data_attribute(name: &str) -> Option<String> {
+    let name = name.to_ascii_lowercase();
+    let rest = name.strip_prefix("data-")?;
+    if rest.is_empty()
+        || !rest
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'))
+    {
+        return None;
+    }
+    Some(name)
+}
+
+// finds the line and column of offsets handed to it in increasing order,
+// without counting from the top of the source every time
+#[derive(Default)]
+struct Lines {
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl Lines {
+    fn position(&mut self, src: &str, offset: usize) -> (usize, usize) {
+        if offset < self.offset {
+            *self = Lines::default();
+        }
+        for (i, _) in src[self.offset..offset].match_indices('\n') {
+            self.line += 1;
+            self.line_start = self.offset + i + 1;
+        }
+        self.offset = offset;
+        (
+            self.line + 1,
+            src[self.line_start..offset].chars().count() + 1,
+        )
+    }
+}
+
+// a guess at how long the html will be: the text plus a bit of markup per block
+fn capacity(md: &[Markdown]) -> usize {
+    md.iter()
+        .map(|block| {
+            let text = match block {
+                Markdown::Codeblock(lang, code) => lang.len() + code.len(),
+                block => block
+                    .texts()
+                    .into_iter()
+                    .flatten()
+                    .map(|inline| match inline {
+                        MarkdownInline::Link(text, url) | MarkdownInline::Image(text, url) => {
+                            text.len() + url.len()
+                        }
+                        MarkdownInline::InlineCode(text)
+                        | MarkdownInline::Bold(text)
+                        | MarkdownInline::Italic(text)
+                        | MarkdownInline::Inserted(text)
+                        | MarkdownInline::Plaintext(text)
+                        | MarkdownInline::FootnoteRef(text) => text.len(),
+                        MarkdownInline::Citation(keys) => keys.len() * 32,
+                    })
+                    .sum(),
+            };
+            text + text / 4 + 16
+        })
+        .sum()
 }
 
-fn translate_boldtext(boldtext: String) -> String {
-    format!("<b>{}</b>", boldtext)
+fn translate_block(
+    out: &mut String,
+    bit: &Markdown,
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    if let Some(html) = options.overrides.block(bit, options) {
+        out.push_str(&html);
+        return Ok(());
+    }
+    match bit {
+        Markdown::Heading(size, line) => translate_header(out, *size, line, options, notes),
+        Markdown::UnorderedList(lines) => translate_unordered_list(out, lines, options, notes),
+        Markdown::OrderedList(lines) => translate_ordered_list(out, lines, options, notes),
+        Markdown::Codeblock(lang, code) if raw_format(lang).is_some() => {
+            if raw_format(lang) == Some("html") {
+                out.push_str(code);
+            }
+            Ok(())
+        }
+        Markdown::Codeblock(lang, code) if options.csv_tables && is_csv(lang) => {
+            translate_csv(out, lang, code, options, notes)
+        }
+        Markdown::Codeblock(lang, code) => translate_codeblock(out, lang, code, options),
+        Markdown::Line(line) => translate_line(out, line, options, notes),
+        Markdown::Table(header, aligns, rows) => {
+            translate_table(out, header, aligns, rows, options, notes)
+        }
+        // definitions are gathered up at the end of the document
+        Markdown::FootnoteDef(_, _) => Ok(()),
+        Markdown::Custom(name, text) => translate_custom(out, name, text),
+        Markdown::Alert(alert, lines) => translate_alert(out, *alert, lines, options, notes),
+    }
 }
 
-fn translate_italic(italic: String) -> String {
-    format!("<i>{}</i>", italic)
+// what the translator picks up along the way and lists at the end of the document.
+// footnotes are numbered in the order they are first referenced, like github
+// and pandoc do. ids come from the labels so links to them survive edits
+#[derive(Default)]
+struct Notes<'a> {
+    definitions: BTreeMap<&'a str, &'a MarkdownText>,
+    // labels in the order they were first referenced
+    order: Vec<String>,
+    // how many times each label has been referenced so far
+    references: BTreeMap<String, usize>,
+    // bibliography keys that have been cited
+    cited: Vec<String>,
+    // what to put in front of each heading in turn, when they are numbered
+    heading_numbers: alloc::vec::IntoIter<String>,
+    // the ids of the headings in turn, when they have permalinks
+    heading_slugs: alloc::vec::IntoIter<String>,
+    // where links go, in the order they first show up, for the link appendix
+    links: Vec<String>,
+    // images that were given alt text made from their file name
+    made_up_alt: Vec<String>,
+}
+
+impl<'a> Notes<'a> {
+    fn new(md: &'a [Markdown], options: &HtmlOptions) -> Notes<'a> {
+        let mut notes = Notes::default();
+        if options.number_headings || options.permalinks.is_some() {
+            let toc = toc::toc(md);
+            if options.number_headings {
+                notes.heading_numbers = toc::numbers(&toc).into_iter();
+            }
+            if options.permalinks.is_some() {
+                notes.heading_slugs = toc
+                    .into_iter()
+                    .map(|entry| entry.slug)
+                    .collect::<Vec<String>>()
+                    .into_iter();
+            }
+        }
+        for block in md {
+            if let Markdown::FootnoteDef(label, text) = block {
+                // the first definition of a label wins
+                notes.definitions.entry(label).or_insert(text);
+            }
+        }
+        notes
+    }
+
+    // the number and id of a new reference to `label`, none if it is never defined
+    fn reference(&mut self, label: &str) -> Option<(usize, String)> {
+        if !self.definitions.contains_key(label) {
+            return None;
+        }
+        let number = match self.order.iter().position(|l| l == label) {
+            Some(i) => i + 1,
+            None => {
+                self.order.push(label.to_string());
+                self.order.len()
+            }
+        };
+        let count = self.references.entry(label.to_string()).or_insert(0);
+        *count += 1;
+        Some((number, reference_id(label, *count)))
+    }
 }
 
-fn translate_inline_code(code: String) -> String {
-    format!("<code>{}</code>", code)
+impl Notes<'_> {
+    // the number of a link to `href` in the appendix, the same one each time it is linked to
+    fn link(&mut self, href: String) -> usize {
+        match self.links.iter().position(|link| *link == href) {
+            Some(i) => i + 1,
+            None => {
+                self.links.push(href);
+                self.links.len()
+            }
+        }
+    }
 }
 
-fn translate_link(text: String, url: String) -> String {
-    format!("<a href=\"{}\">{}</a>", url, text)
+fn reference_id(label: &str, count: usize) -> String {
+    if count == 1 {
+        format!("fnref-{}", slugify(label))
+    } else {
+        format!("fnref-{}-{}", slugify(label), count)
+    }
 }
 
-fn translate_image(text: String, url: String) -> String {
-    format!("<img src=\"{}\" alt=\"{}\" />", url, text)
+// `(Doe, 2020; Roe, 2021)` with each work linking to its entry in the references
+fn translate_citation(
+    out: &mut String,
+    keys: &[String],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    let entries = keys
+        .iter()
+        .map(|key| options.bibliography.get(key).ok_or(key))
+        .collect::<Result<Vec<_>, _>>();
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(key) => {
+            if !options.bibliography.is_empty() {
+                tracing::warn!("no bibliography entry for @{}", key);
+            }
+            out.push('[');
+            for (i, key) in keys.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("; ");
+                }
+                write!(out, "@{}", key)?;
+            }
+            out.push(']');
+            return Ok(());
+        }
+    };
+    out.push_str("<cite class=\"citation\">(");
+    for (i, entry) in entries.iter().enumerate() {
+        if !notes.cited.contains(&entry.key) {
+            notes.cited.push(entry.key.clone());
+        }
+        if i > 0 {
+            out.push_str("; ");
+        }
+        write!(
+            out,
+            "<a href=\"#ref-{}\"{}>{}</a>",
+            slugify(&entry.key),
+            role(options, "doc-biblioref"),
+            escape(&entry.citation())
+        )?;
+    }
+    out.push_str(")</cite>");
+    Ok(())
 }
 
-fn translate_list_elements(lines: Vec<MarkdownText>) -> String {
-    lines
+// every cited work, in author order like a bibliography should be
+fn translate_references(out: &mut String, options: &HtmlOptions, notes: &Notes) -> fmt::Result {
+    let mut entries = notes
+        .cited
         .iter()
-        .map(|line| format!("<li>{}</li>", translate_text(line.to_vec())))
-        .collect::<Vec<String>>()
-        .join("")
+        .filter_map(|key| options.bibliography.get(key))
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        return Ok(());
+    }
+    entries.sort_by_key(|entry| entry.sort_key());
+    write!(
+        out,
+        "<section class=\"references\"{}><ul>",
+        role(options, "doc-bibliography")
+    )?;
+    for entry in entries {
+        write!(
+            out,
+            "<li id=\"ref-{}\">{}</li>",
+            slugify(&entry.key),
+            entry.reference()
+        )?;
+    }
+    out.push_str("</ul></section>");
+    Ok(())
 }
 
-fn translate_header(size: usize, text: MarkdownText) -> String {
-    format!("<h{}>{}</h{}>", size, translate_text(text), size)
+fn translate_footnote_ref(
+    out: &mut String,
+    label: &str,
+    number: usize,
+    id: &str,
+    options: &HtmlOptions,
+) -> fmt::Result {
+    write!(
+        out,
+        "<sup class=\"footnote-ref\"><a href=\"#fn-{}\" id=\"{}\"{}>{}</a></sup>",
+        slugify(label),
+        id,
+        role(options, "doc-noteref"),
+        number
+    )
 }
 
-fn translate_unordered_list(lines: Vec<MarkdownText>) -> String {
-    format!("<ul>{}</ul>", translate_list_elements(lines.to_vec()))
+// ` role="doc-noteref"` when the output is meant to be accessible
+fn role(options: &HtmlOptions, role: &str) -> String {
+    if options.accessibility {
+        format!(" role=\"{}\"", role)
+    } else {
+        String::new()
+    }
 }
 
-fn translate_ordered_list(lines: Vec<MarkdownText>) -> String {
-    format!("<ol>{}</ol>", translate_list_elements(lines.to_vec()))
+// the list of notes, each one with a `↩` link back to every place it was referenced
+fn translate_footnotes<'a>(
+    out: &mut String,
+    options: &HtmlOptions,
+    notes: &mut Notes<'a>,
+) -> fmt::Result {
+    if notes.order.is_empty() {
+        return Ok(());
+    }
+    write!(
+        out,
+        "<section class=\"footnotes\"{}><ol>",
+        role(options, "doc-endnotes")
+    )?;
+    // a note can reference another note, which then joins the end of the list
+    let mut i = 0;
+    while i < notes.order.len() {
+        let label = notes.order[i].to_string();
+        let definition: &'a MarkdownText = notes.definitions[label.as_str()];
+        write!(out, "<li id=\"fn-{}\"", slugify(&label))?;
+        // a note is known by the number it is referenced with
+        if options.accessibility {
+            write!(
+                out,
+                " role=\"doc-footnote\" aria-labelledby=\"{}\"",
+                reference_id(&label, 1)
+            )?;
+        }
+        out.push('>');
+        translate_text(out, definition, options, notes)?;
+        for count in 1..=notes.references[&label] {
+            write!(
+                out,
+                " <a href=\"#{}\" class=\"footnote-backref\"{}>↩",
+                reference_id(&label, count),
+                role(options, "doc-backlink")
+            )?;
+            if count > 1 {
+                write!(out, "<sup>{}</sup>", count)?;
+            }
+            out.push_str("</a>");
+        }
+        out.push_str("</li>");
+        i += 1;
+    }
+    out.push_str("</ol></section>");
+    Ok(())
+}
+
+// every link's url, numbered like the `[1]` after its text
+fn translate_link_appendix(out: &mut String, notes: &Notes) -> fmt::Result {
+    if notes.links.is_empty() {
+        return Ok(());
+    }
+    out.push_str("<section class=\"links\"><h2>Links</h2><ol>");
+    for (i, href) in notes.links.iter().enumerate() {
+        write!(
+            out,
+            "<li id=\"link-{}\"><a href=\"{}\">{}</a></li>",
+            i + 1,
+            href,
+            href
+        )?;
+    }
+    out.push_str("</ol></section>");
+    Ok(())
+}
+
+fn translate_boldtext(out: &mut String, boldtext: &str) -> fmt::Result {
+    write!(out, "<b>{}</b>", boldtext)
+}
+
+fn translate_italic(out: &mut String, italic: &str) -> fmt::Result {
+    write!(out, "<i>{}</i>", italic)
+}
+
+fn translate_alert(
+    out: &mut String,
+    alert: Alert,
+    lines: &[MarkdownText],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    write!(
+        out,
+        "<div class=\"{}\"><p class=\"{}\">{}</p>",
+        escape(&options.alert_classes.container(alert)),
+        escape(&options.alert_classes.title(alert)),
+        alert.title()
+    )?;
+    for line in lines {
+        translate_line(out, line, options, notes)?;
+    }
+    out.push_str("</div>");
+    Ok(())
+}
+
+fn translate_inserted(out: &mut String, inserted: &str) -> fmt::Result {
+    write!(out, "<ins>{}</ins>", inserted)
+}
+
+fn translate_inline_code(out: &mut String, code: &str) -> fmt::Result {
+    write!(out, "<code>{}</code>", code)
+}
+
+fn translate_link(out: &mut String, text: &str, url: &str) -> fmt::Result {
+    write!(out, "<a href=\"{}\">{}</a>", url, text)
+}
+
+fn translate_image(
+    out: &mut String,
+    text: &str,
+    url: &str,
+    size: Option<&(u32, u32)>,
+) -> fmt::Result {
+    match size {
+        Some((width, height)) => write!(
+            out,
+            "<img src=\"{}\" alt=\"{}\" width=\"{}\" height=\"{}\" />",
+            url, text, width, height
+        ),
+        None => write!(out, "<img src=\"{}\" alt=\"{}\" />", url, text),
+    }
+}
+
+fn translate_list_elements<'a>(
+    out: &mut String,
+    lines: impl Iterator<Item = &'a ListItem>,
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    for line in lines {
+        out.push_str("<li>");
+        translate_list_item(out, line, options, notes)?;
+        out.push_str("</li>");
+    }
+    Ok(())
+}
+
+// the item's text followed by any lists nested inside of it
+fn translate_list_item(
+    out: &mut String,
+    item: &ListItem,
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    translate_text(out, &item.text, options, notes)?;
+    for child in &item.children {
+        translate_block(out, child, options, notes)?;
+    }
+    Ok(())
+}
+
+fn translate_header(
+    out: &mut String,
+    size: HeadingLevel,
+    text: &[MarkdownInline],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    // paper has nothing to click
+    let permalinks = options.permalinks.as_ref().filter(|_| !options.print);
+    let class = if options.print && size.get() == 1 {
+        PAGE_BREAK
+    } else {
+        ""
+    };
+    let permalink = match (permalinks, notes.heading_slugs.next()) {
+        (Some(permalinks), Some(slug)) => {
+            write!(out, "<h{} id=\"{}\"{}>", size, slug, class)?;
+            Some((permalinks, slug))
+        }
+        _ => {
+            write!(out, "<h{}{}>", size, class)?;
+            None
+        }
+    };
+    if let Some((permalinks, slug)) = &permalink {
+        if permalinks.position == PermalinkPosition::Before {
+            write!(
+                out,
+                "<a class=\"anchor\" href=\"#{}\">{}</a> ",
+                slug, permalinks.symbol
+            )?;
+        }
+    }
+    if let Some(number) = notes.heading_numbers.next() {
+        write!(out, "<span class=\"heading-number\">{}</span> ", number)?;
+    }
+    translate_text(out, text, options, notes)?;
+    if let Some((permalinks, slug)) = &permalink {
+        if permalinks.position == PermalinkPosition::After {
+            write!(
+                out,
+                " <a class=\"anchor\" href=\"#{}\">{}</a>",
+                slug, permalinks.symbol
+            )?;
+        }
+    }
+    write!(out, "</h{}>", size)
+}
+
+fn translate_unordered_list(
+    out: &mut String,
+    lines: &[ListItem],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    out.push_str("<ul>");
+    translate_list_elements(out, lines.iter(), options, notes)?;
+    out.push_str("</ul>");
+    Ok(())
+}
+
+// with `list_values` set the author's numbering survives: a list counting up
+// from somewhere other than 1 gets a `start`, anything else a `value` per item
+fn translate_ordered_list(
+    out: &mut String,
+    lines: &[(usize, ListItem)],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    let first = lines.first().map_or(1, |(number, _)| *number);
+    let counts_up = lines
+        .iter()
+        .enumerate()
+        .all(|(i, (number, _))| first.checked_add(i) == Some(*number));
+    if !options.list_values || (counts_up && first == 1) {
+        out.push_str("<ol>");
+        translate_list_elements(out, lines.iter().map(|(_, line)| line), options, notes)?;
+    } else if counts_up {
+        write!(out, "<ol start=\"{}\">", first)?;
+        translate_list_elements(out, lines.iter().map(|(_, line)| line), options, notes)?;
+    } else {
+        out.push_str("<ol>");
+        for (number, line) in lines {
+            write!(out, "<li value=\"{}\">", number)?;
+            translate_list_item(out, line, options, notes)?;
+            out.push_str("</li>");
+        }
+    }
+    out.push_str("</ol>");
+    Ok(())
+}
+
+fn translate_table(
+    out: &mut String,
+    header: &[MarkdownText],
+    aligns: &[Align],
+    rows: &[Vec<MarkdownText>],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    write!(out, "<table{}><thead>", avoid_break(options))?;
+    translate_table_row(out, "th", header, aligns, options, notes)?;
+    out.push_str("</thead>");
+    if !rows.is_empty() {
+        out.push_str("<tbody>");
+        for cells in rows {
+            translate_table_row(out, "td", cells, aligns, options, notes)?;
+        }
+        out.push_str("</tbody>");
+    }
+    out.push_str("</table>");
+    Ok(())
+}
+
+fn translate_table_row(
+    out: &mut String,
+    tag: &str,
+    cells: &[MarkdownText],
+    aligns: &[Align],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    out.push_str("<tr>");
+    for (cell, align) in cells.iter().zip(aligns) {
+        let align = match align {
+            Align::Default => "",
+            Align::Left => " align=\"left\"",
+            Align::Center => " align=\"center\"",
+            Align::Right => " align=\"right\"",
+        };
+        write!(out, "<{}{}>", tag, align)?;
+        translate_text(out, cell, options, notes)?;
+        write!(out, "</{}>", tag)?;
+    }
+    out.push_str("</tr>");
+    Ok(())
+}
+
+fn is_csv(lang: &str) -> bool {
+    lang == "csv" || lang == "tsv"
+}
+
+// the first record is the header, the values are data so they are escaped
+// rather than read as markdown
+fn translate_csv(
+    out: &mut String,
+    lang: &str,
+    code: &str,
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    let delimiter = if lang == "tsv" { '\t' } else { ',' };
+    let mut records = csv::records(code, delimiter).into_iter().map(|record| {
+        record
+            .iter()
+            .map(|field| MarkdownText(vec![MarkdownInline::Plaintext(escape(field))]))
+            .collect::<Vec<MarkdownText>>()
+    });
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Ok(()),
+    };
+    let rows = records
+        .map(|mut row| {
+            row.resize(header.len(), MarkdownText::new());
+            row
+        })
+        .collect::<Vec<_>>();
+    translate_table(
+        out,
+        &header,
+        &vec![Align::Default; header.len()],
+        &rows,
+        options,
+        notes,
+    )
 }
 
 // fn translate_code(code: MarkdownText) -> String {
 //     format!("<code>{}</code>", translate_text(code))
 // }
 
-fn translate_codeblock(lang: String, code: String) -> String {
-    format!("<pre><code class=\"lang-{}\">{}</code></pre>", lang, code)
+fn translate_codeblock(
+    out: &mut String,
+    lang: &str,
+    code: &str,
+    options: &HtmlOptions,
+) -> fmt::Result {
+    write!(
+        out,
+        "<pre{}><code class=\"{}{}\">{}</code></pre>",
+        avoid_break(options),
+        options.code_class_prefix,
+        lang,
+        code
+    )
 }
 
-fn translate_line(text: MarkdownText) -> String {
-    let line = translate_text(text);
-    if line.len() > 0 {
-        format!("<p>{}</p>", line)
+// classes for a print stylesheet to hang `break-before: page` and
+// `break-inside: avoid` on
+const PAGE_BREAK: &str = " class=\"page-break\"";
+const AVOID_BREAK: &str = " class=\"avoid-break\"";
+
+fn avoid_break(options: &HtmlOptions) -> &'static str {
+    if options.print {
+        AVOID_BREAK
     } else {
-        format!("{}", line)
+        ""
     }
 }
 
-fn translate_text(text: MarkdownText) -> String {
-    text.iter()
-        .map(|part| match part {
-            MarkdownInline::Bold(text) => translate_boldtext(text.to_string()),
-            MarkdownInline::Italic(text) => translate_italic(text.to_string()),
-            MarkdownInline::InlineCode(code) => translate_inline_code(code.to_string()),
-            MarkdownInline::Link(text, url) => translate_link(text.to_string(), url.to_string()),
-            MarkdownInline::Image(text, url) => translate_image(text.to_string(), url.to_string()),
-            MarkdownInline::Plaintext(text) => text.to_string(),
-        })
-        .collect::<Vec<String>>()
-        .join("")
+// prose can't know what a block from a registered parser means, so its text
+// goes out as is, in a div named after the parser for stylesheets to pick up
+fn translate_custom(out: &mut String, name: &str, text: &str) -> fmt::Result {
+    write!(
+        out,
+        "<div class=\"{}\">{}</div>",
+        escape(name),
+        escape(text.trim_end_matches('\n'))
+    )
+}
+
+// an empty line leaves nothing behind, not even the paragraph
+fn translate_line(
+    out: &mut String,
+    text: &[MarkdownInline],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    if options.standalone_images && standalone_image(text) {
+        return translate_text(out, text, options, notes);
+    }
+    let start = out.len();
+    let (text, open) = match paragraph_attributes(text) {
+        Some((text, attributes)) => (Cow::Owned(text), format!("<p{}>", attributes)),
+        None => (Cow::Borrowed(text), String::from("<p>")),
+    };
+    out.push_str(&open);
+    translate_text(out, &text, options, notes)?;
+    if out.len() == start + open.len() {
+        out.truncate(start);
+    } else {
+        out.push_str("</p>");
+    }
+    Ok(())
+}
+
+// a single image with at most some space around it
+fn standalone_image(text: &[MarkdownInline]) -> bool {
+    let mut images = 0;
+    for part in text {
+        match part {
+            MarkdownInline::Image(_, _) => images += 1,
+            MarkdownInline::Plaintext(text) if text.trim().is_empty() => {}
+            _ => return false,
+        }
+    }
+    images == 1
+}
+
+fn translate_text(
+    out: &mut String,
+    text: &[MarkdownInline],
+    options: &HtmlOptions,
+    notes: &mut Notes,
+) -> fmt::Result {
+    for part in text {
+        if let Some(html) = options.overrides.inline(part, options) {
+            out.push_str(&html);
+            continue;
+        }
+        match part {
+            MarkdownInline::Bold(text) => translate_boldtext(out, &options.typeset(text))?,
+            MarkdownInline::Italic(text) => translate_italic(out, &options.typeset(text))?,
+            MarkdownInline::Inserted(text) => translate_inserted(out, &options.typeset(text))?,
+            // code is shown exactly as written
+            MarkdownInline::InlineCode(code) => translate_inline_code(out, code)?,
+            // links within the page stay as they are, there is nothing to print for them
+            MarkdownInline::Link(text, url) if options.link_appendix && !url.starts_with('#') => {
+                let number = notes.link(options.href(&options.substitute_url(url)));
+                write!(
+                    out,
+                    "{}<sup class=\"link-ref\"><a href=\"#link-{}\">[{}]</a></sup>",
+                    options.typeset(text),
+                    number,
+                    number
+                )?
+            }
+            // on paper the url is all there is to follow, unless the text already is it
+            MarkdownInline::Link(text, url) if options.print && !url.starts_with('#') => {
+                let href = options.href(&options.substitute_url(url));
+                let text = options.typeset(text);
+                translate_link(out, &text, &href)?;
+                if text != href {
+                    write!(out, " <span class=\"print-url\">({})</span>", href)?;
+                }
+            }
+            MarkdownInline::Link(text, url) => translate_link(
+                out,
+                &options.typeset(text),
+                &options.href(&options.substitute_url(url)),
+            )?,
+            MarkdownInline::Image(text, url)
+                if options.alt_from_filename && text.trim().is_empty() =>
+            {
+                let alt = options.alt(text, url);
+                if alt != *text {
+                    notes.made_up_alt.push(url.to_string());
+                }
+                translate_image(
+                    out,
+                    &escape(&alt),
+                    &options.src(&options.substitute_url(url)),
+                    options.image_sizes.get(url.as_str()),
+                )?
+            }
+            MarkdownInline::Image(text, url) if options.accessibility && text.trim().is_empty() => {
+                tracing::warn!("image {} has no alt text", url);
+                translate_image(
+                    out,
+                    text,
+                    &options.src(&options.substitute_url(url)),
+                    options.image_sizes.get(url.as_str()),
+                )?
+            }
+            MarkdownInline::Image(text, url) => translate_image(
+                out,
+                &options.typeset(text),
+                &options.src(&options.substitute_url(url)),
+                options.image_sizes.get(url.as_str()),
+            )?,
+            MarkdownInline::Plaintext(text) => out.push_str(&options.typeset(text)),
+            MarkdownInline::FootnoteRef(label) => match notes.reference(label) {
+                Some((number, id)) => translate_footnote_ref(out, label, number, &id, options)?,
+                // a reference to nothing is left as it was written
+                None => write!(out, "[^{}]", label)?,
+            },
+            MarkdownInline::Citation(keys) => translate_citation(out, keys, options, notes)?,
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::{AlertClasses, Permalinks};
+    use crate::Url;
+
+    fn render(f: impl FnOnce(&mut String) -> fmt::Result) -> String {
+        let mut out = String::new();
+        f(&mut out).unwrap();
+        out
+    }
 
     #[test]
     fn test_translate_boldtext() {
         assert_eq!(
-            translate_boldtext(String::from("bold af")),
+            render(|out| translate_boldtext(out, "bold af")),
             String::from("<b>bold af</b>")
         );
     }
@@ -103,7 +1086,7 @@ mod tests {
     #[test]
     fn test_translate_italic() {
         assert_eq!(
-            translate_italic(String::from("italic af")),
+            render(|out| translate_italic(out, "italic af")),
             String::from("<i>italic af</i>")
         );
     }
@@ -111,7 +1094,7 @@ mod tests {
     #[test]
     fn test_translate_inline_code() {
         assert_eq!(
-            translate_inline_code(String::from("code af")),
+            render(|out| translate_inline_code(out, "code af")),
             String::from("<code>code af</code>")
         );
     }
@@ -119,10 +1102,7 @@ mod tests {
     #[test]
     fn test_translate_link() {
         assert_eq!(
-            translate_link(
-                String::from("click me!"),
-                String::from("https://github.com")
-            ),
+            render(|out| translate_link(out, "click me!", "https://github.com")),
             String::from("<a href=\"https://github.com\">click me!</a>")
         );
     }
@@ -130,46 +1110,79 @@ mod tests {
     #[test]
     fn test_translate_image() {
         assert_eq!(
-            translate_image(String::from("alt text"), String::from("https://github.com")),
+            render(|out| translate_image(out, "alt text", "https://github.com", None)),
             String::from("<img src=\"https://github.com\" alt=\"alt text\" />")
         );
     }
 
     #[test]
     fn test_translate_text() {
-        let x = translate_text(vec![
-            MarkdownInline::Plaintext(String::from(
-                "Foobar is a Python library for dealing with word pluralization.",
-            )),
-            MarkdownInline::Bold(String::from("bold")),
-            MarkdownInline::Italic(String::from("italic")),
-            MarkdownInline::InlineCode(String::from("code")),
-            MarkdownInline::Link(String::from("tag"), String::from("https://link.com")),
-            MarkdownInline::Image(String::from("tag"), String::from("https://link.com")),
-            MarkdownInline::Plaintext(String::from(". the end!")),
-        ]);
+        let x = render(|out| {
+            translate_text(
+                out,
+                &[
+                    MarkdownInline::Plaintext(String::from(
+                        "Foobar is a Python library for dealing with word pluralization.",
+                    )),
+                    MarkdownInline::Bold(String::from("bold")),
+                    MarkdownInline::Italic(String::from("italic")),
+                    MarkdownInline::InlineCode(String::from("code")),
+                    MarkdownInline::Link(String::from("tag"), Url::from("https://link.com")),
+                    MarkdownInline::Image(String::from("tag"), Url::from("https://link.com")),
+                    MarkdownInline::Plaintext(String::from(". the end!")),
+                ],
+                &HtmlOptions::default(),
+                &mut Notes::default(),
+            )
+        });
         assert_eq!(x, String::from("Foobar is a Python library for dealing with word pluralization.<b>bold</b><i>italic</i><code>code</code><a href=\"https://link.com\">tag</a><img src=\"https://link.com\" alt=\"tag\" />. the end!"));
-        let x = translate_text(vec![]);
+        let x =
+            render(|out| translate_text(out, &[], &HtmlOptions::default(), &mut Notes::default()));
         assert_eq!(x, String::from(""));
     }
 
     #[test]
     fn test_translate_header() {
         assert_eq!(
-            translate_header(1, vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+            render(|out| translate_header(
+                out,
+                HeadingLevel::H1,
+                &[MarkdownInline::Plaintext(String::from("Foobar"))],
+                &HtmlOptions::default(),
+                &mut Notes::default()
+            )),
             String::from("<h1>Foobar</h1>")
         );
     }
 
+    #[test]
+    fn test_translate_numbered_headings() {
+        let (_, md) = crate::parser::parse_markdown("# Title\n## Part\ntext\n# Next\n").unwrap();
+        let options = HtmlOptions {
+            number_headings: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            String::from("<h1><span class=\"heading-number\">1.</span> Title</h1><h2><span class=\"heading-number\">1.1</span> Part</h2><p>text</p><h1><span class=\"heading-number\">2.</span> Next</h1>")
+        );
+    }
+
     #[test]
     fn test_translate_list_elements() {
         assert_eq!(
-            translate_list_elements(vec![
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-            ]),
+            render(|out| translate_list_elements(
+                out,
+                [
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                ]
+                .iter(),
+                &HtmlOptions::default(),
+                &mut Notes::default()
+            )),
             String::from("<li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li>")
         );
     }
@@ -177,12 +1190,17 @@ mod tests {
     #[test]
     fn test_translate_unordered_list() {
         assert_eq!(
-            translate_unordered_list(vec![
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-            ]),
+            render(|out| translate_unordered_list(
+                out,
+                &[
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                    ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))]),
+                ],
+                &HtmlOptions::default(),
+                &mut Notes::default()
+            )),
             String::from("<ul><li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li></ul>")
         );
     }
@@ -190,31 +1208,88 @@ mod tests {
     #[test]
     fn test_translate_ordered_list() {
         assert_eq!(
-            translate_ordered_list(vec![
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-                vec![MarkdownInline::Plaintext(String::from("Foobar"))],
-            ]),
+            render(|out| translate_ordered_list(
+                out,
+                &[
+                    (
+                        1,
+                        ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))])
+                    ),
+                    (
+                        2,
+                        ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))])
+                    ),
+                    (
+                        3,
+                        ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))])
+                    ),
+                    (
+                        4,
+                        ListItem::from(vec![MarkdownInline::Plaintext(String::from("Foobar"))])
+                    ),
+                ],
+                &HtmlOptions::default(),
+                &mut Notes::default()
+            )),
             String::from("<ol><li>Foobar</li><li>Foobar</li><li>Foobar</li><li>Foobar</li></ol>")
         );
     }
 
+    #[test]
+    fn test_translate_ordered_list_values() {
+        let item = |number: usize| {
+            (
+                number,
+                ListItem::from(vec![MarkdownInline::Plaintext(number.to_string())]),
+            )
+        };
+        let options = HtmlOptions {
+            list_values: true,
+            ..Default::default()
+        };
+        let translate = |lines: &[(usize, ListItem)], options: &HtmlOptions| {
+            render(|out| translate_ordered_list(out, lines, options, &mut Notes::default()))
+        };
+        assert_eq!(
+            translate(&[item(1), item(5), item(9)], &HtmlOptions::default()),
+            String::from("<ol><li>1</li><li>5</li><li>9</li></ol>")
+        );
+        assert_eq!(
+            translate(&[item(1), item(5), item(9)], &options),
+            String::from(
+                "<ol><li value=\"1\">1</li><li value=\"5\">5</li><li value=\"9\">9</li></ol>"
+            )
+        );
+        assert_eq!(
+            translate(&[item(3), item(4)], &options),
+            String::from("<ol start=\"3\"><li>3</li><li>4</li></ol>")
+        );
+        assert_eq!(
+            translate(&[item(1), item(2)], &options),
+            String::from("<ol><li>1</li><li>2</li></ol>")
+        );
+        // numbers as big as they come don't count up past the end
+        assert_eq!(
+            crate::markdown("18446744073709551615. a\n0. b\n"),
+            "<ol><li>a</li><li>b</li></ol>"
+        );
+    }
+
     #[test]
     fn test_translate_codeblock() {
         assert_eq!(
-            translate_codeblock(
-                String::from("python"),
-                String::from(
-                    r#"
+            render(|out| translate_codeblock(
+                out,
+                "python",
+                r#"
 import foobar
 
 foobar.pluralize(\'word\') # returns \'words\'
 foobar.pluralize(\'goose\') # returns \'geese\'
 foobar.singularize(\'phenomena\') # returns \'phenomenon\'
-"#
-                )
-            ),
+"#,
+                &HtmlOptions::default()
+            )),
             String::from(
                 r#"<pre><code class="lang-python">
 import foobar
@@ -230,13 +1305,521 @@ foobar.singularize(\'phenomena\') # returns \'phenomenon\'
     #[test]
     fn test_translate_line() {
         assert_eq!(
-            translate_line(vec![
-                MarkdownInline::Plaintext(String::from("Foobar")),
-                MarkdownInline::Bold(String::from("Foobar")),
-                MarkdownInline::Italic(String::from("Foobar")),
-                MarkdownInline::InlineCode(String::from("Foobar")),
-            ]),
+            render(|out| translate_line(
+                out,
+                &[
+                    MarkdownInline::Plaintext(String::from("Foobar")),
+                    MarkdownInline::Bold(String::from("Foobar")),
+                    MarkdownInline::Italic(String::from("Foobar")),
+                    MarkdownInline::InlineCode(String::from("Foobar")),
+                ],
+                &HtmlOptions::default(),
+                &mut Notes::default()
+            )),
             String::from("<p>Foobar<b>Foobar</b><i>Foobar</i><code>Foobar</code></p>")
         );
+        assert_eq!(
+            render(|out| translate_line(out, &[], &HtmlOptions::default(), &mut Notes::default())),
+            String::new()
+        );
+    }
+
+    #[test]
+    fn test_translate_codeblock_class_prefix() {
+        let options = HtmlOptions {
+            code_class_prefix: String::from("language-"),
+            ..Default::default()
+        };
+        assert_eq!(
+            render(|out| translate_codeblock(out, "rust", "fn main() {}\n", &options)),
+            String::from("<pre><code class=\"language-rust\">fn main() {}\n</code></pre>")
+        );
+    }
+
+    #[test]
+    fn test_translate_variables() {
+        let mut options = HtmlOptions::default();
+        options
+            .variables
+            .insert(String::from("version"), String::from("1.2"));
+        assert_eq!(
+            render(|out| translate_text(
+                out,
+                &[
+                    MarkdownInline::Plaintext(String::from("v{{version}} ")),
+                    MarkdownInline::Link(
+                        String::from("notes"),
+                        Url::from("/releases/{{ version }}.html")
+                    ),
+                    MarkdownInline::InlineCode(String::from("{{version}}")),
+                ],
+                &options,
+                &mut Notes::default()
+            )),
+            String::from("v1.2 <a href=\"/releases/1.2.html\">notes</a><code>{{version}}</code>")
+        );
+    }
+
+    #[test]
+    fn test_translate_overrides() {
+        let mut options = HtmlOptions::default();
+        options
+            .override_inline(|inline, _| match inline {
+                MarkdownInline::Image(alt, url) => Some(format!(
+                    "<img src=\"https://cdn.example.com/{}\" alt=\"{}\" />",
+                    url, alt
+                )),
+                _ => None,
+            })
+            .override_block(|block, _| match block {
+                Markdown::Heading(HeadingLevel::H1, _) => Some(String::from("<header></header>")),
+                _ => None,
+            });
+        let (_, md) =
+            crate::parser::parse_markdown("# Title\n## Sub\n![cat](cat.png) [a](b.md)\n").unwrap();
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<header></header><h2>Sub</h2><p><img src=\"https://cdn.example.com/cat.png\" alt=\"cat\" /> <a href=\"b.md\">a</a></p>"
+        );
+        assert_eq!(options.clone(), options);
+        assert_ne!(options, HtmlOptions::default());
+    }
+
+    #[test]
+    fn test_translate_override_math_fences() {
+        let options = HtmlOptions::builder()
+            .override_math_fences(|tex| match tex.trim() {
+                "x^2" => Ok(String::from(
+                    "<math><msup><mi>x</mi><mn>2</mn></msup></math>",
+                )),
+                _ => Err(String::from("unknown")),
+            })
+            .build();
+        let (_, md) = crate::parser::parse_markdown(
+            "```math\nx^2\n```\n```math\n\\frac\n```\n```rust\nx^2\n```\n",
+        )
+        .unwrap();
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<math><msup><mi>x</mi><mn>2</mn></msup></math><pre><code class=\"lang-math\">\\frac\n</code></pre><pre><code class=\"lang-rust\">x^2\n</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_translate_filters() {
+        let mut options = HtmlOptions::default();
+        options
+            .filter(crate::filters::external_links)
+            .filter(|html| html.replace("<p>", "<p class=\"prose\">"));
+        assert_eq!(
+            translate_with_options(
+                &[Markdown::Line(vec![MarkdownInline::Link(
+                    String::from("x"),
+                    Url::from("https://x.com")
+                )].into())],
+                &options
+            ),
+            "<p class=\"prose\"><a href=\"https://x.com\" target=\"_blank\" rel=\"noopener noreferrer\">x</a></p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_custom() {
+        assert_eq!(
+            translate(&[Markdown::Custom(
+                String::from("note"),
+                String::from(":::note <b>\n")
+            )]),
+            "<div class=\"note\">:::note &lt;b&gt;</div>"
+        );
+    }
+
+    #[test]
+    fn test_translate_streaming() {
+        let (_, md) =
+            crate::parser::parse_markdown("# Hi\none[^1]\ntwo\n\n- three\n\n[^1]: note\n").unwrap();
+        let stream = |options: &HtmlOptions| {
+            let mut chunks = vec![];
+            translate_streaming(&md, options, |chunk| chunks.push(chunk.to_string()));
+            chunks
+        };
+        let options = HtmlOptions {
+            lang: Some(String::from("en")),
+            ..Default::default()
+        };
+        let chunks = stream(&options);
+        assert_eq!(chunks[0], "<div lang=\"en\"><h1>Hi</h1>");
+        assert_eq!(chunks.concat(), translate_with_options(&md, &options));
+        let options = HtmlOptions {
+            hard_breaks: true,
+            ..Default::default()
+        };
+        let chunks = stream(&options);
+        assert!(chunks.contains(&String::from("<p>one<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup><br />two</p>")));
+        assert_eq!(chunks.concat(), translate_with_options(&md, &options));
+        let mut options = HtmlOptions::default();
+        options.filter(|html| html.replace("Hi", "Bye"));
+        assert_eq!(
+            stream(&options),
+            vec![translate_with_options(&md, &options)]
+        );
+    }
+
+    #[test]
+    fn test_translate_image_sizes() {
+        let (_, md) = crate::parser::parse_markdown("![a](img/a.webp) ![b](b.png)\n").unwrap();
+        let options = HtmlOptions::builder()
+            .image_sizes(BTreeMap::from([(String::from("img/a.webp"), (640, 480))]))
+            .build();
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<p><img src=\"img/a.webp\" alt=\"a\" width=\"640\" height=\"480\" /> <img src=\"b.png\" alt=\"b\" /></p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_raw() {
+        let (_, md) = crate::parser::parse_markdown(
+            "```{=html}\n<video src=\"a.mp4\"></video>\n```\n```{=latex}\n\\newpage\n```\n",
+        )
+        .unwrap();
+        assert_eq!(translate(&md), "<video src=\"a.mp4\"></video>\n");
+    }
+
+    #[test]
+    fn test_translate_alert() {
+        let (_, md) =
+            crate::parser::parse_markdown("> [!warning]\n> Mind the **gap**\n>\n> twice\nafter\n")
+                .unwrap();
+        assert_eq!(
+            translate(&md),
+            "<div class=\"markdown-alert markdown-alert-warning\"><p class=\"markdown-alert-title\">Warning</p><p>Mind the <b>gap</b></p><p>twice</p></div><p>after</p>"
+        );
+        let options = HtmlOptions {
+            alert_classes: AlertClasses::mkdocs(),
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md[..1], &options),
+            "<div class=\"admonition warning\"><p class=\"admonition-title\">Warning</p><p>Mind the <b>gap</b></p><p>twice</p></div>"
+        );
+    }
+
+    #[test]
+    fn test_translate_permalinks() {
+        let (_, md) = crate::parser::parse_markdown("# Intro\n## Intro\n").unwrap();
+        let options = HtmlOptions {
+            permalinks: Some(Permalinks::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<h1 id=\"intro\">Intro <a class=\"anchor\" href=\"#intro\">¶</a></h1><h2 id=\"intro-1\">Intro <a class=\"anchor\" href=\"#intro-1\">¶</a></h2>"
+        );
+        let options = HtmlOptions {
+            number_headings: true,
+            permalinks: Some(Permalinks {
+                symbol: String::from("#"),
+                position: PermalinkPosition::Before,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md[..1], &options),
+            "<h1 id=\"intro\"><a class=\"anchor\" href=\"#intro\">#</a> <span class=\"heading-number\">1.</span> Intro</h1>"
+        );
+        assert_eq!(translate(&md[..1]), "<h1>Intro</h1>");
+    }
+
+    #[test]
+    fn test_translate_data_attributes() {
+        let (_, md) = crate::parser::parse_markdown(
+            "step {data-step=2 data-Hint=\"a <b> c\" dir=rtl}\n\nbad {data-x/onclick=1 data-ok=y}\n\nnot {data-=1}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            translate(&md),
+            "<p dir=\"rtl\" data-step=\"2\" data-hint=\"a &lt;b&gt; c\">step</p><p data-ok=\"y\">bad</p><p>not {data-=1}</p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_direction() {
+        let (_, md) =
+            crate::parser::parse_markdown("مرحبا\n\nshalom {dir=rtl lang=he}\n\nplain {x}\n")
+                .unwrap();
+        let options = HtmlOptions {
+            lang: Some(String::from("ar")),
+            dir: Some(String::from("rtl")),
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<div lang=\"ar\" dir=\"rtl\"><p>مرحبا</p><p lang=\"he\" dir=\"rtl\">shalom</p><p>plain {x}</p></div>"
+        );
+        let options = HtmlOptions {
+            lang: Some(String::from("\"><script>")),
+            dir: Some(String::from("sideways")),
+            ..Default::default()
+        };
+        assert_eq!(translate_with_options(&md[..1], &options), "<p>مرحبا</p>");
+    }
+
+    #[test]
+    fn test_translate_entities() {
+        let (_, md) = crate::parser::parse_markdown("Q&amp;A &copy; &#x1F600;\n").unwrap();
+        let options = HtmlOptions {
+            typographer: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<p>Q&amp;A &copy; &#x1F600;</p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_typographer() {
+        let (_, md) =
+            crate::parser::parse_markdown("(c) *Acme(tm)* -> `a -> b` [1/2](x->y.md)\n").unwrap();
+        let options = HtmlOptions {
+            typographer: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<p>© <i>Acme™</i> → <code>a -> b</code> <a href=\"x-%3Ey.md\">½</a></p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_urls() {
+        let options = HtmlOptions {
+            base_url: Some(String::from("https://example.com/docs")),
+            md_links_to_html: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render(|out| translate_text(out,
+                &[
+                    MarkdownInline::Link(String::from("a"), Url::from("guide/intro.md#setup")),
+                    MarkdownInline::Link(String::from("b"), Url::from("/about.md")),
+                    MarkdownInline::Link(String::from("c"), Url::from("#top")),
+                    MarkdownInline::Link(String::from("d"), Url::from("https://rust-lang.org/x.md")),
+                    MarkdownInline::Image(String::from("e"), Url::from("img/diagram.md")),
+                ],
+                &options,
+                &mut Notes::default()
+            )),
+            String::from("<a href=\"https://example.com/docs/guide/intro.html#setup\">a</a><a href=\"https://example.com/about.html\">b</a><a href=\"#top\">c</a><a href=\"https://rust-lang.org/x.md\">d</a><img src=\"https://example.com/docs/img/diagram.md\" alt=\"e\" />")
+        );
+    }
+
+    #[test]
+    fn test_translate_footnotes() {
+        let (_, md) = crate::parser::parse_markdown(
+            "one[^b] two[^a] again[^b] missing[^x]\n\n[^a]: first\n[^b]: second\n[^c]: unused\n",
+        )
+        .unwrap();
+        assert_eq!(
+            translate(&md),
+            String::from("<p>one<sup class=\"footnote-ref\"><a href=\"#fn-b\" id=\"fnref-b\">1</a></sup> two<sup class=\"footnote-ref\"><a href=\"#fn-a\" id=\"fnref-a\">2</a></sup> again<sup class=\"footnote-ref\"><a href=\"#fn-b\" id=\"fnref-b-2\">1</a></sup> missing[^x]</p><section class=\"footnotes\"><ol><li id=\"fn-b\">second <a href=\"#fnref-b\" class=\"footnote-backref\">↩</a> <a href=\"#fnref-b-2\" class=\"footnote-backref\">↩<sup>2</sup></a></li><li id=\"fn-a\">first <a href=\"#fnref-a\" class=\"footnote-backref\">↩</a></li></ol></section>")
+        );
+    }
+
+    #[test]
+    fn test_translate_hard_breaks() {
+        let (_, md) =
+            crate::parser::parse_markdown("one\n**two**\nthree\n\nfour\n# Five\nsix\n").unwrap();
+        let options = HtmlOptions {
+            hard_breaks: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            String::from("<p>one<br /><b>two</b><br />three</p><p>four</p><h1>Five</h1><p>six</p>")
+        );
+    }
+
+    #[test]
+    fn test_translate_standalone_images() {
+        let (_, md) = crate::parser::parse_markdown(
+            "![a](a.png)\ntext ![b](b.png)\n![c](c.png) ![d](d.png)\n",
+        )
+        .unwrap();
+        let options = HtmlOptions {
+            standalone_images: true,
+            hard_breaks: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<img src=\"a.png\" alt=\"a\" /><p>text <img src=\"b.png\" alt=\"b\" /><br /><img src=\"c.png\" alt=\"c\" /> <img src=\"d.png\" alt=\"d\" /></p>"
+        );
+        assert_eq!(
+            translate_with_options(&md[..1], &HtmlOptions::default()),
+            "<p><img src=\"a.png\" alt=\"a\" /></p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_print() {
+        let (_, md) = crate::parser::parse_markdown(
+            "# One\nsee [docs](https://e.com/docs) or [top](#top) at [https://e.com](https://e.com)\n```sh\nls\n```\n| a |\n|---|\n| b |\n## Two\n",
+        )
+        .unwrap();
+        let options = HtmlOptions {
+            print: true,
+            permalinks: Some(Permalinks::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            "<h1 class=\"page-break\">One</h1><p>see <a href=\"https://e.com/docs\">docs</a> <span class=\"print-url\">(https://e.com/docs)</span> or <a href=\"#top\">top</a> at <a href=\"https://e.com\">https://e.com</a></p><pre class=\"avoid-break\"><code class=\"lang-sh\">ls\n</code></pre><table class=\"avoid-break\"><thead><tr><th>a</th></tr></thead><tbody><tr><td>b</td></tr></tbody></table><h2>Two</h2>"
+        );
+    }
+
+    #[test]
+    fn test_translate_link_appendix() {
+        let (_, md) = crate::parser::parse_markdown(
+            "see [a](https://a.com) and [top](#top) or [a again](https://a.com)[^n]\n\n[^n]: from [b](b.md)\n",
+        )
+        .unwrap();
+        let options = HtmlOptions {
+            link_appendix: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            String::from("<p>see a<sup class=\"link-ref\"><a href=\"#link-1\">[1]</a></sup> and <a href=\"#top\">top</a> or a again<sup class=\"link-ref\"><a href=\"#link-1\">[1]</a></sup><sup class=\"footnote-ref\"><a href=\"#fn-n\" id=\"fnref-n\">1</a></sup></p><section class=\"footnotes\"><ol><li id=\"fn-n\">from b<sup class=\"link-ref\"><a href=\"#link-2\">[2]</a></sup> <a href=\"#fnref-n\" class=\"footnote-backref\">↩</a></li></ol></section><section class=\"links\"><h2>Links</h2><ol><li id=\"link-1\"><a href=\"https://a.com\">https://a.com</a></li><li id=\"link-2\"><a href=\"b.md\">b.md</a></li></ol></section>")
+        );
+    }
+
+    #[test]
+    fn test_translate_accessible_footnotes() {
+        let (_, md) = crate::parser::parse_markdown("one[^a] two[^a]\n\n[^a]: note\n").unwrap();
+        let options = HtmlOptions {
+            accessibility: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            String::from("<p>one<sup class=\"footnote-ref\"><a href=\"#fn-a\" id=\"fnref-a\" role=\"doc-noteref\">1</a></sup> two<sup class=\"footnote-ref\"><a href=\"#fn-a\" id=\"fnref-a-2\" role=\"doc-noteref\">1</a></sup></p><section class=\"footnotes\" role=\"doc-endnotes\"><ol><li id=\"fn-a\" role=\"doc-footnote\" aria-labelledby=\"fnref-a\">note <a href=\"#fnref-a\" class=\"footnote-backref\" role=\"doc-backlink\">↩</a> <a href=\"#fnref-a-2\" class=\"footnote-backref\" role=\"doc-backlink\">↩<sup>2</sup></a></li></ol></section>")
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_translate_parallel() {
+        let src = "# Title\n- *one*\n- two\n\nsome `code` and [a link](x.md)\n".repeat(200);
+        let (_, md) = crate::parser::parse_markdown(&src).unwrap();
+        assert!(md.len() > PARALLEL_CHUNK);
+        let options = HtmlOptions::default();
+        assert_eq!(
+            translate_parallel(&md, &options),
+            translate_with_options(&md, &options)
+        );
+
+        let (_, md) =
+            crate::parser::parse_markdown(&format!("{}note[^a]\n[^a]: hi\n", src)).unwrap();
+        assert_eq!(
+            translate_parallel(&md, &options),
+            translate_with_options(&md, &options)
+        );
+    }
+
+    #[test]
+    fn test_translate_source_positions() {
+        let src = "---\ntitle: x\n---\n# Hi\n\n- a\n  - b\n```rust\nx\n```\nsee[^n]\n[^n]: note\n";
+        let (blocks, spans): (Vec<Markdown>, Vec<Span>) =
+            crate::document::spanned_blocks(src).into_iter().unzip();
+        assert_eq!(
+            translate_with_source_positions(&blocks, &spans, src, &HtmlOptions::default()),
+            String::from("<h1 data-sourcepos=\"4:1\">Hi</h1><ul data-sourcepos=\"6:1\"><li>a<ul><li>b</li></ul></li></ul><pre data-sourcepos=\"8:1\"><code class=\"lang-rust\">x\n</code></pre><p data-sourcepos=\"11:1\">see<sup class=\"footnote-ref\"><a href=\"#fn-n\" id=\"fnref-n\">1</a></sup></p><section class=\"footnotes\"><ol><li id=\"fn-n\">note <a href=\"#fnref-n\" class=\"footnote-backref\">↩</a></li></ol></section>")
+        );
+    }
+
+    #[test]
+    fn test_translate_source_positions_raw() {
+        let src = "```{=html}\nhello world\n```\n```{=html}\n<div class=\"x\">y</div>\n```\ntext\n";
+        let (blocks, spans): (Vec<Markdown>, Vec<Span>) =
+            crate::document::spanned_blocks(src).into_iter().unzip();
+        assert_eq!(
+            translate_with_source_positions(&blocks, &spans, src, &HtmlOptions::default()),
+            "hello world\n<div class=\"x\">y</div>\n<p data-sourcepos=\"7:1\">text</p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_source_positions_missing_spans() {
+        let src = "# Hi\n";
+        let (mut blocks, spans): (Vec<Markdown>, Vec<Span>) =
+            crate::document::spanned_blocks(src).into_iter().unzip();
+        blocks.push(Markdown::Line(MarkdownText(vec![
+            MarkdownInline::Plaintext(String::from("more")),
+        ])));
+        assert_eq!(
+            translate_with_source_positions(&blocks, &spans, src, &HtmlOptions::default()),
+            "<h1 data-sourcepos=\"1:1\">Hi</h1><p>more</p>"
+        );
+    }
+
+    #[test]
+    fn test_translate_nested_lists() {
+        let (_, md) = crate::parser::parse_markdown("- a\n  1. one\n  2. two\n- b\n").unwrap();
+        assert_eq!(
+            translate(&md),
+            String::from("<ul><li>a<ol><li>one</li><li>two</li></ol></li><li>b</li></ul>")
+        );
+    }
+
+    #[test]
+    fn test_translate_table() {
+        let (_, md) =
+            crate::parser::parse_markdown("| a | b |\n|---|:-:|\n| **x** | `y` |\n").unwrap();
+        assert_eq!(
+            translate(&md),
+            String::from("<table><thead><tr><th>a</th><th align=\"center\">b</th></tr></thead><tbody><tr><td><b>x</b></td><td align=\"center\"><code>y</code></td></tr></tbody></table>")
+        );
+    }
+
+    #[test]
+    fn test_translate_csv() {
+        let md = vec![Markdown::Codeblock(
+            String::from("csv"),
+            String::from("name,score\n<hg>,\"1,5\"\nmax\n"),
+        )];
+        assert_eq!(
+            translate(&md),
+            String::from(
+                "<pre><code class=\"lang-csv\">name,score\n<hg>,\"1,5\"\nmax\n</code></pre>"
+            )
+        );
+        let options = HtmlOptions {
+            csv_tables: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            translate_with_options(&md, &options),
+            String::from("<table><thead><tr><th>name</th><th>score</th></tr></thead><tbody><tr><td>&lt;hg&gt;</td><td>1,5</td></tr><tr><td>max</td><td></td></tr></tbody></table>")
+        );
+    }
+
+    #[test]
+    fn test_translate_citations() {
+        let options = HtmlOptions {
+            bibliography: crate::bibliography::parse_bibtex(
+            "@book{roe, author = {Roe, Ann}, title = {Later}, year = 2021}\n@book{doe, author = {Doe, Jo}, title = {Earlier}, year = 2020}\n",
+        )
+        .unwrap(),
+            ..Default::default()
+        };
+        let (_, md) =
+            crate::parser::parse_markdown("as shown [@roe; @doe] and [@roe] but not [@nope]\n")
+                .unwrap();
+        assert_eq!(
+            translate_with_options(&md, &options),
+            String::from("<p>as shown <cite class=\"citation\">(<a href=\"#ref-roe\">Roe, 2021</a>; <a href=\"#ref-doe\">Doe, 2020</a>)</cite> and <cite class=\"citation\">(<a href=\"#ref-roe\">Roe, 2021</a>)</cite> but not [@nope]</p><section class=\"references\"><ul><li id=\"ref-doe\">Doe, Jo (2020). Earlier.</li><li id=\"ref-roe\">Roe, Ann (2021). Later.</li></ul></section>")
+        );
     }
 }
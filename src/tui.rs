@@ -0,0 +1,269 @@
+use crate::entities::decode;
+use crate::raw_format;
+use crate::Align;
+use crate::HeadingLevel;
+use crate::ListItem;
+use crate::Markdown;
+use crate::MarkdownInline;
+use crate::MarkdownText;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Paragraph, Widget, Wrap};
+
+// parsed blocks drawn with styled text, wrapped to the width of the area
+// and scrolled down by however many lines the app asks for
+#[derive(Clone, Copy, Debug)]
+pub struct MarkdownWidget<'a> {
+    blocks: &'a [Markdown],
+    scroll: u16,
+}
+
+impl<'a> MarkdownWidget<'a> {
+    pub fn new(blocks: &'a [Markdown]) -> MarkdownWidget<'a> {
+        MarkdownWidget { blocks, scroll: 0 }
+    }
+
+    // how many wrapped lines to skip from the top
+    pub fn scroll(self, scroll: u16) -> MarkdownWidget<'a> {
+        MarkdownWidget { scroll, ..self }
+    }
+}
+
+impl Widget for MarkdownWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Paragraph::new(text(self.blocks))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0))
+            .render(area, buf);
+    }
+}
+
+// the styled lines the widget draws, for apps that want to lay them out themselves
+pub fn text(md: &[Markdown]) -> Text<'static> {
+    let mut lines = vec![];
+    let mut blocks = md.iter().peekable();
+    while let Some(block) = blocks.next() {
+        block_lines(&mut lines, block, "");
+        // the parser reads the newline after a closing fence as an empty line
+        if let (Markdown::Codeblock(_, _), Some(Markdown::Line(text))) = (block, blocks.peek()) {
+            if text.is_empty() {
+                blocks.next();
+            }
+        }
+    }
+    Text::from(lines)
+}
+
+fn code_style() -> Style {
+    Style::new().fg(Color::Yellow)
+}
+
+fn marker_style() -> Style {
+    Style::new().add_modifier(Modifier::DIM)
+}
+
+fn block_lines(lines: &mut Vec<Line<'static>>, block: &Markdown, indent: &str) {
+    match block {
+        Markdown::Heading(level, text) => {
+            let mut style = Style::new().bold();
+            if *level == HeadingLevel::H1 {
+                style = style.underlined();
+            }
+            let mut spans = vec![Span::styled(
+                format!("{} ", "#".repeat(level.get())),
+                marker_style(),
+            )];
+            spans.extend(spans_of(text));
+            lines.push(Line::from(spans).style(style));
+        }
+        Markdown::UnorderedList(items) => {
+            for item in items {
+                item_lines(lines, item, indent, String::from("• "));
+            }
+        }
+        Markdown::OrderedList(items) => {
+            for (number, item) in items {
+                item_lines(lines, item, indent, format!("{}. ", number));
+            }
+        }
+        // raw html or latex has nothing to show in a terminal
+        Markdown::Codeblock(lang, _) if raw_format(lang).is_some() => {}
+        Markdown::Codeblock(_, code) => {
+            for line in code.lines() {
+                lines.push(Line::styled(format!("{}  {}", indent, line), code_style()));
+            }
+        }
+        Markdown::Line(text) => lines.push(Line::from(spans_of(text))),
+        Markdown::FootnoteDef(label, text) => {
+            let mut spans = vec![Span::styled(format!("[^{}]: ", label), marker_style())];
+            spans.extend(spans_of(text));
+            lines.push(Line::from(spans));
+        }
+        Markdown::Table(header, aligns, rows) => table_lines(lines, header, aligns, rows),
+        Markdown::Custom(_, text) => {
+            for line in text.lines() {
+                lines.push(Line::raw(format!("{}{}", indent, line)));
+            }
+        }
+        Markdown::Alert(alert, text) => {
+            lines.push(Line::from(vec![
+                Span::styled(format!("{}▌ ", indent), marker_style()),
+                Span::raw(alert.title()).bold(),
+            ]));
+            for line in text {
+                let mut spans = vec![Span::styled(format!("{}▌ ", indent), marker_style())];
+                spans.extend(spans_of(line));
+                lines.push(Line::from(spans));
+            }
+        }
+    }
+}
+
+// the marker in front of the text, nested lists lined up underneath it
+fn item_lines(lines: &mut Vec<Line<'static>>, item: &ListItem, indent: &str, marker: String) {
+    let nested = format!("{}{}", indent, " ".repeat(marker.chars().count()));
+    let mut spans = vec![
+        Span::raw(indent.to_string()),
+        Span::styled(marker, marker_style()),
+    ];
+    spans.extend(spans_of(&item.text));
+    lines.push(Line::from(spans));
+    for child in &item.children {
+        block_lines(lines, child, &nested);
+    }
+}
+
+// columns padded out to their widest cell, `│` between them
+fn table_lines(
+    lines: &mut Vec<Line<'static>>,
+    header: &[MarkdownText],
+    aligns: &[Align],
+    rows: &[Vec<MarkdownText>],
+) {
+    let cells = |row: &[MarkdownText]| {
+        row.iter()
+            .map(|cell| Line::from(spans_of(cell)))
+            .collect::<Vec<Line>>()
+    };
+    let header = cells(header);
+    let rows = rows.iter().map(|row| cells(row)).collect::<Vec<_>>();
+    let widths = (0..aligns.len())
+        .map(|column| {
+            core::iter::once(&header)
+                .chain(&rows)
+                .filter_map(|row| row.get(column))
+                .map(Line::width)
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<usize>>();
+
+    let row_line = |row: Vec<Line<'static>>| {
+        let mut spans = vec![];
+        for (column, (cell, align)) in row.into_iter().zip(aligns).enumerate() {
+            if column > 0 {
+                spans.push(Span::styled(" │ ", marker_style()));
+            }
+            let padding = widths[column] - cell.width();
+            let before = match align {
+                Align::Right => padding,
+                Align::Center => padding / 2,
+                Align::Default | Align::Left => 0,
+            };
+            spans.push(Span::raw(" ".repeat(before)));
+            spans.extend(cell.spans);
+            spans.push(Span::raw(" ".repeat(padding - before)));
+        }
+        Line::from(spans)
+    };
+    lines.push(row_line(header).bold());
+    lines.push(Line::styled(
+        widths
+            .iter()
+            .map(|width| "─".repeat(*width))
+            .collect::<Vec<String>>()
+            .join("─┼─"),
+        marker_style(),
+    ));
+    for row in rows {
+        lines.push(row_line(row));
+    }
+}
+
+fn spans_of(text: &[MarkdownInline]) -> Vec<Span<'static>> {
+    text.iter()
+        .map(|part| match part {
+            MarkdownInline::Plaintext(text) => Span::raw(decode(text).into_owned()),
+            MarkdownInline::Bold(text) => Span::raw(decode(text).into_owned()).bold(),
+            MarkdownInline::Italic(text) => Span::raw(decode(text).into_owned()).italic(),
+            MarkdownInline::Inserted(text) => Span::raw(decode(text).into_owned()).underlined(),
+            MarkdownInline::InlineCode(code) => Span::styled(code.clone(), code_style()),
+            MarkdownInline::Link(text, _) => Span::raw(decode(text).into_owned())
+                .fg(Color::Blue)
+                .underlined(),
+            MarkdownInline::Image(alt, _) => {
+                Span::styled(format!("[image: {}]", alt), marker_style()).italic()
+            }
+            MarkdownInline::FootnoteRef(label) => {
+                Span::styled(format!("[^{}]", label), marker_style())
+            }
+            MarkdownInline::Citation(keys) => {
+                Span::styled(format!("[@{}]", keys.join("; @")), marker_style())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_markdown;
+
+    fn plain(text: &Text) -> Vec<String> {
+        text.lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_text() {
+        let (_, md) = parse_markdown(
+            "# Hi *there*\n- one\n  1. two\n\n| a | long |\n|--:|:-:|\n| bb | c |\n```\nlet x;\n```\n",
+        )
+        .unwrap();
+        let text = text(&md);
+        assert_eq!(
+            plain(&text),
+            vec![
+                "# Hi there",
+                "• one",
+                "  1. two",
+                "",
+                " a │ long",
+                "───┼─────",
+                "bb │  c  ",
+                "  let x;",
+            ]
+        );
+        assert!(text.lines[0].style.add_modifier.contains(Modifier::BOLD));
+        assert!(text.lines[0].spans[2]
+            .style
+            .add_modifier
+            .contains(Modifier::ITALIC));
+    }
+
+    #[test]
+    fn test_render() {
+        let (_, md) = parse_markdown("one two three\nfour\n").unwrap();
+        let mut buf = Buffer::empty(Rect::new(0, 0, 8, 2));
+        MarkdownWidget::new(&md)
+            .scroll(1)
+            .render(buf.area, &mut buf);
+        let rows = buf
+            .content
+            .chunks(8)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<String>>();
+        assert_eq!(rows, vec!["three   ", "four    "]);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+// longer sequences come first so `<->` is not read as `<` and `->`
+const REPLACEMENTS: [(&str, &str); 20] = [
+    ("(c)", "©"),
+    ("(C)", "©"),
+    ("(r)", "®"),
+    ("(R)", "®"),
+    ("(tm)", "™"),
+    ("(TM)", "™"),
+    ("...", "…"),
+    ("<->", "↔"),
+    ("<=>", "⇔"),
+    ("->", "→"),
+    ("<-", "←"),
+    ("=>", "⇒"),
+    ("<=", "≤"),
+    (">=", "≥"),
+    ("!=", "≠"),
+    ("+-", "±"),
+    ("1/2", "½"),
+    ("1/4", "¼"),
+    ("3/4", "¾"),
+    ("1/3", "⅓"),
+];
+
+// swaps what people type for the characters they mean, `(c) 2021 -> now`
+// becomes `© 2021 → now`. text without anything to replace is handed back as it is
+pub fn typeset(text: &str) -> Cow<'_, str> {
+    if !text.contains(['(', '.', '<', '>', '-', '=', '!', '+', '/']) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    'text: while let Some(c) = rest.chars().next() {
+        for (from, to) in REPLACEMENTS.iter() {
+            if rest.starts_with(from) && fits(out.chars().last(), from, &rest[from.len()..]) {
+                out.push_str(to);
+                rest = &rest[from.len()..];
+                continue 'text;
+            }
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if out == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+// keeps html comments, longer numbers and dates like `11/2/2021` as they are
+fn fits(before: Option<char>, from: &str, after: &str) -> bool {
+    let number = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '/');
+    match from {
+        "->" => before != Some('-'),
+        "<-" => !after.starts_with('-'),
+        "1/2" | "1/4" | "3/4" | "1/3" => !number(before) && !number(after.chars().next()),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typeset() {
+        assert_eq!(
+            typeset("(c) 2021 Prose(tm) -> 1/2 done... x <= y"),
+            "© 2021 Prose™ → ½ done… x ≤ y"
+        );
+        assert_eq!(
+            typeset("<!--more--> on 11/2/2021"),
+            "<!--more--> on 11/2/2021"
+        );
+        assert!(matches!(typeset("plain words"), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::prelude::*;
+use alloc::borrow::Cow;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+}
+
+// text as older tools save it: utf-16 is only recognised by its byte order
+// mark, and anything else that is not utf-8 is taken to be latin-1, which
+// every byte is valid in
+pub fn decode(bytes: &[u8]) -> (Cow<'_, str>, Encoding) {
+    match bytes {
+        [0xff, 0xfe, rest @ ..] => (
+            Cow::Owned(utf16(rest, u16::from_le_bytes)),
+            Encoding::Utf16Le,
+        ),
+        [0xfe, 0xff, rest @ ..] => (
+            Cow::Owned(utf16(rest, u16::from_be_bytes)),
+            Encoding::Utf16Be,
+        ),
+        _ => match core::str::from_utf8(bytes) {
+            Ok(text) => (Cow::Borrowed(text), Encoding::Utf8),
+            Err(_) => (
+                Cow::Owned(bytes.iter().map(|&byte| char::from(byte)).collect()),
+                Encoding::Latin1,
+            ),
+        },
+    }
+}
+
+// broken surrogates turn into `�` rather than failing the whole file
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    char::decode_utf16(bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]])))
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+// editors on windows like to start files with a byte order mark, which would
+// otherwise end up in the first heading and its slug
+pub fn strip_bom(src: &str) -> &str {
+    src.strip_prefix('\u{feff}').unwrap_or(src)
+}
+
+// composes `e` followed by a combining accent into `é`, so text typed on
+// different systems reads, links and searches the same. text already in
+// nfc, which is nearly all of it, is handed back as it is
+#[cfg(feature = "unicode-normalization")]
+pub fn nfc(src: &str) -> Cow<'_, str> {
+    use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+    match is_nfc_quick(src.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(src),
+        _ => Cow::Owned(src.nfc().collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            decode("# Café\n".as_bytes()),
+            (Cow::Borrowed("# Café\n"), Encoding::Utf8)
+        );
+        assert_eq!(
+            decode(b"# Caf\xe9\n"),
+            (Cow::Owned(String::from("# Café\n")), Encoding::Latin1)
+        );
+        assert_eq!(
+            decode(b"\xff\xfe#\x00 \x00\xe9\x00"),
+            (Cow::Owned(String::from("# é")), Encoding::Utf16Le)
+        );
+        assert_eq!(
+            decode(b"\xfe\xff\x00#\xd8\x00"),
+            (Cow::Owned(String::from("#\u{fffd}")), Encoding::Utf16Be)
+        );
+    }
+
+    #[test]
+    fn test_strip_bom() {
+        assert_eq!(strip_bom("\u{feff}# Hi\n"), "# Hi\n");
+        assert_eq!(strip_bom("# Hi\n"), "# Hi\n");
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn test_nfc() {
+        assert_eq!(nfc("Cafe\u{301}"), "Café");
+        assert!(matches!(nfc("Café"), Cow::Borrowed(_)));
+    }
+}
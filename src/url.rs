@@ -0,0 +1,224 @@
+use crate::prelude::*;
+use alloc::borrow::Cow;
+use core::fmt;
+use core::ops::Deref;
+
+// where a link or image points. it is percent-encoded when it is parsed, so
+// `my notes.md` goes out as `my%20notes.md`, and remembers how it was written
+// for the formatter and for finding it in the source again
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Url {
+    encoded: String,
+    written: String,
+}
+
+impl Url {
+    pub fn new(written: &str) -> Url {
+        let written = written.trim();
+        Url {
+            encoded: encode(written).into_owned(),
+            written: written.to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.encoded
+    }
+
+    pub fn as_written(&self) -> &str {
+        &self.written
+    }
+
+    // with `%20` and friends turned back into what they stand for, a path to
+    // look up on disk
+    pub fn decoded(&self) -> Cow<'_, str> {
+        decode(&self.encoded)
+    }
+
+    // `https` in `https://example.com`, none for relative urls
+    pub fn scheme(&self) -> Option<&str> {
+        let colon = self.encoded.find(':')?;
+        let scheme = &self.encoded[..colon];
+        let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+        valid.then_some(scheme)
+    }
+
+    // `example.com` in `https://user@example.com:8080/a`, also for protocol
+    // relative `//example.com/a`
+    pub fn host(&self) -> Option<&str> {
+        let rest = match self.scheme() {
+            Some(scheme) => &self.encoded[scheme.len() + 1..],
+            None => &self.encoded,
+        };
+        let authority = rest.strip_prefix("//")?;
+        let authority = &authority[..authority.find(['/', '?', '#']).unwrap_or(authority.len())];
+        let host = authority.rsplit('@').next().unwrap_or(authority);
+        let host = match host.rfind(':') {
+            Some(colon) if !host.ends_with(']') => &host[..colon],
+            _ => host,
+        };
+        (!host.is_empty()).then_some(host)
+    }
+
+    // what comes after the `#`
+    pub fn fragment(&self) -> Option<&str> {
+        self.encoded.split_once('#').map(|(_, fragment)| fragment)
+    }
+
+    // the last part of the path, decoded: `my cat.png` in `img/my%20cat.png?v=2`.
+    // none for urls that end in a `/` or are data of their own, `data:`
+    pub fn file_name(&self) -> Option<Cow<'_, str>> {
+        if self.scheme() == Some("data") {
+            return None;
+        }
+        let path = &self.encoded[..self.encoded.find(['?', '#']).unwrap_or(self.encoded.len())];
+        let path = match self.host() {
+            Some(host) => &path[path.find(host).map_or(0, |at| at + host.len())..],
+            None => path,
+        };
+        let name = path.rsplit('/').next().unwrap_or(path);
+        (!name.is_empty()).then(|| decode(name))
+    }
+
+    // anything with a scheme (https:, mailto:) or a protocol relative `//host` leaves the site
+    pub fn is_external(&self) -> bool {
+        self.scheme().is_some() || self.encoded.starts_with("//")
+    }
+}
+
+impl Deref for Url {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.encoded
+    }
+}
+
+impl From<&str> for Url {
+    fn from(written: &str) -> Url {
+        Url::new(written)
+    }
+}
+
+impl From<String> for Url {
+    fn from(written: String) -> Url {
+        Url::new(&written)
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.encoded)
+    }
+}
+
+impl PartialEq<str> for Url {
+    fn eq(&self, other: &str) -> bool {
+        self.encoded == other
+    }
+}
+
+impl PartialEq<&str> for Url {
+    fn eq(&self, other: &&str) -> bool {
+        self.encoded == *other
+    }
+}
+
+// serialized as the encoded url, the way it ends up in html
+#[cfg(feature = "serde")]
+impl serde::Serialize for Url {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.encoded)
+    }
+}
+
+// spaces, quotes, angle brackets and anything outside of ascii can't appear
+// in a url as they are. `%` is left alone so urls that are already encoded
+// aren't encoded twice, and so are `{{variables}}`
+fn encode(url: &str) -> Cow<'_, str> {
+    let unsafe_byte = |b: u8| b <= b' ' || b >= 0x7f || b"\"<>\\^`|".contains(&b);
+    if !url.bytes().any(unsafe_byte) {
+        return Cow::Borrowed(url);
+    }
+    let mut out = String::with_capacity(url.len() + 8);
+    for b in url.bytes() {
+        if unsafe_byte(b) {
+            out.push_str(&format!("%{:02X}", b));
+        } else {
+            out.push(b as char);
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn decode(url: &str) -> Cow<'_, str> {
+    if !url.contains('%') {
+        return Cow::Borrowed(url);
+    }
+    let bytes = url.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| core::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    match String::from_utf8(out) {
+        Ok(decoded) => Cow::Owned(decoded),
+        Err(_) => Cow::Borrowed(url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let url = Url::new(" my notes/café.md ");
+        assert_eq!(url.as_str(), "my%20notes/caf%C3%A9.md");
+        assert_eq!(url.as_written(), "my notes/café.md");
+        assert_eq!(url.decoded(), "my notes/café.md");
+        assert_eq!(Url::new("a%20b.md?x={{v}}").as_str(), "a%20b.md?x={{v}}");
+    }
+
+    #[test]
+    fn test_parts() {
+        let url = Url::new("https://me@example.com:8080/a?b#c");
+        assert_eq!(url.scheme(), Some("https"));
+        assert_eq!(url.host(), Some("example.com"));
+        assert_eq!(url.fragment(), Some("c"));
+        assert!(url.is_external());
+
+        let url = Url::new("//cdn.example.com/x.png");
+        assert_eq!((url.scheme(), url.host()), (None, Some("cdn.example.com")));
+        assert!(url.is_external());
+
+        let url = Url::new("notes.md#usage");
+        assert_eq!((url.scheme(), url.host()), (None, None));
+        assert_eq!(url.fragment(), Some("usage"));
+        assert!(!url.is_external());
+        assert_eq!(Url::new("mailto:me@example.com").host(), None);
+
+        assert_eq!(
+            Url::new("img/my cat.png?v=2").file_name().as_deref(),
+            Some("my cat.png")
+        );
+        assert_eq!(Url::new("https://example.com").file_name(), None);
+        assert_eq!(Url::new("data:image/png;base64,AAAA").file_name(), None);
+    }
+}
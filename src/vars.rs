@@ -0,0 +1,73 @@
+use crate::frontmatter::FrontMatter;
+use crate::prelude::*;
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+
+// values for `{{name}}` placeholders in a document
+pub type Variables = BTreeMap<String, String>;
+
+// swaps every `{{ name }}` that has a value, placeholders nobody
+// filled in are left as they are so the gap is easy to spot.
+// text without any placeholders is handed back as is
+pub fn substitute<'a>(text: &'a str, vars: &Variables) -> Cow<'a, str> {
+    if vars.is_empty() || !text.contains("{{") {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        out.push_str(&rest[..start]);
+        match vars.get(rest[start + 2..end].trim()) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+// every front matter field becomes a variable, laid over `defaults`
+pub fn with_front_matter(defaults: &Variables, front_matter: &FrontMatter) -> Variables {
+    let mut vars = defaults.clone();
+    for (key, value) in front_matter.iter() {
+        vars.insert(key.clone(), value.to_string());
+    }
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter;
+
+    #[test]
+    fn test_substitute() {
+        let vars = Variables::from([
+            (String::from("product"), String::from("Prose")),
+            (String::from("version"), String::from("0.1.3")),
+        ]);
+        assert_eq!(
+            substitute("{{product}} {{ version }} and {{ nope }} {{ open", &vars),
+            "Prose 0.1.3 and {{ nope }} {{ open"
+        );
+    }
+
+    #[test]
+    fn test_with_front_matter() {
+        let defaults = Variables::from([
+            (String::from("product"), String::from("Prose")),
+            (String::from("version"), String::from("0.1.3")),
+        ]);
+        let (front_matter, _) = frontmatter::split("---\nversion: 2\ntags: [a, b]\n---\n");
+        let vars = with_front_matter(&defaults, &front_matter);
+        assert_eq!(vars["product"], "Prose");
+        assert_eq!(vars["version"], "2");
+        assert_eq!(vars["tags"], "a, b");
+    }
+}
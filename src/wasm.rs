@@ -0,0 +1,181 @@
+use crate::filters;
+use crate::frontmatter;
+use crate::markdown_with_options;
+use crate::options::{AlertClasses, HtmlOptions, PermalinkPosition, Permalinks, ProseOptions};
+use crate::parser;
+use crate::vars::Variables;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+// the options javascript hands `render`, every field can be left out
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RenderOptions {
+    pub code_class_prefix: Option<String>,
+    pub base_url: Option<String>,
+    pub md_links_to_html: bool,
+    pub variables: Variables,
+    pub number_headings: bool,
+    pub list_values: bool,
+    pub csv_tables: bool,
+    pub lang: Option<String>,
+    pub dir: Option<String>,
+    pub accessibility: bool,
+    pub typographer: bool,
+    pub link_appendix: bool,
+    pub alt_from_filename: bool,
+    pub hard_breaks: bool,
+    pub standalone_images: bool,
+    pub print: bool,
+    pub permalinks: bool,
+    pub permalink_symbol: Option<String>,
+    // `"before"` or `"after"`
+    pub permalink_position: Option<String>,
+    // `"github"` or `"mkdocs"`
+    pub alert_classes: Option<String>,
+    // names of built in filters, `["external-links"]`
+    pub filters: Vec<String>,
+}
+
+// names prose doesn't know are refused, like the python binding and
+// `prose.toml` do, rather than quietly doing nothing
+impl TryFrom<RenderOptions> for HtmlOptions {
+    type Error = String;
+
+    fn try_from(options: RenderOptions) -> Result<HtmlOptions, String> {
+        let defaults = HtmlOptions::default();
+        let mut html = HtmlOptions {
+            code_class_prefix: options
+                .code_class_prefix
+                .unwrap_or(defaults.code_class_prefix),
+            base_url: options.base_url,
+            md_links_to_html: options.md_links_to_html,
+            variables: options.variables,
+            number_headings: options.number_headings,
+            list_values: options.list_values,
+            csv_tables: options.csv_tables,
+            lang: options.lang,
+            dir: options.dir,
+            accessibility: options.accessibility,
+            typographer: options.typographer,
+            link_appendix: options.link_appendix,
+            alt_from_filename: options.alt_from_filename,
+            hard_breaks: options.hard_breaks,
+            standalone_images: options.standalone_images,
+            print: options.print,
+            ..defaults
+        };
+        if let Some(name) = options.alert_classes {
+            html.alert_classes = AlertClasses::named(&name)
+                .ok_or_else(|| format!("unknown alert classes '{}'", name))?;
+        }
+        if options.permalinks {
+            let defaults = Permalinks::default();
+            html.permalinks = Some(Permalinks {
+                symbol: options.permalink_symbol.unwrap_or(defaults.symbol),
+                position: match options.permalink_position {
+                    Some(name) => PermalinkPosition::named(&name)
+                        .ok_or_else(|| format!("unknown permalink position '{}'", name))?,
+                    None => defaults.position,
+                },
+            });
+        }
+        for name in options.filters {
+            let filter =
+                filters::named(&name).ok_or_else(|| format!("unknown filter '{}'", name))?;
+            html.filter(filter);
+        }
+        Ok(html)
+    }
+}
+
+// `render("# Hi", { numberHeadings: true })`, like `markdown` it renders as
+// much as parsed. options that are not an object, or name an alert class,
+// permalink position or filter prose doesn't know, throw
+#[wasm_bindgen]
+pub fn render(md: &str, options: JsValue) -> String {
+    let options = if options.is_undefined() || options.is_null() {
+        RenderOptions::default()
+    } else {
+        match serde_wasm_bindgen::from_value::<RenderOptions>(options) {
+            Ok(options) => options,
+            Err(e) => wasm_bindgen::throw_str(&format!("invalid options: {}", e)),
+        }
+    };
+    let html = match HtmlOptions::try_from(options) {
+        Ok(html) => html,
+        Err(e) => wasm_bindgen::throw_str(&format!("invalid options: {}", e)),
+    };
+    let options = ProseOptions {
+        html,
+        ..ProseOptions::default()
+    };
+    markdown_with_options(md, &options).unwrap_or_default()
+}
+
+// the blocks as plain objects, `{ type: "Heading", value: [1, [...]] }`
+#[wasm_bindgen]
+pub fn parse(md: &str) -> JsValue {
+    let (_, md) = frontmatter::split(md);
+    let blocks = parser::parse_markdown(md)
+        .map(|(_, blocks)| blocks)
+        .unwrap_or_default();
+    match serde_wasm_bindgen::to_value(&blocks) {
+        Ok(value) => value,
+        Err(e) => wasm_bindgen::throw_str(&e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HeadingLevel;
+    use crate::Markdown;
+    use crate::MarkdownInline;
+
+    #[test]
+    fn test_render_options() {
+        let options: RenderOptions = serde_json::from_str(
+            r#"{"numberHeadings": true, "baseUrl": "https://example.com/", "variables": {"name": "prose"}}"#,
+        )
+        .unwrap();
+        let options = HtmlOptions::try_from(options).unwrap();
+        assert!(options.number_headings);
+        assert_eq!(options.base_url.as_deref(), Some("https://example.com/"));
+        assert_eq!(
+            options.variables.get("name").map(String::as_str),
+            Some("prose")
+        );
+        assert_eq!(options.code_class_prefix, "lang-");
+        assert_eq!(
+            HtmlOptions::try_from(RenderOptions::default()),
+            Ok(HtmlOptions::default())
+        );
+        let typo = RenderOptions {
+            filters: vec![String::from("external-link")],
+            ..RenderOptions::default()
+        };
+        assert_eq!(
+            HtmlOptions::try_from(typo),
+            Err(String::from("unknown filter 'external-link'"))
+        );
+        let typo = RenderOptions {
+            alert_classes: Some(String::from("gitlab")),
+            ..RenderOptions::default()
+        };
+        assert!(HtmlOptions::try_from(typo).is_err());
+    }
+
+    #[test]
+    fn test_serialize_blocks() {
+        let block = Markdown::Heading(
+            HeadingLevel::H1,
+            vec![MarkdownInline::Bold(String::from("Hi"))].into(),
+        );
+        assert_eq!(
+            serde_json::to_string(&block).unwrap(),
+            r#"{"type":"Heading","value":[1,[{"type":"Bold","value":"Hi"}]]}"#
+        );
+    }
+}